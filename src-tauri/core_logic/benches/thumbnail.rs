@@ -0,0 +1,70 @@
+// 大きな JPEG のサムネイル生成のベンチマーク
+//
+// 全体をデコードしてからリサイズする場合と、縮小デコードの高速経路を使う場合を比較する。
+// 実行: cargo bench -p core_logic --bench thumbnail
+
+use core_logic::decode::{open_image, open_image_to_fit};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::imageops::FilterType;
+use image::{Rgb, RgbImage};
+use std::path::{Path, PathBuf};
+
+/// サムネイルのサイズ
+const THUMBNAIL_SIZE: u32 = 200;
+
+/// ベンチマーク用の画像（約 12 / 40 メガピクセル）
+const FIXTURES: [(u32, u32); 2] = [(4240, 2832), (7744, 5184)];
+
+/// グラデーションと細かい模様を持つ JPEG を作成する（単色だとデコードが速すぎるため）
+fn create_fixture(dir: &Path, (width, height): (u32, u32)) -> PathBuf {
+    let path = dir.join(format!("{}x{}.jpg", width, height));
+    if !path.exists() {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                ((x ^ y) & 0xFF) as u8,
+            ])
+        })
+        .save(&path)
+        .unwrap();
+    }
+    path
+}
+
+fn bench_thumbnail(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join("core_logic_thumbnail_bench");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut group = c.benchmark_group("jpeg_thumbnail");
+    group.sample_size(10);
+    for size in FIXTURES {
+        let path = create_fixture(&dir, size);
+        let label = format!("{}x{}", size.0, size.1);
+
+        group.bench_with_input(BenchmarkId::new("full_decode", &label), &path, |b, path| {
+            b.iter(|| {
+                open_image(path).unwrap().resize(
+                    THUMBNAIL_SIZE,
+                    THUMBNAIL_SIZE,
+                    FilterType::Lanczos3,
+                )
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("scaled_decode", &label),
+            &path,
+            |b, path| {
+                b.iter(|| {
+                    open_image_to_fit(path, THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                        .unwrap()
+                        .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_thumbnail);
+criterion_main!(benches);
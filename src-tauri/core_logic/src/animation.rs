@@ -0,0 +1,214 @@
+// アニメーション画像（GIF / WebP / APNG）の情報取得
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::metadata::LoopCount;
+use image::{AnimationDecoder, Frames, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// アニメーション画像の情報
+///
+/// アニメーションでない画像は1フレーム（表示時間0）として扱う。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationInfo {
+    pub width: u32,
+    pub height: u32,
+    pub frame_count: usize,
+    /// 各フレームの表示時間（ミリ秒）
+    pub frame_delays_ms: Vec<u32>,
+    /// ループ回数（`None` は無限ループ）
+    pub loop_count: Option<u32>,
+}
+
+impl AnimationInfo {
+    /// アニメーションするか（2フレーム以上）
+    pub fn is_animated(&self) -> bool {
+        self.frame_count > 1
+    }
+
+    /// 静止画の情報を作成
+    fn still(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frame_count: 1,
+            frame_delays_ms: vec![0],
+            loop_count: Some(1),
+        }
+    }
+
+    /// フレームを走査して情報を作成
+    fn from_frames(
+        (width, height): (u32, u32),
+        loop_count: LoopCount,
+        frames: Frames<'_>,
+    ) -> image::ImageResult<Self> {
+        let frame_delays_ms = frames
+            .map(|frame| {
+                let (numer, denom) = frame?.delay().numer_denom_ms();
+                Ok(numer.checked_div(denom).unwrap_or(0))
+            })
+            .collect::<image::ImageResult<Vec<u32>>>()?;
+
+        Ok(Self {
+            width,
+            height,
+            frame_count: frame_delays_ms.len(),
+            frame_delays_ms,
+            loop_count: match loop_count {
+                LoopCount::Infinite => None,
+                LoopCount::Finite(count) => Some(count.get()),
+            },
+        })
+    }
+}
+
+/// 画像のフレーム数と各フレームの表示時間を取得
+///
+/// 形式はファイルの内容から判定する（拡張子は参照しない）。
+/// GIF・WebP・APNG 以外の形式は静止画として扱う。
+pub fn read_animation_info<P: AsRef<Path>>(image_path: P) -> Result<AnimationInfo, String> {
+    let image_path = image_path.as_ref();
+    read_animation_info_inner(image_path).map_err(|e| {
+        format!(
+            "Failed to read animation info of '{}': {}",
+            image_path.display(),
+            e
+        )
+    })
+}
+
+fn read_animation_info_inner(image_path: &Path) -> image::ImageResult<AnimationInfo> {
+    let format = ImageReader::open(image_path)?
+        .with_guessed_format()?
+        .format();
+    let reader = BufReader::new(File::open(image_path)?);
+
+    match format {
+        Some(ImageFormat::Gif) => {
+            let decoder = GifDecoder::new(reader)?;
+            let dimensions = decoder.dimensions();
+            let loop_count = decoder.loop_count();
+            AnimationInfo::from_frames(dimensions, loop_count, decoder.into_frames())
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader)?;
+            let dimensions = decoder.dimensions();
+            if !decoder.has_animation() {
+                return Ok(AnimationInfo::still(dimensions.0, dimensions.1));
+            }
+            let loop_count = decoder.loop_count();
+            AnimationInfo::from_frames(dimensions, loop_count, decoder.into_frames())
+        }
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader)?;
+            let dimensions = decoder.dimensions();
+            if !decoder.is_apng()? {
+                return Ok(AnimationInfo::still(dimensions.0, dimensions.1));
+            }
+            let decoder = decoder.apng()?;
+            let loop_count = decoder.loop_count();
+            AnimationInfo::from_frames(dimensions, loop_count, decoder.into_frames())
+        }
+        _ => {
+            let (width, height) = crate::decode::image_dimensions(image_path)?;
+            Ok(AnimationInfo::still(width, height))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame, RgbaImage};
+
+    fn create_animated_gif(path: &Path, delays_ms: &[u32]) {
+        let mut encoder = GifEncoder::new(File::create(path).unwrap());
+        encoder.set_repeat(Repeat::Infinite).unwrap();
+        for (i, delay) in delays_ms.iter().enumerate() {
+            let color = image::Rgba([(i * 80) as u8, 0, 0, 255]);
+            let frame = Frame::from_parts(
+                RgbaImage::from_pixel(8, 6, color),
+                0,
+                0,
+                Delay::from_numer_denom_ms(*delay, 1),
+            );
+            encoder.encode_frame(frame).unwrap();
+        }
+    }
+
+    fn create_apng(path: &Path, frames: u32) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), 4, 4);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_animated(frames, 2).unwrap();
+        encoder.set_frame_delay(1, 20).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for _ in 0..frames {
+            writer.write_image_data(&[255; 4 * 4 * 4]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_animated_gif_info() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("anim.gif");
+        create_animated_gif(&path, &[100, 200, 50]);
+
+        let info = read_animation_info(&path).unwrap();
+        assert!(info.is_animated());
+        assert_eq!((info.width, info.height), (8, 6));
+        assert_eq!(info.frame_count, 3);
+        assert_eq!(info.frame_delays_ms, vec![100, 200, 50]);
+        assert_eq!(info.loop_count, None);
+    }
+
+    #[test]
+    fn test_apng_info() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("anim.png");
+        create_apng(&path, 2);
+
+        let info = read_animation_info(&path).unwrap();
+        assert_eq!(info.frame_count, 2);
+        assert_eq!(info.frame_delays_ms, vec![50, 50]);
+        assert_eq!(info.loop_count, Some(2));
+    }
+
+    #[test]
+    fn test_still_image_info() {
+        let temp = TempTestDir::new_random();
+        let png = temp.path().join("still.png");
+        image::RgbImage::new(5, 3).save(&png).unwrap();
+        let webp = temp.path().join("still.webp");
+        image::RgbImage::new(5, 3).save(&webp).unwrap();
+
+        for path in [png, webp] {
+            let info = read_animation_info(&path).unwrap();
+            assert!(!info.is_animated());
+            assert_eq!((info.width, info.height), (5, 3));
+            assert_eq!(info.frame_delays_ms, vec![0]);
+        }
+    }
+
+    #[test]
+    fn test_detects_format_from_content() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("misnamed.jpg");
+        create_animated_gif(&path, &[10, 10]);
+
+        assert_eq!(read_animation_info(&path).unwrap().frame_count, 2);
+    }
+
+    #[test]
+    fn test_error_for_missing_file() {
+        assert!(read_animation_info("/nonexistent/anim.gif").is_err());
+    }
+}
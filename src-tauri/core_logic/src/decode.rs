@@ -0,0 +1,407 @@
+// 画像ファイルのデコード
+//
+// サムネイル生成などで画像を開く処理はこのモジュールを経由する。
+// 使うデコーダーは形式のレジストリ（[`crate::format`]）で決める。
+// image クレートが扱えない形式のうち、AVIF / HEIF は cargo feature で有効化したデコーダーで開き、
+// カメラ RAW は埋め込みの JPEG プレビューを開き、SVG はラスタライズする。
+// サムネイル用に大きな JPEG を開く場合は、EXIF サムネイルや DCT の縮小デコードで全体のデコードを省く。
+// 開いた画像には EXIF の向き（Orientation）を適用し、ICC プロファイルが埋め込まれていれば sRGB に変換する。
+
+mod color;
+#[cfg(any(feature = "avif", feature = "heif"))]
+mod heif;
+mod jpeg;
+mod multi_page;
+mod raw;
+mod svg;
+
+#[cfg(test)]
+pub(crate) use color::tests::linear_rgb_profile;
+pub use multi_page::{count_tiff_pages, for_each_tiff_page, for_each_tiff_page_with_limits};
+#[cfg(test)]
+pub(crate) use raw::tests::create_raw_fixture;
+#[cfg(test)]
+pub(crate) use tests::{create_oriented_jpeg, write_bmp_header};
+
+use crate::format::FormatDecoder;
+#[cfg(not(any(feature = "avif", feature = "heif")))]
+use image::error::{ImageError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Limits};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 画像の情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    /// 向きを適用した表示上の幅
+    pub width: u32,
+    /// 向きを適用した表示上の高さ
+    pub height: u32,
+    /// EXIF の Orientation の値（1〜8。1 は回転なし）
+    pub orientation: u8,
+}
+
+/// 画像を開く（アニメーション画像は最初のフレーム）
+///
+/// EXIF の向きを適用し、表示される向きの画像を返す。
+/// ICC プロファイルが埋め込まれている場合は sRGB に変換する。
+/// デコードの上限は image クレートの既定の上限（[`Limits::default`]）を使う。
+pub fn open_image<P: AsRef<Path>>(path: P) -> ImageResult<DynamicImage> {
+    open_image_with_limits(path, &Limits::default())
+}
+
+/// `limits` を上限として画像を開く
+///
+/// 上限を超える画像は、デコードする前に `ImageError::Limits` を返す。
+/// HEIF と SVG は、デコード後の画像（8ビット RGBA 換算）の大きさを上限と比べる。
+pub fn open_image_with_limits<P: AsRef<Path>>(
+    path: P,
+    limits: &Limits,
+) -> ImageResult<DynamicImage> {
+    let path = path.as_ref();
+    match decoder_for(path) {
+        FormatDecoder::Standard => {
+            let mut reader = ImageReader::open(path)?;
+            reader.limits(limits.clone());
+            let mut decoder = reader.into_decoder()?;
+            // into_decoder は出力の大きさを上限と比べないため、ImageReader::decode と同じく先に確かめる
+            limits.clone().reserve(decoder.total_bytes())?;
+            let orientation = decoder.orientation()?;
+            let icc_profile = decoder.icc_profile()?;
+            let mut img = DynamicImage::from_decoder(decoder)?;
+            if let Some(icc_profile) = icc_profile {
+                img = color::convert_to_srgb(img, &icc_profile);
+            }
+            img.apply_orientation(orientation);
+            Ok(img)
+        }
+        #[cfg(any(feature = "avif", feature = "heif"))]
+        FormatDecoder::Heif => heif::decode(path, limits),
+        #[cfg(not(any(feature = "avif", feature = "heif")))]
+        FormatDecoder::Heif => Err(heif_disabled()),
+        FormatDecoder::RawPreview => raw::decode(path, limits),
+        FormatDecoder::Svg => svg::decode(path, limits),
+    }
+}
+
+/// `max_width` x `max_height` に収める前提で画像を開く（サムネイル生成用）
+///
+/// ベクター画像（SVG）は収まるサイズで直接描画する。
+/// 大きな JPEG は EXIF サムネイルか縮小デコードで、収まるサイズ以上のできるだけ小さな画像を返す。
+/// その他の形式は [`open_image`] と同じ。
+pub fn open_image_to_fit<P: AsRef<Path>>(
+    path: P,
+    max_width: u32,
+    max_height: u32,
+) -> ImageResult<DynamicImage> {
+    open_image_to_fit_with_limits(path, max_width, max_height, &Limits::default())
+}
+
+/// `limits` を上限として、`max_width` x `max_height` に収める前提で画像を開く
+///
+/// 縮小デコードする JPEG は縮小後の大きさ、全体をデコードする場合は元の大きさを上限と比べる。
+pub fn open_image_to_fit_with_limits<P: AsRef<Path>>(
+    path: P,
+    max_width: u32,
+    max_height: u32,
+    limits: &Limits,
+) -> ImageResult<DynamicImage> {
+    let path = path.as_ref();
+    match decoder_for(path) {
+        FormatDecoder::Svg => svg::decode_to_fit(path, max_width, max_height, limits),
+        FormatDecoder::Standard if ImageFormat::from_path(path).ok() == Some(ImageFormat::Jpeg) => {
+            match jpeg::decode_to_fit(path, max_width, max_height) {
+                Ok(Some(img)) => Ok(img),
+                Ok(None) => open_image_with_limits(path, limits),
+                // jpeg-decoder が扱えない JPEG でも image クレートなら開ける場合がある
+                Err(e) => {
+                    log::debug!("Falling back to full decode of {}: {}", path.display(), e);
+                    open_image_with_limits(path, limits)
+                }
+            }
+        }
+        _ => open_image_with_limits(path, limits),
+    }
+}
+
+/// 画像の向き（EXIF の Orientation）を取得（記録がない場合は回転なし）
+pub fn image_orientation<P: AsRef<Path>>(path: P) -> ImageResult<Orientation> {
+    let path = path.as_ref();
+    match decoder_for(path) {
+        FormatDecoder::Standard => ImageReader::open(path)?.into_decoder()?.orientation(),
+        FormatDecoder::RawPreview => raw::orientation(path),
+        // HEIF はデコード時に向きが適用され、SVG は向きを持たない
+        FormatDecoder::Heif | FormatDecoder::Svg => Ok(Orientation::NoTransforms),
+    }
+}
+
+/// 画像全体をデコードせずに、表示上の寸法と向きを取得
+pub fn read_image_info<P: AsRef<Path>>(path: P) -> ImageResult<ImageInfo> {
+    let path = path.as_ref();
+    let (width, height) = image_dimensions(path)?;
+    let orientation = image_orientation(path)?;
+    let (width, height) = if is_transposed(orientation) {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    Ok(ImageInfo {
+        width,
+        height,
+        orientation: orientation.to_exif(),
+    })
+}
+
+/// 画像全体をデコードせずに寸法を取得（向きを適用する前の、保存されている寸法）
+pub fn image_dimensions<P: AsRef<Path>>(path: P) -> ImageResult<(u32, u32)> {
+    let path = path.as_ref();
+    match decoder_for(path) {
+        FormatDecoder::Standard => image::image_dimensions(path),
+        #[cfg(any(feature = "avif", feature = "heif"))]
+        FormatDecoder::Heif => heif::dimensions(path),
+        #[cfg(not(any(feature = "avif", feature = "heif")))]
+        FormatDecoder::Heif => Err(heif_disabled()),
+        FormatDecoder::RawPreview => raw::dimensions(path),
+        FormatDecoder::Svg => svg::dimensions(path),
+    }
+}
+
+/// カメラ RAW に埋め込まれた JPEG プレビューを取り出す（表示用）
+pub fn extract_raw_preview<P: AsRef<Path>>(path: P) -> ImageResult<Vec<u8>> {
+    raw::extract_preview(path.as_ref())
+}
+
+/// カメラ RAW の JPEG プレビューをキャッシュディレクトリに書き出し、そのパスを返す
+///
+/// 書き出し済みでソースより新しい場合は書き出しを省略する。
+pub fn write_raw_preview<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    cache_dir: Q,
+) -> ImageResult<PathBuf> {
+    let path = path.as_ref();
+    let preview_path = cache_dir
+        .as_ref()
+        .join(format!("{}.jpg", crate::utils::hash_path(&path)));
+
+    let is_fresh = match (fs::metadata(path), fs::metadata(&preview_path)) {
+        (Ok(source), Ok(preview)) => match (source.modified(), preview.modified()) {
+            (Ok(source), Ok(preview)) => preview >= source,
+            _ => false,
+        },
+        _ => false,
+    };
+    if !is_fresh {
+        let preview = extract_raw_preview(path)?;
+        fs::create_dir_all(cache_dir.as_ref())?;
+        fs::write(&preview_path, preview)?;
+    }
+    Ok(preview_path)
+}
+
+/// デコード後の画像（1ピクセル `bytes_per_pixel` バイト）が `limits` に収まるかを確かめる
+///
+/// image クレートの上限を自前のデコーダーにも適用するために使う。
+fn check_limits(limits: &Limits, width: u32, height: u32, bytes_per_pixel: u64) -> ImageResult<()> {
+    limits.check_dimensions(width, height)?;
+    limits
+        .clone()
+        .reserve(u64::from(width) * u64::from(height) * bytes_per_pixel)
+}
+
+/// 向きを適用すると縦横が入れ替わるか
+fn is_transposed(orientation: Orientation) -> bool {
+    matches!(
+        orientation,
+        Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH
+    )
+}
+
+/// レジストリから画像を開くデコーダーを決める（未登録の拡張子は image クレートで開く）
+fn decoder_for(path: &Path) -> FormatDecoder {
+    crate::format::registry()
+        .decoder(path)
+        .unwrap_or(FormatDecoder::Standard)
+}
+
+#[cfg(not(any(feature = "avif", feature = "heif")))]
+fn heif_disabled() -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Name("HEIF".to_string()),
+        UnsupportedErrorKind::Format(ImageFormatHint::Name("HEIF".to_string())),
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ExtendedColorType, ImageEncoder, Rgb, RgbImage};
+
+    /// EXIF の Orientation を記録した JPEG を作成する（左半分が赤、右半分が青）
+    pub(crate) fn create_oriented_jpeg(path: &Path, (width, height): (u32, u32), orientation: u16) {
+        // リトルエンディアンの TIFF ヘッダーと、Orientation（SHORT）だけを持つ IFD0
+        let mut exif = b"II\x2A\0\x08\0\0\0\x01\0".to_vec();
+        exif.extend(0x0112u16.to_le_bytes());
+        exif.extend(3u16.to_le_bytes());
+        exif.extend(1u32.to_le_bytes());
+        exif.extend(orientation.to_le_bytes());
+        exif.extend([0; 2 + 4]);
+
+        let img = RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+        let mut encoder = JpegEncoder::new(fs::File::create(path).unwrap());
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .write_image(img.as_raw(), width, height, ExtendedColorType::Rgb8)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_open_image_and_dimensions() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("image.png");
+        image::RgbImage::new(7, 3).save(&path).unwrap();
+
+        assert_eq!(image_dimensions(&path).unwrap(), (7, 3));
+        assert_eq!(open_image(&path).unwrap().width(), 7);
+        assert!(open_image(temp.path().join("missing.png")).is_err());
+    }
+
+    /// 画素データを持たない 24bit BMP（ヘッダーだけで、デコードは画素の読み込みで失敗する）
+    pub(crate) fn write_bmp_header(path: &Path, width: i32, height: i32) {
+        let mut bmp = b"BM".to_vec();
+        bmp.extend(54u32.to_le_bytes()); // ファイルサイズ
+        bmp.extend([0; 4]);
+        bmp.extend(54u32.to_le_bytes()); // 画素データの位置
+        bmp.extend(40u32.to_le_bytes()); // BITMAPINFOHEADER
+        bmp.extend(width.to_le_bytes());
+        bmp.extend(height.to_le_bytes());
+        bmp.extend(1u16.to_le_bytes());
+        bmp.extend(24u16.to_le_bytes());
+        bmp.extend([0; 24]);
+        fs::write(path, bmp).unwrap();
+    }
+
+    #[test]
+    fn test_open_image_with_limits() {
+        let temp = TempTestDir::new_random();
+        // 14000x14000 の RGB（約 560MiB）は image クレートの既定の上限（512MiB）を超える
+        let large = temp.path().join("large.bmp");
+        write_bmp_header(&large, 14000, 14000);
+        assert!(matches!(
+            open_image(&large),
+            Err(image::ImageError::Limits(_))
+        ));
+
+        // 上限を引き上げると画素の読み込みまで進む（画素データがないため、上限以外の理由で失敗する）
+        let mut limits = Limits::no_limits();
+        limits.max_alloc = Some(1024 * 1024 * 1024);
+        let error = open_image_with_limits(&large, &limits).unwrap_err();
+        assert!(!matches!(error, image::ImageError::Limits(_)), "{}", error);
+        let error = open_image_to_fit_with_limits(&large, 200, 200, &limits).unwrap_err();
+        assert!(!matches!(error, image::ImageError::Limits(_)), "{}", error);
+
+        // 既定より小さい上限も適用される
+        let small = temp.path().join("small.png");
+        RgbImage::new(100, 100).save(&small).unwrap();
+        limits.max_alloc = Some(1000);
+        assert!(matches!(
+            open_image_with_limits(&small, &limits),
+            Err(image::ImageError::Limits(_))
+        ));
+
+        // image クレート以外でデコードする形式（カメラ RAW・SVG）にも適用される
+        let raw = temp.path().join("photo.CR2");
+        create_raw_fixture(&raw, false, (30, 20));
+        let svg = temp.path().join("icon.svg");
+        std::fs::write(
+            &svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="20"/>"#,
+        )
+        .unwrap();
+        for path in [&raw, &svg] {
+            assert!(matches!(
+                open_image_with_limits(path, &limits),
+                Err(image::ImageError::Limits(_))
+            ));
+            assert!(open_image(path).is_ok());
+        }
+        assert!(matches!(
+            open_image_to_fit_with_limits(&svg, 64, 64, &limits),
+            Err(image::ImageError::Limits(_))
+        ));
+    }
+
+    #[test]
+    fn test_applies_exif_orientation() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("portrait.jpg");
+        // 90度回転（時計回り）して表示する
+        create_oriented_jpeg(&path, (40, 20), 6);
+
+        assert_eq!(image_orientation(&path).unwrap(), Orientation::Rotate90);
+        assert_eq!(image_dimensions(&path).unwrap(), (40, 20));
+        assert_eq!(
+            read_image_info(&path).unwrap(),
+            ImageInfo {
+                width: 20,
+                height: 40,
+                orientation: 6,
+            }
+        );
+
+        let img = open_image(&path).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (20, 40));
+        // 元の左半分（赤）が上になる
+        assert!(img.get_pixel(10, 5).0[0] > 200);
+        assert!(img.get_pixel(10, 35).0[2] > 200);
+
+        // 向きの記録がない画像はそのまま
+        let plain = temp.path().join("plain.png");
+        image::RgbImage::new(7, 3).save(&plain).unwrap();
+        assert_eq!(read_image_info(&plain).unwrap().orientation, 1);
+    }
+
+    #[test]
+    fn test_open_svg_to_fit() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("icon.svg");
+        std::fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="8"/>"#,
+        )
+        .unwrap();
+
+        assert_eq!(image_dimensions(&path).unwrap(), (16, 8));
+        assert_eq!(open_image(&path).unwrap().width(), 16);
+        assert_eq!(open_image_to_fit(&path, 64, 64).unwrap().width(), 64);
+    }
+
+    #[test]
+    fn test_open_raw_preview() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("photo.CR2");
+        create_raw_fixture(&path, false, (30, 20));
+
+        assert_eq!(image_dimensions(&path).unwrap(), (30, 20));
+        assert_eq!(open_image(&path).unwrap().height(), 20);
+
+        let cache_dir = temp.path().join("previews");
+        let preview_path = write_raw_preview(&path, &cache_dir).unwrap();
+        assert_eq!(image::image_dimensions(&preview_path).unwrap(), (30, 20));
+        // 2回目はキャッシュを返す
+        assert_eq!(write_raw_preview(&path, &cache_dir).unwrap(), preview_path);
+    }
+}
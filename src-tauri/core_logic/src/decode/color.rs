@@ -0,0 +1,131 @@
+// ICC プロファイルによるカラーマネジメント（qcms）
+//
+// Adobe RGB や Display P3 などのプロファイルが埋め込まれた画像を sRGB に変換する。
+// 変換できないプロファイル（CMYK やグレースケールなど）の画像はそのまま返す。
+
+use image::DynamicImage;
+use qcms::{DataType, Intent, Profile, Transform};
+use std::sync::LazyLock;
+
+/// 変換先の sRGB プロファイル（出力側の変換テーブルを事前に計算しておく）
+static SRGB: LazyLock<Box<Profile>> = LazyLock::new(|| {
+    let mut profile = Profile::new_sRGB();
+    profile.precache_output_transform();
+    profile
+});
+
+/// 埋め込みの ICC プロファイルに従って画像を sRGB に変換する
+pub(super) fn convert_to_srgb(img: DynamicImage, icc_profile: &[u8]) -> DynamicImage {
+    let Some(profile) = Profile::new_from_slice(icc_profile, false) else {
+        log::warn!("Ignoring unsupported ICC profile");
+        return img;
+    };
+    if profile.is_sRGB() || img.color().channel_count() < 3 {
+        return img;
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let data_type = if has_alpha {
+        DataType::RGBA8
+    } else {
+        DataType::RGB8
+    };
+    let Some(transform) = Transform::new(&profile, &SRGB, data_type, Intent::Perceptual) else {
+        log::warn!("Ignoring ICC profile that cannot be converted to sRGB");
+        return img;
+    };
+
+    if has_alpha {
+        let mut pixels = img.into_rgba8();
+        transform.apply(&mut pixels);
+        DynamicImage::ImageRgba8(pixels)
+    } else {
+        let mut pixels = img.into_rgb8();
+        transform.apply(&mut pixels);
+        DynamicImage::ImageRgb8(pixels)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 原色は sRGB と同じで、トーンカーブが線形（ガンマ 1.0）の ICC プロファイルを作成する
+    ///
+    /// 線形の値を sRGB に変換すると中間調が明るくなるため、変換されたかどうかを判定できる。
+    pub(crate) fn linear_rgb_profile() -> Vec<u8> {
+        fn s15_fixed16(value: f64) -> [u8; 4] {
+            ((value * 65536.0).round() as i32).to_be_bytes()
+        }
+        fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for value in [x, y, z] {
+                tag.extend(s15_fixed16(value));
+            }
+            tag
+        }
+        // ガンマ 1.0（u8Fixed8）
+        let curve = b"curv\0\0\0\0\0\0\0\x01\x01\x00\0\0".to_vec();
+        let tags: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+            (b"rXYZ", xyz(0.4361, 0.2225, 0.0139)),
+            (b"gXYZ", xyz(0.3851, 0.7169, 0.0971)),
+            (b"bXYZ", xyz(0.1431, 0.0606, 0.7141)),
+            (b"rTRC", curve.clone()),
+            (b"gTRC", curve.clone()),
+            (b"bTRC", curve),
+        ];
+
+        let mut header = vec![0; 128];
+        header[8..12].copy_from_slice(&[0x02, 0x10, 0, 0]);
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        header[68..80].copy_from_slice(&xyz(0.9642, 1.0, 0.8249)[8..]);
+
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data: Vec<u8> = Vec::new();
+        let data_start = 128 + 4 + tags.len() * 12;
+        for (signature, tag) in &tags {
+            table.extend(*signature);
+            table.extend(((data_start + data.len()) as u32).to_be_bytes());
+            table.extend((tag.len() as u32).to_be_bytes());
+            data.extend(tag);
+        }
+
+        let mut profile = header;
+        profile.extend(table);
+        profile.extend(data);
+        let size = (profile.len() as u32).to_be_bytes();
+        profile[..4].copy_from_slice(&size);
+        profile
+    }
+
+    #[test]
+    fn test_converts_to_srgb() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([50; 3])));
+        let converted = convert_to_srgb(img, &linear_rgb_profile()).into_rgb8();
+        // 線形 50/255 ≒ 0.196 は sRGB では約 0.48
+        let value = converted.get_pixel(0, 0).0[0];
+        assert!((115..=130).contains(&value), "got {}", value);
+
+        // アルファは維持する
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([50, 50, 50, 77]),
+        ));
+        let converted = convert_to_srgb(img, &linear_rgb_profile()).into_rgba8();
+        assert_eq!(converted.get_pixel(0, 0).0[3], 77);
+    }
+
+    #[test]
+    fn test_keeps_image_with_unsupported_profile() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1, 1, image::Rgb([50; 3])));
+        assert_eq!(convert_to_srgb(img.clone(), b"not a profile"), img);
+
+        let gray = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(1, 1, image::Luma([50])));
+        assert_eq!(convert_to_srgb(gray.clone(), &linear_rgb_profile()), gray);
+    }
+}
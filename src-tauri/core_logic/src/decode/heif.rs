@@ -0,0 +1,72 @@
+// libheif による HEIF / AVIF のデコード（`heif` / `avif` feature）
+//
+// システムに libheif（1.18 以降）がインストールされている必要がある。
+// AVIF のデコードには libheif が AV1 デコーダー（dav1d / libaom）付きでビルドされている必要がある。
+
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageError, ImageResult, Limits, RgbImage, RgbaImage};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use std::path::Path;
+
+/// 主画像を `limits` を上限としてデコードする（回転・切り抜きなどファイル内の変換は適用済み）
+pub(super) fn decode(path: &Path, limits: &Limits) -> ImageResult<DynamicImage> {
+    let context = open_context(path)?;
+    let handle = context.primary_image_handle().map_err(decoding_error)?;
+    let has_alpha = handle.has_alpha_channel();
+    // libheif は上限を持たないため、デコードする前に主画像の寸法で確かめる
+    super::check_limits(
+        limits,
+        handle.width(),
+        handle.height(),
+        if has_alpha { 4 } else { 3 },
+    )?;
+    let chroma = if has_alpha {
+        RgbChroma::Rgba
+    } else {
+        RgbChroma::Rgb
+    };
+
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(decoding_error)?;
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| decoding_error("Decoded image has no interleaved plane"))?;
+
+    // 行末のパディング（stride）を取り除いて詰める
+    let channels = if has_alpha { 4 } else { 3 };
+    let row_len = plane.width as usize * channels;
+    let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+
+    let image = if has_alpha {
+        RgbaImage::from_raw(plane.width, plane.height, pixels).map(DynamicImage::ImageRgba8)
+    } else {
+        RgbImage::from_raw(plane.width, plane.height, pixels).map(DynamicImage::ImageRgb8)
+    };
+    image.ok_or_else(|| decoding_error("Decoded image has unexpected size"))
+}
+
+/// 主画像の寸法を取得
+pub(super) fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let context = open_context(path)?;
+    let handle = context.primary_image_handle().map_err(decoding_error)?;
+    Ok((handle.width(), handle.height()))
+}
+
+fn open_context(path: &Path) -> ImageResult<HeifContext<'static>> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| decoding_error("Path is not valid UTF-8"))?;
+    HeifContext::read_from_file(path).map_err(decoding_error)
+}
+
+fn decoding_error<E: std::fmt::Display>(error: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("HEIF".to_string()),
+        error.to_string(),
+    ))
+}
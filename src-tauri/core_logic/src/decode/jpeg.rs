@@ -0,0 +1,258 @@
+// 大きな JPEG の縮小デコード（サムネイル生成用）
+//
+// 全体をデコードせず、EXIF に埋め込まれたサムネイルが十分な大きさであればそれを使い、
+// そうでなければ jpeg-decoder で DCT の段階から 1/2・1/4・1/8 に縮小してデコードする。
+// 最終的なサイズへのリサイズは呼び出し側で行う。
+
+use super::{color, is_transposed};
+use exif::{In, Reader, Tag};
+use image::error::{DecodingError, ImageFormatHint};
+use image::metadata::Orientation;
+use image::{DynamicImage, GrayImage, ImageError, ImageFormat, ImageReader, ImageResult, RgbImage};
+use jpeg_decoder::{Decoder, PixelFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+/// 縦横比がこれ以上ずれている EXIF サムネイルは使わない（黒帯の入ったサムネイルを避ける）
+const MAX_ASPECT_RATIO_DIFF: f64 = 0.02;
+
+/// `max_width` x `max_height` に収めるのに必要な大きさ以上で、できるだけ小さくデコードする
+///
+/// 向きの適用と sRGB への変換は [`super::open_image`] と同じ。
+/// 元の画像が収めるサイズの2倍未満で縮小の効果がない場合や、CMYK など縮小デコードに
+/// 対応しない画像の場合は `None` を返す。
+pub(super) fn decode_to_fit(
+    path: &Path,
+    max_width: u32,
+    max_height: u32,
+) -> ImageResult<Option<DynamicImage>> {
+    let data = std::fs::read(path)?;
+    let mut decoder = Decoder::new(Cursor::new(&data));
+    decoder.read_info().map_err(decoding_error)?;
+    let Some(info) = decoder.info() else {
+        return Ok(None);
+    };
+    if !matches!(info.pixel_format, PixelFormat::L8 | PixelFormat::RGB24) {
+        return Ok(None);
+    }
+
+    let orientation = decoder
+        .exif_data()
+        .and_then(Orientation::from_exif_chunk)
+        .unwrap_or(Orientation::NoTransforms);
+    // 保存されている向きでの寸法に合わせる
+    let (max_width, max_height) = if is_transposed(orientation) {
+        (max_height, max_width)
+    } else {
+        (max_width, max_height)
+    };
+    let size = (u32::from(info.width), u32::from(info.height));
+    let Some(required) = required_size(size, max_width, max_height) else {
+        return Ok(None);
+    };
+
+    let icc_profile = decoder.icc_profile();
+    let exif_thumbnail = decoder
+        .exif_data()
+        .and_then(|exif| exif_thumbnail(exif, size, required));
+    let mut img = match exif_thumbnail {
+        Some(thumbnail) => thumbnail,
+        None => {
+            // JPEG の寸法は u16 に収まるため、それ以下の required も収まる
+            decoder
+                .scale(required.0 as u16, required.1 as u16)
+                .map_err(decoding_error)?;
+            let pixels = decoder.decode().map_err(decoding_error)?;
+            let Some(info) = decoder.info() else {
+                return Ok(None);
+            };
+            let (width, height) = (u32::from(info.width), u32::from(info.height));
+            match info.pixel_format {
+                PixelFormat::L8 => {
+                    GrayImage::from_raw(width, height, pixels).map(DynamicImage::from)
+                }
+                _ => RgbImage::from_raw(width, height, pixels).map(DynamicImage::from),
+            }
+            .ok_or_else(|| decoding_error("Decoded pixels do not match the image size"))?
+        }
+    };
+
+    if let Some(icc_profile) = icc_profile {
+        img = color::convert_to_srgb(img, &icc_profile);
+    }
+    img.apply_orientation(orientation);
+    Ok(Some(img))
+}
+
+/// 縦横比を維持して `max_width` x `max_height` に収めたときの寸法（切り上げ）
+///
+/// 元の寸法が収めたサイズの2倍未満の場合は `None`。
+fn required_size(
+    (width, height): (u32, u32),
+    max_width: u32,
+    max_height: u32,
+) -> Option<(u32, u32)> {
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(width),
+        f64::from(max_height) / f64::from(height),
+    );
+    if scale > 0.5 {
+        return None;
+    }
+    Some((
+        (f64::from(width) * scale).ceil().max(1.0) as u32,
+        (f64::from(height) * scale).ceil().max(1.0) as u32,
+    ))
+}
+
+/// EXIF に埋め込まれたサムネイルが `required` 以上の大きさで、元の画像と同じ縦横比ならデコードする
+fn exif_thumbnail(
+    exif: &[u8],
+    (width, height): (u32, u32),
+    required: (u32, u32),
+) -> Option<DynamicImage> {
+    let exif = Reader::new().read_raw(exif.to_vec()).ok()?;
+    let value = |tag: Tag| exif.get_field(tag, In::THUMBNAIL)?.value.get_uint(0);
+    let offset = value(Tag::JPEGInterchangeFormat)? as usize;
+    let length = value(Tag::JPEGInterchangeFormatLength)? as usize;
+    let thumbnail = exif.buf().get(offset..offset.checked_add(length)?)?;
+
+    let (thumb_width, thumb_height) =
+        ImageReader::with_format(Cursor::new(thumbnail), ImageFormat::Jpeg)
+            .into_dimensions()
+            .ok()?;
+    let aspect_ratio_diff =
+        (f64::from(thumb_width) / f64::from(thumb_height)) / (f64::from(width) / f64::from(height));
+    if thumb_width < required.0
+        || thumb_height < required.1
+        || (aspect_ratio_diff - 1.0).abs() > MAX_ASPECT_RATIO_DIFF
+    {
+        return None;
+    }
+
+    image::load_from_memory_with_format(thumbnail, ImageFormat::Jpeg)
+        .inspect_err(|e| log::warn!("Skipping broken EXIF thumbnail: {}", e))
+        .ok()
+}
+
+fn decoding_error(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Jpeg),
+        e,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use exif::experimental::Writer;
+    use exif::{Field, Value};
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageEncoder, Rgb};
+
+    /// 左半分が赤、右半分が青の画像
+    fn split_image((width, height): (u32, u32)) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        })
+    }
+
+    /// EXIF サムネイル（緑一色）を埋め込んだ JPEG を作成する
+    fn create_jpeg_with_exif_thumbnail(path: &Path, size: (u32, u32), thumbnail_size: (u32, u32)) {
+        let mut thumbnail = Vec::new();
+        RgbImage::from_pixel(thumbnail_size.0, thumbnail_size.1, Rgb([0, 255, 0]))
+            .write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Jpeg)
+            .unwrap();
+
+        let mut writer = Writer::new();
+        let orientation = Field {
+            tag: Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![1]),
+        };
+        writer.push_field(&orientation);
+        writer.set_jpeg(&thumbnail, In::THUMBNAIL);
+        let mut exif = Cursor::new(Vec::new());
+        writer.write(&mut exif, false).unwrap();
+
+        let mut encoder = JpegEncoder::new(std::fs::File::create(path).unwrap());
+        encoder.set_exif_metadata(exif.into_inner()).unwrap();
+        encoder
+            .write_image(
+                &split_image(size),
+                size.0,
+                size.1,
+                image::ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_decodes_at_reduced_scale() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("large.jpg");
+        split_image((1600, 800)).save(&path).unwrap();
+
+        let img = decode_to_fit(&path, 200, 200).unwrap().unwrap();
+        // 1/8 で 200x100 に収まる大きさ以上になる
+        assert_eq!((img.width(), img.height()), (200, 100));
+        let img = img.to_rgb8();
+        assert!(img.get_pixel(10, 50)[0] > 200);
+        assert!(img.get_pixel(190, 50)[2] > 200);
+
+        let img = decode_to_fit(&path, 500, 500).unwrap().unwrap();
+        assert_eq!((img.width(), img.height()), (800, 400));
+    }
+
+    #[test]
+    fn test_skips_small_image() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("small.jpg");
+        split_image((300, 300)).save(&path).unwrap();
+
+        assert!(decode_to_fit(&path, 200, 200).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_applies_orientation() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("rotated.jpg");
+        super::super::create_oriented_jpeg(&path, (1600, 800), 8);
+
+        // 表示上は縦長になる
+        let img = decode_to_fit(&path, 100, 200).unwrap().unwrap();
+        assert_eq!((img.width(), img.height()), (100, 200));
+    }
+
+    #[test]
+    fn test_uses_exif_thumbnail_when_large_enough() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("photo.jpg");
+        create_jpeg_with_exif_thumbnail(&path, (1600, 1200), (160, 120));
+
+        let img = decode_to_fit(&path, 160, 160).unwrap().unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (160, 120));
+        assert!(img.get_pixel(80, 60)[1] > 200, "Should use the thumbnail");
+
+        // 必要な大きさに足りない場合は本体を縮小デコードする
+        let img = decode_to_fit(&path, 400, 400).unwrap().unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (400, 300));
+        assert!(img.get_pixel(10, 150)[0] > 200);
+    }
+
+    #[test]
+    fn test_ignores_exif_thumbnail_with_other_aspect_ratio() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("photo.jpg");
+        create_jpeg_with_exif_thumbnail(&path, (1600, 900), (160, 120));
+
+        let img = decode_to_fit(&path, 160, 160).unwrap().unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (200, 113));
+        assert!(img.get_pixel(10, 50)[0] > 200);
+    }
+}
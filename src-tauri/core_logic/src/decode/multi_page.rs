@@ -0,0 +1,307 @@
+// 複数ページの TIFF のデコード
+//
+// image クレートは先頭ページしか読まないため、tiff クレートで各ページを読む。
+
+use image::error::{
+    DecodingError, LimitError, LimitErrorKind, UnsupportedError, UnsupportedErrorKind,
+};
+use image::{
+    DynamicImage, GrayAlphaImage, GrayImage, ImageBuffer, ImageError, ImageFormat, ImageResult,
+    Limits, Luma, Rgb, RgbImage, Rgba, RgbaImage,
+};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::{ColorType, TiffError};
+
+/// TIFF のページ数を取得
+pub fn count_tiff_pages<P: AsRef<Path>>(path: P) -> ImageResult<usize> {
+    let mut decoder = open_decoder(path.as_ref(), &Limits::default())?;
+    let mut count = 1;
+    while decoder.more_images() {
+        decoder.next_image().map_err(tiff_error)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// TIFF の各ページを先頭から順にデコードする
+///
+/// デコードの上限は image クレートの既定の上限（[`Limits::default`]）を使う。
+///
+/// # Returns
+/// デコードしたページ数
+pub fn for_each_tiff_page<P, F>(path: P, on_page: F) -> ImageResult<usize>
+where
+    P: AsRef<Path>,
+    F: FnMut(usize, DynamicImage) -> ImageResult<()>,
+{
+    for_each_tiff_page_with_limits(path, &Limits::default(), on_page)
+}
+
+/// `limits` を上限として、TIFF の各ページを先頭から順にデコードする
+///
+/// 上限を超えるページがあれば、そのページをデコードする前に `ImageError::Limits` を返す。
+pub fn for_each_tiff_page_with_limits<P, F>(
+    path: P,
+    limits: &Limits,
+    mut on_page: F,
+) -> ImageResult<usize>
+where
+    P: AsRef<Path>,
+    F: FnMut(usize, DynamicImage) -> ImageResult<()>,
+{
+    let mut decoder = open_decoder(path.as_ref(), limits)?;
+    let mut index = 0;
+    loop {
+        on_page(index, read_page(&mut decoder, limits)?)?;
+        index += 1;
+        if !decoder.more_images() {
+            return Ok(index);
+        }
+        decoder.next_image().map_err(tiff_error)?;
+    }
+}
+
+/// tiff クレートのデコーダーを開く（バッファの上限は `limits` の `max_alloc` に合わせる）
+fn open_decoder(path: &Path, limits: &Limits) -> ImageResult<Decoder<BufReader<File>>> {
+    let mut tiff_limits = tiff::decoder::Limits::default();
+    if let Some(max_alloc) = limits.max_alloc {
+        let max_alloc = usize::try_from(max_alloc).unwrap_or(usize::MAX);
+        tiff_limits.decoding_buffer_size = max_alloc;
+        tiff_limits.intermediate_buffer_size = max_alloc;
+    }
+    Decoder::new(BufReader::new(File::open(path)?))
+        .map(|decoder| decoder.with_limits(tiff_limits))
+        .map_err(tiff_error)
+}
+
+/// 現在のページを DynamicImage に変換する
+fn read_page(decoder: &mut Decoder<BufReader<File>>, limits: &Limits) -> ImageResult<DynamicImage> {
+    let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+    let color_type = decoder.colortype().map_err(tiff_error)?;
+    let bytes_per_pixel =
+        u64::from(color_type.bit_depth().div_ceil(8)) * u64::from(color_type.num_samples());
+    super::check_limits(limits, width, height, bytes_per_pixel)?;
+    let data = decoder.read_image().map_err(tiff_error)?;
+
+    let image = match (color_type, data) {
+        (ColorType::Gray(1), DecodingResult::U8(buf)) => {
+            GrayImage::from_raw(width, height, unpack_bilevel(&buf, width, height))
+                .map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            GrayImage::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            GrayAlphaImage::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            RgbImage::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            RgbaImage::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::CMYK(8), DecodingResult::U8(buf)) => {
+            RgbImage::from_raw(width, height, cmyk_to_rgb(&buf)).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::<Luma<u16>, _>::from_raw(width, height, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, buf).map(DynamicImage::ImageRgba16)
+        }
+        (color_type, _) => {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Tiff.into(),
+                    UnsupportedErrorKind::GenericFeature(format!("{:?}", color_type)),
+                ),
+            ))
+        }
+    };
+    image.ok_or_else(|| decoding_error("Decoded page has unexpected size"))
+}
+
+/// 1ビットの白黒画像（各行はバイト境界で揃えられている）を8ビットのグレースケールに展開
+///
+/// tiff クレートは WhiteIsZero のページを読み込むときにビットを反転するため、
+/// `buf` は PhotometricInterpretation によらず立っているビットが白になっている。
+fn unpack_bilevel(buf: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in buf.chunks(row_bytes).take(height as usize) {
+        for x in 0..width as usize {
+            let bit = (row[x / 8] >> (7 - x % 8)) & 1;
+            pixels.push(if bit == 1 { 255 } else { 0 });
+        }
+    }
+    pixels
+}
+
+/// CMYK（8ビット）を RGB に変換
+fn cmyk_to_rgb(buf: &[u8]) -> Vec<u8> {
+    buf.chunks_exact(4)
+        .flat_map(|cmyk| {
+            let k = 255 - cmyk[3] as u32;
+            cmyk[..3]
+                .iter()
+                .map(move |&c| ((255 - c as u32) * k / 255) as u8)
+        })
+        .collect()
+}
+
+/// tiff クレートのエラーを変換する（上限を超えた場合は `ImageError::Limits`）
+fn tiff_error(error: TiffError) -> ImageError {
+    match error {
+        TiffError::LimitsExceeded => {
+            ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+        }
+        error => decoding_error(error),
+    }
+}
+
+fn decoding_error<E: std::fmt::Display>(error: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormat::Tiff.into(),
+        error.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    #[test]
+    fn test_reads_every_page() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("scan.tiff");
+        let mut encoder = TiffEncoder::new(File::create(&path).unwrap()).unwrap();
+        encoder
+            .write_image::<colortype::RGB8>(4, 2, &[200; 4 * 2 * 3])
+            .unwrap();
+        encoder
+            .write_image::<colortype::Gray8>(3, 5, &[10; 3 * 5])
+            .unwrap();
+        drop(encoder);
+
+        assert_eq!(count_tiff_pages(&path).unwrap(), 2);
+
+        let mut pages = Vec::new();
+        let count = for_each_tiff_page(&path, |index, page| {
+            pages.push((index, page.width(), page.height()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(pages, vec![(0, 4, 2), (1, 3, 5)]);
+    }
+
+    #[test]
+    fn test_page_limits() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("scan.tiff");
+        let mut encoder = TiffEncoder::new(File::create(&path).unwrap()).unwrap();
+        encoder
+            .write_image::<colortype::Gray8>(4, 4, &[10; 4 * 4])
+            .unwrap();
+        encoder
+            .write_image::<colortype::RGB8>(40, 40, &[200; 40 * 40 * 3])
+            .unwrap();
+        drop(encoder);
+
+        // 2ページ目（40x40 の RGB）だけが上限を超える
+        let mut limits = Limits::no_limits();
+        limits.max_alloc = Some(1000);
+        let mut pages = 0;
+        let result = for_each_tiff_page_with_limits(&path, &limits, |_, _| {
+            pages += 1;
+            Ok(())
+        });
+        assert!(matches!(result, Err(ImageError::Limits(_))), "{:?}", result);
+        assert_eq!(pages, 1);
+
+        assert_eq!(for_each_tiff_page(&path, |_, _| Ok(())).unwrap(), 2);
+    }
+
+    /// 1ビットの白黒の TIFF を作成する（1行8ピクセル、`row` のビットが各行の値）
+    fn write_bilevel_tiff(path: &Path, photometric: u16, rows: &[u8]) {
+        let entries: [(u16, u16, u32); 8] = [
+            (256, 3, 8),                 // ImageWidth
+            (257, 3, rows.len() as u32), // ImageLength
+            (258, 3, 1),                 // BitsPerSample
+            (259, 3, 1),                 // Compression（なし）
+            (262, 3, photometric as u32),
+            (273, 4, 8),                 // StripOffsets（ヘッダーの直後）
+            (278, 3, rows.len() as u32), // RowsPerStrip
+            (279, 4, rows.len() as u32), // StripByteCounts
+        ];
+        let ifd_offset = 8 + rows.len() as u32;
+        let mut tiff = b"II\x2A\0".to_vec();
+        tiff.extend(ifd_offset.to_le_bytes());
+        tiff.extend(rows);
+        tiff.extend((entries.len() as u16).to_le_bytes());
+        for (tag, field_type, value) in entries {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(field_type.to_le_bytes());
+            tiff.extend(1u32.to_le_bytes());
+            if field_type == 3 {
+                tiff.extend((value as u16).to_le_bytes());
+                tiff.extend([0; 2]);
+            } else {
+                tiff.extend(value.to_le_bytes());
+            }
+        }
+        tiff.extend(0u32.to_le_bytes());
+        std::fs::write(path, tiff).unwrap();
+    }
+
+    #[test]
+    fn test_bilevel_photometric_interpretation() {
+        let temp = TempTestDir::new_random();
+        // 先頭のピクセルだけビットが立っている。WhiteIsZero では黒、BlackIsZero では白になる
+        for (photometric, expected) in [(0, [0, 255]), (1, [255, 0])] {
+            let path = temp.path().join(format!("bilevel-{}.tiff", photometric));
+            write_bilevel_tiff(&path, photometric, &[0b1000_0000]);
+            for_each_tiff_page(&path, |_, page| {
+                let page = page.to_luma8();
+                assert_eq!(
+                    [page.get_pixel(0, 0)[0], page.get_pixel(1, 0)[0]],
+                    expected,
+                    "PhotometricInterpretation {}",
+                    photometric
+                );
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_unpack_bilevel() {
+        // 幅10ピクセル → 1行2バイト
+        let pixels = unpack_bilevel(&[0b1000_0001, 0b0100_0000], 10, 1);
+        assert_eq!(pixels, vec![255, 0, 0, 0, 0, 0, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_cmyk_to_rgb() {
+        assert_eq!(cmyk_to_rgb(&[0, 0, 0, 0]), vec![255, 255, 255]);
+        assert_eq!(cmyk_to_rgb(&[0, 0, 0, 255]), vec![0, 0, 0]);
+        assert_eq!(cmyk_to_rgb(&[255, 0, 0, 0]), vec![0, 255, 255]);
+    }
+
+    #[test]
+    fn test_error_for_invalid_file() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("broken.tiff");
+        std::fs::write(&path, b"not a tiff").unwrap();
+        assert!(count_tiff_pages(&path).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+// カメラ RAW（CR2 / NEF / ARW / DNG）に埋め込まれた JPEG プレビューの取り出し
+//
+// いずれも TIFF 構造を持つため、IFD（SubIFD を含む）をたどって JPEG のプレビューを探す。
+// RAW データ自体の現像（デモザイク）は行わない。
+// プレビューの向きは IFD0 の Orientation に従う。
+
+use image::error::{DecodingError, ImageFormatHint};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader, ImageResult, Limits};
+use std::io::Cursor;
+use std::path::Path;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+
+/// 圧縮方式: JPEG（旧形式 / 新形式）
+const COMPRESSION_OLD_JPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+
+/// 壊れたファイルで IFD が循環していても止まるよう、たどる IFD 数に上限を設ける
+const MAX_IFDS: usize = 64;
+
+/// 最も大きい JPEG プレビューを `limits` を上限としてデコードし、向きを適用する
+pub(super) fn decode(path: &Path, limits: &Limits) -> ImageResult<DynamicImage> {
+    let data = std::fs::read(path)?;
+    let preview = largest_preview(&data)?;
+    let mut reader = ImageReader::with_format(Cursor::new(preview), ImageFormat::Jpeg);
+    reader.limits(limits.clone());
+    let mut img = reader.decode()?;
+    img.apply_orientation(read_orientation(&data)?);
+    Ok(img)
+}
+
+/// IFD0 の Orientation を取得（記録がない場合は回転なし）
+pub(super) fn orientation(path: &Path) -> ImageResult<Orientation> {
+    read_orientation(&std::fs::read(path)?)
+}
+
+/// 最も大きい JPEG プレビューの寸法を取得
+pub(super) fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let data = std::fs::read(path)?;
+    let preview = largest_preview(&data)?;
+    ImageReader::with_format(Cursor::new(preview), ImageFormat::Jpeg).into_dimensions()
+}
+
+/// 最も大きい JPEG プレビューのバイト列を取得（表示用にそのまま保存できる）
+pub(super) fn extract_preview(path: &Path) -> ImageResult<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    largest_preview(&data).map(<[u8]>::to_vec)
+}
+
+fn read_orientation(data: &[u8]) -> ImageResult<Orientation> {
+    let tiff = Tiff::parse(data)?;
+    let ifd0 = tiff.read_ifd(tiff.read_u32(4)?)?;
+    Ok(ifd0
+        .value(&tiff, TAG_ORIENTATION)
+        .and_then(|value| u8::try_from(value).ok())
+        .and_then(Orientation::from_exif)
+        .unwrap_or(Orientation::NoTransforms))
+}
+
+fn largest_preview(data: &[u8]) -> ImageResult<&[u8]> {
+    find_previews(data)?
+        .into_iter()
+        .max_by_key(|preview| preview.len())
+        .ok_or_else(|| decoding_error("No embedded JPEG preview found"))
+}
+
+/// ファイル内の JPEG プレビューを列挙する
+///
+/// ロスレス JPEG（RAW データ本体）は image クレートで開けないため除外する。
+fn find_previews(data: &[u8]) -> ImageResult<Vec<&[u8]>> {
+    let tiff = Tiff::parse(data)?;
+    let mut previews = Vec::new();
+    let mut pending = vec![tiff.read_u32(4)?];
+    let mut visited = Vec::new();
+
+    while let Some(offset) = pending.pop() {
+        if offset == 0 || visited.contains(&offset) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        visited.push(offset);
+
+        let ifd = tiff.read_ifd(offset)?;
+        pending.extend(ifd.next);
+        pending.extend(ifd.values(&tiff, TAG_SUB_IFDS));
+
+        for candidate in ifd.jpeg_candidates(&tiff) {
+            if is_baseline_jpeg(candidate) {
+                previews.push(candidate);
+            }
+        }
+    }
+    Ok(previews)
+}
+
+/// バイトオーダーを考慮して TIFF を読む
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+/// IFD のエントリ（タグ, 型, 個数, 値またはオフセットの位置）
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value_pos: usize,
+}
+
+struct Ifd {
+    entries: Vec<Entry>,
+    next: Option<u32>,
+}
+
+impl<'a> Tiff<'a> {
+    fn parse(data: &'a [u8]) -> ImageResult<Self> {
+        let big_endian = match data.get(..4) {
+            Some([b'I', b'I', 42, 0]) => false,
+            Some([b'M', b'M', 0, 42]) => true,
+            _ => return Err(decoding_error("Not a TIFF-based RAW file")),
+        };
+        Ok(Self { data, big_endian })
+    }
+
+    fn bytes<const N: usize>(&self, pos: usize) -> ImageResult<[u8; N]> {
+        self.data
+            .get(pos..pos + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| decoding_error("Unexpected end of file"))
+    }
+
+    fn read_u16(&self, pos: usize) -> ImageResult<u16> {
+        let bytes = self.bytes(pos)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn read_u32(&self, pos: usize) -> ImageResult<u32> {
+        let bytes = self.bytes(pos)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn read_ifd(&self, offset: u32) -> ImageResult<Ifd> {
+        let offset = offset as usize;
+        let count = self.read_u16(offset)? as usize;
+        let entries = (0..count)
+            .map(|i| {
+                let pos = offset + 2 + i * 12;
+                Ok(Entry {
+                    tag: self.read_u16(pos)?,
+                    field_type: self.read_u16(pos + 2)?,
+                    count: self.read_u32(pos + 4)?,
+                    value_pos: pos + 8,
+                })
+            })
+            .collect::<ImageResult<Vec<_>>>()?;
+        let next = self.read_u32(offset + 2 + count * 12).ok();
+        Ok(Ifd { entries, next })
+    }
+}
+
+impl Ifd {
+    /// SHORT / LONG 型のタグの値を取得（未知の型や範囲外の値は読み飛ばす）
+    fn values(&self, tiff: &Tiff, tag: u16) -> Vec<u32> {
+        let Some(entry) = self.entries.iter().find(|entry| entry.tag == tag) else {
+            return Vec::new();
+        };
+        let size = match entry.field_type {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Vec::new(),
+        };
+        // 4バイトに収まらない値はオフセット先に格納されている
+        let start = if size * entry.count as usize <= 4 {
+            entry.value_pos
+        } else {
+            match tiff.read_u32(entry.value_pos) {
+                Ok(offset) => offset as usize,
+                Err(_) => return Vec::new(),
+            }
+        };
+        (0..entry.count as usize)
+            .map_while(|i| match size {
+                2 => tiff.read_u16(start + i * 2).ok().map(u32::from),
+                _ => tiff.read_u32(start + i * 4).ok(),
+            })
+            .collect()
+    }
+
+    fn value(&self, tiff: &Tiff, tag: u16) -> Option<u32> {
+        self.values(tiff, tag).first().copied()
+    }
+
+    /// JPEG として格納されている可能性のある領域
+    fn jpeg_candidates<'a>(&self, tiff: &Tiff<'a>) -> Vec<&'a [u8]> {
+        let mut candidates = Vec::new();
+        let slice = |offset: u32, length: u32| {
+            let start = offset as usize;
+            tiff.data.get(start..start.checked_add(length as usize)?)
+        };
+
+        if let (Some(offset), Some(length)) = (
+            self.value(tiff, TAG_JPEG_OFFSET),
+            self.value(tiff, TAG_JPEG_LENGTH),
+        ) {
+            candidates.extend(slice(offset, length));
+        }
+
+        // CR2 の IFD0 や DNG のプレビュー IFD は、1ストリップの JPEG として格納されている
+        let compression = self.value(tiff, TAG_COMPRESSION);
+        let is_jpeg = matches!(
+            compression,
+            Some(COMPRESSION_OLD_JPEG) | Some(COMPRESSION_JPEG)
+        );
+        // DNG の RAW データ本体（NewSubfileType = 0）は対象外
+        let is_raw_data = self.value(tiff, TAG_NEW_SUBFILE_TYPE) == Some(0)
+            && compression == Some(COMPRESSION_JPEG);
+        if is_jpeg && !is_raw_data {
+            let offsets = self.values(tiff, TAG_STRIP_OFFSETS);
+            let lengths = self.values(tiff, TAG_STRIP_BYTE_COUNTS);
+            if let ([offset], [length]) = (offsets.as_slice(), lengths.as_slice()) {
+                candidates.extend(slice(*offset, *length));
+            }
+        }
+        candidates
+    }
+}
+
+/// image クレートで開ける JPEG（ベースライン・プログレッシブ）か
+///
+/// SOF マーカーまでセグメントをたどり、ロスレス（SOF3）などを除外する。
+fn is_baseline_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, len_hi, len_lo]) = data.get(pos..pos + 4) {
+        match marker {
+            0xC0..=0xC2 => return true,
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return false,
+            0xD9 | 0xDA => return false,
+            _ => pos += 2 + u16::from_be_bytes([len_hi, len_lo]) as usize,
+        }
+    }
+    false
+}
+
+fn decoding_error<E: std::fmt::Display>(error: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("RAW".to_string()),
+        error.to_string(),
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+
+    /// テスト用の JPEG を作成
+    pub(crate) fn jpeg_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::from_pixel(width, height, image::Rgb([200, 100, 50]))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    /// TIFF を組み立てるテスト用のビルダー
+    struct TiffWriter {
+        big_endian: bool,
+        data: Vec<u8>,
+    }
+
+    impl TiffWriter {
+        fn new(big_endian: bool) -> Self {
+            let mut data = if big_endian {
+                b"MM\0\x2A".to_vec()
+            } else {
+                b"II\x2A\0".to_vec()
+            };
+            data.extend([0; 4]);
+            Self { big_endian, data }
+        }
+
+        fn u16(&self, value: u16) -> [u8; 2] {
+            if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        }
+
+        fn u32(&self, value: u32) -> [u8; 4] {
+            if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        }
+
+        fn append(&mut self, bytes: &[u8]) -> u32 {
+            let offset = self.data.len() as u32;
+            self.data.extend_from_slice(bytes);
+            offset
+        }
+
+        /// LONG 型のタグのみを持つ IFD を追加し、そのオフセットを返す
+        fn append_ifd(&mut self, entries: &[(u16, u32)], next: u32) -> u32 {
+            let mut ifd = self.u16(entries.len() as u16).to_vec();
+            for (tag, value) in entries {
+                ifd.extend(self.u16(*tag));
+                ifd.extend(self.u16(4));
+                ifd.extend(self.u32(1));
+                ifd.extend(self.u32(*value));
+            }
+            ifd.extend(self.u32(next));
+            self.append(&ifd)
+        }
+
+        fn set_first_ifd(&mut self, offset: u32) {
+            let bytes = self.u32(offset);
+            self.data[4..8].copy_from_slice(&bytes);
+        }
+    }
+
+    /// RAW ファイルのフィクスチャを作成する
+    ///
+    /// IFD0 に小さいサムネイル（JPEGInterchangeFormat）、SubIFD に大きいプレビュー（JPEG ストリップ）と
+    /// ロスレス JPEG の RAW データを持つ、CR2 / NEF / DNG に近い構造。
+    pub(crate) fn create_raw_fixture(path: &Path, big_endian: bool, preview: (u32, u32)) {
+        create_oriented_raw_fixture(path, big_endian, preview, 1);
+    }
+
+    /// IFD0 に Orientation（EXIF と同じ 1〜8 の値）を記録した RAW ファイルのフィクスチャを作成する
+    pub(crate) fn create_oriented_raw_fixture(
+        path: &Path,
+        big_endian: bool,
+        preview: (u32, u32),
+        orientation: u32,
+    ) {
+        let mut tiff = TiffWriter::new(big_endian);
+        let thumbnail = jpeg_bytes(16, 12);
+        let large = jpeg_bytes(preview.0, preview.1);
+        // ロスレス JPEG（SOF3）に見せかけた RAW データ
+        let mut raw_data = vec![0xFF, 0xD8, 0xFF, 0xC3, 0x00, 0x02];
+        raw_data.resize(large.len() * 2, 0);
+
+        let thumbnail_offset = tiff.append(&thumbnail);
+        let large_offset = tiff.append(&large);
+        let raw_offset = tiff.append(&raw_data);
+
+        let raw_ifd = tiff.append_ifd(
+            &[
+                (TAG_NEW_SUBFILE_TYPE, 0),
+                (TAG_COMPRESSION, COMPRESSION_JPEG),
+                (TAG_STRIP_OFFSETS, raw_offset),
+                (TAG_STRIP_BYTE_COUNTS, raw_data.len() as u32),
+            ],
+            0,
+        );
+        let preview_ifd = tiff.append_ifd(
+            &[
+                (TAG_NEW_SUBFILE_TYPE, 1),
+                (TAG_COMPRESSION, COMPRESSION_OLD_JPEG),
+                (TAG_STRIP_OFFSETS, large_offset),
+                (TAG_STRIP_BYTE_COUNTS, large.len() as u32),
+            ],
+            raw_ifd,
+        );
+        let ifd0 = tiff.append_ifd(
+            &[
+                (TAG_NEW_SUBFILE_TYPE, 1),
+                (TAG_ORIENTATION, orientation),
+                (TAG_SUB_IFDS, preview_ifd),
+                (TAG_JPEG_OFFSET, thumbnail_offset),
+                (TAG_JPEG_LENGTH, thumbnail.len() as u32),
+            ],
+            0,
+        );
+        tiff.set_first_ifd(ifd0);
+        std::fs::write(path, tiff.data).unwrap();
+    }
+
+    #[test]
+    fn test_decodes_largest_preview() {
+        let temp = TempTestDir::new_random();
+        for (name, big_endian) in [("a.cr2", false), ("a.nef", true)] {
+            let path = temp.path().join(name);
+            create_raw_fixture(&path, big_endian, (64, 48));
+
+            assert_eq!(dimensions(&path).unwrap(), (64, 48), "{}", name);
+            assert_eq!(
+                decode(&path, &Limits::default()).unwrap().width(),
+                64,
+                "{}",
+                name
+            );
+            let preview = extract_preview(&path).unwrap();
+            assert!(preview.starts_with(&[0xFF, 0xD8]));
+        }
+    }
+
+    #[test]
+    fn test_applies_orientation() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("portrait.cr2");
+        // 90度回転（時計回り）して表示する
+        create_oriented_raw_fixture(&path, true, (64, 48), 6);
+
+        assert_eq!(orientation(&path).unwrap(), Orientation::Rotate90);
+        assert_eq!(dimensions(&path).unwrap(), (64, 48), "Stored size");
+        let img = decode(&path, &Limits::default()).unwrap();
+        assert_eq!((img.width(), img.height()), (48, 64));
+    }
+
+    #[test]
+    fn test_skips_lossless_raw_data() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("a.dng");
+        create_raw_fixture(&path, false, (40, 30));
+
+        let data = std::fs::read(&path).unwrap();
+        let previews = find_previews(&data).unwrap();
+        assert_eq!(previews.len(), 2, "Thumbnail and preview only");
+    }
+
+    #[test]
+    fn test_error_without_preview() {
+        let temp = TempTestDir::new_random();
+        let not_tiff = temp.path().join("a.arw");
+        std::fs::write(&not_tiff, b"not a raw file").unwrap();
+        assert!(decode(&not_tiff, &Limits::default()).is_err());
+
+        // IFD が自分自身を指していても止まる
+        let mut tiff = TiffWriter::new(false);
+        let ifd0 = tiff.data.len() as u32;
+        tiff.append_ifd(&[(TAG_COMPRESSION, 1)], ifd0);
+        tiff.set_first_ifd(ifd0);
+        let looped = temp.path().join("b.arw");
+        std::fs::write(&looped, tiff.data).unwrap();
+        assert!(decode(&looped, &Limits::default()).is_err());
+    }
+
+    #[test]
+    fn test_is_baseline_jpeg() {
+        assert!(is_baseline_jpeg(&jpeg_bytes(4, 4)));
+        assert!(!is_baseline_jpeg(&[0xFF, 0xD8, 0xFF, 0xC3, 0x00, 0x02]));
+        assert!(!is_baseline_jpeg(b"not a jpeg"));
+    }
+}
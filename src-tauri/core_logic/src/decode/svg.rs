@@ -0,0 +1,193 @@
+// resvg による SVG のラスタライズ
+//
+// 外部ファイルや URL への参照（`<image href="...">` など）は読み込まない。
+// データ URL として埋め込まれた画像のみ描画する。
+
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageError, ImageResult, Limits, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{ImageHrefResolver, Options, Tree};
+use std::path::Path;
+
+/// 描画サイズを指定しない場合の長辺の上限（ピクセル）
+///
+/// 巨大な寸法を宣言した SVG でメモリを使い切らないようにする。
+const MAX_INTRINSIC_SIZE: u32 = 4096;
+
+/// SVG の宣言サイズで描画する（[`MAX_INTRINSIC_SIZE`] を超える場合は縮小する）
+pub(super) fn decode(path: &Path, limits: &Limits) -> ImageResult<DynamicImage> {
+    let tree = parse(path)?;
+    let (width, height) = intrinsic_size(&tree);
+    render(
+        &tree,
+        width.min(MAX_INTRINSIC_SIZE),
+        height.min(MAX_INTRINSIC_SIZE),
+        limits,
+    )
+}
+
+/// アスペクト比を維持して `max_width` x `max_height` に収まるサイズで描画する
+///
+/// ベクター画像のため、宣言サイズより大きく描画しても劣化しない。
+pub(super) fn decode_to_fit(
+    path: &Path,
+    max_width: u32,
+    max_height: u32,
+    limits: &Limits,
+) -> ImageResult<DynamicImage> {
+    render(&parse(path)?, max_width, max_height, limits)
+}
+
+/// SVG の宣言サイズを取得（小数は切り上げ）
+pub(super) fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    Ok(intrinsic_size(&parse(path)?))
+}
+
+fn intrinsic_size(tree: &Tree) -> (u32, u32) {
+    let size = tree.size();
+    (size.width().ceil() as u32, size.height().ceil() as u32)
+}
+
+/// 描画サイズ（RGBA）が `limits` を超える場合は、描画する前に `ImageError::Limits` を返す
+fn render(
+    tree: &Tree,
+    max_width: u32,
+    max_height: u32,
+    limits: &Limits,
+) -> ImageResult<DynamicImage> {
+    let size = tree.size();
+    let scale = (max_width as f32 / size.width()).min(max_height as f32 / size.height());
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+    super::check_limits(limits, width, height, 4)?;
+
+    let mut pixmap =
+        Pixmap::new(width, height).ok_or_else(|| decoding_error("Invalid render size"))?;
+    resvg::render(
+        tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia のピクセルは乗算済みアルファのため、通常のアルファに戻す
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| decoding_error("Rendered image has unexpected size"))
+}
+
+fn parse(path: &Path) -> ImageResult<Tree> {
+    let data = std::fs::read(path)?;
+    let options = Options {
+        resources_dir: None,
+        image_href_resolver: ImageHrefResolver {
+            resolve_data: ImageHrefResolver::default_data_resolver(),
+            // ファイルパスや URL による参照は読み込まない
+            resolve_string: Box::new(|href, _| {
+                log::warn!("Skipping external reference in SVG: {}", href);
+                None
+            }),
+        },
+        ..Options::default()
+    };
+    Tree::from_data(&data, &options).map_err(decoding_error)
+}
+
+fn decoding_error<E: std::fmt::Display>(error: E) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("SVG".to_string()),
+        error.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use image::GenericImageView;
+
+    const RED_SQUARE: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+        <rect x="0" y="0" width="20" height="20" fill="red"/>
+    </svg>"#;
+
+    #[test]
+    fn test_decode_at_intrinsic_size() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("icon.svg");
+        std::fs::write(&path, RED_SQUARE).unwrap();
+
+        assert_eq!(dimensions(&path).unwrap(), (40, 20));
+        assert_eq!(
+            decode(&path, &Limits::default()).unwrap().dimensions(),
+            (40, 20)
+        );
+
+        // 巨大な宣言サイズは上限まで縮小する
+        let huge = temp.path().join("huge.svg");
+        std::fs::write(
+            &huge,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100000" height="50000"/>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            decode(&huge, &Limits::default()).unwrap().dimensions(),
+            (MAX_INTRINSIC_SIZE, MAX_INTRINSIC_SIZE / 2)
+        );
+    }
+
+    #[test]
+    fn test_decode_to_fit_renders_at_target_size() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("icon.svg");
+        std::fs::write(&path, RED_SQUARE).unwrap();
+
+        let img = decode_to_fit(&path, 200, 200, &Limits::default()).unwrap();
+        assert_eq!(img.dimensions(), (200, 100));
+        // 左半分は赤、右半分は透明
+        assert_eq!(img.get_pixel(50, 50).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(150, 50).0[3], 0);
+    }
+
+    #[test]
+    fn test_external_references_are_not_loaded() {
+        let temp = TempTestDir::new_random();
+        let secret = temp.path().join("secret.png");
+        image::RgbImage::from_pixel(10, 10, image::Rgb([0, 255, 0]))
+            .save(&secret)
+            .unwrap();
+        let path = temp.path().join("external.svg");
+        std::fs::write(
+            &path,
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10">
+                    <image width="10" height="10" xlink:href="{}"/>
+                    <image width="10" height="10" xlink:href="secret.png"/>
+                </svg>"#,
+                secret.display()
+            ),
+        )
+        .unwrap();
+
+        let img = decode_to_fit(&path, 10, 10, &Limits::default()).unwrap();
+        assert_eq!(
+            img.get_pixel(5, 5).0[3],
+            0,
+            "External image should be skipped"
+        );
+    }
+
+    #[test]
+    fn test_error_for_invalid_svg() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("broken.svg");
+        std::fs::write(&path, "<svg").unwrap();
+        assert!(decode(&path, &Limits::default()).is_err());
+    }
+}
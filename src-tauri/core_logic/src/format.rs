@@ -0,0 +1,421 @@
+// 対応するファイル形式のレジストリ
+//
+// フォルダ内の画像の列挙、兄弟コンテナの検出、サムネイル生成で同じレジストリを参照する。
+// プロセス全体で1つのレジストリを共有し、実行時に差し替えられる（`configure`）。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// ファイル形式の扱い方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FormatKind {
+    /// 1枚の画像
+    Image,
+    /// 画像として表示でき、ページのコンテナとしても開ける（複数ページの TIFF など）
+    MultiPageImage,
+    /// 画像を含むアーカイブ
+    Archive,
+    /// ページを画像として扱う PDF 文書
+    Pdf,
+    /// 画像を読み順に並べた EPUB 書籍
+    Epub,
+}
+
+/// 画像を開くデコーダー
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FormatDecoder {
+    /// image クレート
+    #[default]
+    Standard,
+    /// libheif（`avif` / `heif` feature が必要）
+    Heif,
+    /// カメラ RAW に埋め込まれた JPEG プレビュー
+    RawPreview,
+    /// SVG のラスタライズ
+    Svg,
+}
+
+/// 拡張子ごとの形式の情報
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatEntry {
+    /// 拡張子（小文字、ドットなし）
+    pub extension: String,
+    pub kind: FormatKind,
+    pub mime_type: String,
+    /// 画像としてデコード（サムネイル生成）できるか
+    ///
+    /// false の場合は一覧には含めるが、サムネイルは生成しない。
+    #[serde(default = "default_decodable")]
+    pub decodable: bool,
+    #[serde(default)]
+    pub decoder: FormatDecoder,
+}
+
+fn default_decodable() -> bool {
+    true
+}
+
+impl FormatEntry {
+    pub fn new(extension: &str, kind: FormatKind, mime_type: &str) -> Self {
+        Self {
+            extension: extension.to_ascii_lowercase(),
+            kind,
+            mime_type: mime_type.to_string(),
+            decodable: matches!(kind, FormatKind::Image | FormatKind::MultiPageImage),
+            decoder: FormatDecoder::Standard,
+        }
+    }
+
+    /// デコーダーを指定
+    pub fn with_decoder(mut self, decoder: FormatDecoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// 画像として扱う形式か
+    pub fn is_image(&self) -> bool {
+        matches!(self.kind, FormatKind::Image | FormatKind::MultiPageImage)
+    }
+}
+
+/// 拡張子から形式を引くレジストリ
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatRegistry {
+    entries: BTreeMap<String, FormatEntry>,
+}
+
+impl FormatRegistry {
+    /// 空のレジストリを作成
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// 組み込みの形式を登録したレジストリを作成
+    pub fn builtin() -> Self {
+        use FormatDecoder::*;
+        use FormatKind::*;
+
+        // 拡張子, 種類, MIME タイプ, デコーダー
+        let builtin_formats: &[(&str, FormatKind, &str, FormatDecoder)] = &[
+            ("jpg", Image, "image/jpeg", Standard),
+            ("jpeg", Image, "image/jpeg", Standard),
+            ("png", Image, "image/png", Standard),
+            ("gif", Image, "image/gif", Standard),
+            ("webp", Image, "image/webp", Standard),
+            ("bmp", Image, "image/bmp", Standard),
+            ("tif", MultiPageImage, "image/tiff", Standard),
+            ("tiff", MultiPageImage, "image/tiff", Standard),
+            ("tga", Image, "image/x-tga", Standard),
+            ("ico", Image, "image/vnd.microsoft.icon", Standard),
+            ("pbm", Image, "image/x-portable-bitmap", Standard),
+            ("pgm", Image, "image/x-portable-graymap", Standard),
+            ("ppm", Image, "image/x-portable-pixmap", Standard),
+            ("pnm", Image, "image/x-portable-anymap", Standard),
+            #[cfg(feature = "avif")]
+            ("avif", Image, "image/avif", Heif),
+            #[cfg(feature = "heif")]
+            ("heic", Image, "image/heic", Heif),
+            #[cfg(feature = "heif")]
+            ("heif", Image, "image/heif", Heif),
+            ("cr2", Image, "image/x-canon-cr2", RawPreview),
+            ("nef", Image, "image/x-nikon-nef", RawPreview),
+            ("arw", Image, "image/x-sony-arw", RawPreview),
+            ("dng", Image, "image/x-adobe-dng", RawPreview),
+            ("svg", Image, "image/svg+xml", Svg),
+            ("svgz", Image, "image/svg+xml", Svg),
+            ("zip", Archive, "application/zip", Standard),
+            ("cbz", Archive, "application/vnd.comicbook+zip", Standard),
+            ("pdf", Pdf, "application/pdf", Standard),
+            ("epub", Epub, "application/epub+zip", Standard),
+        ];
+
+        let mut registry = Self::empty();
+        for (extension, kind, mime_type, decoder) in builtin_formats {
+            registry
+                .register(FormatEntry::new(extension, *kind, mime_type).with_decoder(*decoder))
+                .expect("Builtin format should be valid");
+        }
+        registry
+    }
+
+    /// エントリのリストからレジストリを作成（同じ拡張子は後のエントリで上書き）
+    pub fn from_entries<I: IntoIterator<Item = FormatEntry>>(entries: I) -> Result<Self, String> {
+        let mut registry = Self::empty();
+        for entry in entries {
+            registry.register(entry)?;
+        }
+        Ok(registry)
+    }
+
+    /// 形式を登録する（同じ拡張子が登録済みの場合は上書き）
+    pub fn register(&mut self, mut entry: FormatEntry) -> Result<(), String> {
+        let extension = entry.extension.trim_start_matches('.').to_ascii_lowercase();
+        if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid extension: '{}'", entry.extension));
+        }
+        entry.extension = extension.clone();
+        self.entries.insert(extension, entry);
+        Ok(())
+    }
+
+    /// 形式の登録を解除する
+    pub fn unregister(&mut self, extension: &str) -> Option<FormatEntry> {
+        self.entries
+            .remove(&extension.trim_start_matches('.').to_ascii_lowercase())
+    }
+
+    /// 拡張子から形式を取得（大文字小文字は区別しない）
+    pub fn lookup(&self, extension: &str) -> Option<&FormatEntry> {
+        self.entries.get(&extension.to_ascii_lowercase())
+    }
+
+    /// パスの拡張子から形式を取得
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&FormatEntry> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        self.lookup(extension)
+    }
+
+    /// 登録されている形式（拡張子順）
+    pub fn entries(&self) -> impl Iterator<Item = &FormatEntry> {
+        self.entries.values()
+    }
+
+    /// 画像として一覧に含める形式か
+    pub fn is_image<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.get(path).is_some_and(FormatEntry::is_image)
+    }
+
+    /// 複数ページを持ちうる画像形式か
+    pub fn is_multi_page_image<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.kind_of(path) == Some(FormatKind::MultiPageImage)
+    }
+
+    /// 画像を含むアーカイブか
+    pub fn is_archive<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.kind_of(path) == Some(FormatKind::Archive)
+    }
+
+    /// フォルダのように開ける（兄弟コンテナとして扱う）ファイル形式か
+    pub fn is_container_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        matches!(
+            self.kind_of(path),
+            Some(FormatKind::Archive | FormatKind::Pdf | FormatKind::Epub)
+        )
+    }
+
+    /// 画像としてデコードできる形式か
+    pub fn can_decode<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.get(path)
+            .is_some_and(|entry| entry.is_image() && entry.decodable)
+    }
+
+    /// 画像を開くデコーダーを取得（デコードできない形式は `None`）
+    pub fn decoder<P: AsRef<Path>>(&self, path: P) -> Option<FormatDecoder> {
+        self.get(path)
+            .filter(|entry| entry.is_image() && entry.decodable)
+            .map(|entry| entry.decoder)
+    }
+
+    /// MIME タイプを取得
+    pub fn mime_type<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.get(path).map(|entry| entry.mime_type.as_str())
+    }
+
+    /// JSON ファイル（[`FormatEntry`] の配列）から読み込む
+    ///
+    /// ファイルが存在しない場合は組み込みの形式を返す。
+    pub fn load<P: AsRef<Path>>(file_path: P) -> std::io::Result<Self> {
+        let entries: Vec<FormatEntry> = match std::fs::read(file_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::builtin()),
+            Err(e) => return Err(e),
+        };
+        Self::from_entries(entries)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// JSON ファイルに保存する
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> std::io::Result<()> {
+        crate::utils::write_json_atomic(file_path.as_ref(), &self.entries().collect::<Vec<_>>())
+    }
+
+    fn kind_of<P: AsRef<Path>>(&self, path: P) -> Option<FormatKind> {
+        self.get(path).map(|entry| entry.kind)
+    }
+}
+
+/// プロセス全体で共有するレジストリ
+static REGISTRY: LazyLock<RwLock<Arc<FormatRegistry>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(FormatRegistry::builtin())));
+
+/// 現在のレジストリを取得
+pub fn registry() -> Arc<FormatRegistry> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// レジストリを差し替える（以降の一覧・サムネイル生成に反映される）
+pub fn configure(registry: FormatRegistry) {
+    *REGISTRY.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(registry);
+}
+
+/// 拡張子が画像として一覧に含める形式か（大文字小文字は区別しない）
+pub fn is_supported_image<P: AsRef<Path>>(path: P) -> bool {
+    registry().is_image(path)
+}
+
+/// 複数ページを持ちうる画像形式か（大文字小文字は区別しない）
+pub fn is_multi_page_image<P: AsRef<Path>>(path: P) -> bool {
+    registry().is_multi_page_image(path)
+}
+
+/// 画像を含むアーカイブ形式か（大文字小文字は区別しない）
+pub fn is_archive<P: AsRef<Path>>(path: P) -> bool {
+    registry().is_archive(path)
+}
+
+/// フォルダのように開けるファイル形式か（大文字小文字は区別しない）
+pub fn is_container_file<P: AsRef<Path>>(path: P) -> bool {
+    registry().is_container_file(path)
+}
+
+/// 画像としてデコードできる形式か（大文字小文字は区別しない）
+pub fn can_decode<P: AsRef<Path>>(path: P) -> bool {
+    registry().can_decode(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_images() {
+        let registry = FormatRegistry::builtin();
+        assert!(registry.is_image("/photos/a.jpg"));
+        assert!(registry.is_image("/photos/a.WEBP"));
+        assert!(registry.is_image("/photos/anim.gif"));
+        assert!(registry.is_image("/scans/page.bmp"));
+        assert!(registry.is_image("/scans/doc.TIF"));
+        assert!(registry.is_image("/textures/a.tga"));
+        assert!(registry.is_image("/icons/a.svg"));
+        assert!(!registry.is_image("/photos/notes.txt"));
+        assert!(!registry.is_image("/photos/no_extension"));
+        assert!(!registry.is_image("/books/a.zip"));
+        assert_eq!(registry.is_image("/photos/a.heic"), cfg!(feature = "heif"));
+        assert_eq!(registry.is_image("/photos/a.avif"), cfg!(feature = "avif"));
+    }
+
+    #[test]
+    fn test_builtin_kinds_and_mime_types() {
+        let registry = FormatRegistry::builtin();
+        assert!(registry.is_multi_page_image("/scans/doc.tiff"));
+        assert!(!registry.is_multi_page_image("/scans/page.bmp"));
+        assert!(registry.is_archive("/books/a.ZIP"));
+        assert!(!registry.is_archive("/books/a.pdf"));
+        assert!(registry.is_container_file("/books/a.pdf"));
+        assert!(registry.is_container_file("/books/a.zip"));
+        assert!(registry.is_container_file("/books/a.epub"));
+        assert!(!registry.is_archive("/books/a.epub"));
+        assert!(!registry.is_container_file("/scans/doc.tiff"));
+        assert!(!registry.can_decode("/books/a.pdf"));
+        assert!(!registry.can_decode("/books/a.zip"));
+        assert!(registry.can_decode("/photos/a.png"));
+        assert_eq!(registry.mime_type("/photos/a.JPG"), Some("image/jpeg"));
+        assert_eq!(registry.mime_type("/photos/a.txt"), None);
+        assert_eq!(
+            registry.decoder("/photos/a.NEF"),
+            Some(FormatDecoder::RawPreview)
+        );
+        assert_eq!(
+            registry.decoder("/photos/a.png"),
+            Some(FormatDecoder::Standard)
+        );
+        assert_eq!(registry.decoder("/books/a.zip"), None);
+    }
+
+    #[test]
+    fn test_register_and_unregister() {
+        let mut registry = FormatRegistry::builtin();
+        registry
+            .register(FormatEntry::new(".JFIF", FormatKind::Image, "image/jpeg"))
+            .unwrap();
+        assert!(registry.is_image("/photos/a.jfif"));
+        assert_eq!(registry.lookup("jfif").unwrap().extension, "jfif");
+
+        // 一覧には含めるがデコードしない形式
+        let mut listed_only =
+            FormatEntry::new("psd", FormatKind::Image, "image/vnd.adobe.photoshop");
+        listed_only.decodable = false;
+        registry.register(listed_only).unwrap();
+        assert!(registry.is_image("/art/a.psd"));
+        assert!(!registry.can_decode("/art/a.psd"));
+
+        assert!(registry.unregister("GIF").is_some());
+        assert!(!registry.is_image("/photos/anim.gif"));
+        assert!(registry.unregister("gif").is_none());
+    }
+
+    #[test]
+    fn test_register_rejects_invalid_extension() {
+        let mut registry = FormatRegistry::empty();
+        assert!(registry
+            .register(FormatEntry::new("", FormatKind::Image, "image/png"))
+            .is_err());
+        assert!(registry
+            .register(FormatEntry::new(
+                "tar.gz",
+                FormatKind::Archive,
+                "application/gzip"
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_entries_round_trip_through_json() {
+        let registry = FormatRegistry::builtin();
+        let json = serde_json::to_string(&registry.entries().collect::<Vec<_>>()).unwrap();
+        let entries: Vec<FormatEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(FormatRegistry::from_entries(entries).unwrap(), registry);
+
+        // decodable は省略可能
+        let entries: Vec<FormatEntry> = serde_json::from_str(
+            r#"[{"extension": "jxl", "kind": "image", "mimeType": "image/jxl"}]"#,
+        )
+        .unwrap();
+        assert!(entries[0].decodable);
+        assert_eq!(entries[0].decoder, FormatDecoder::Standard);
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let temp = crate::test_helper::test_helpers::TempTestDir::new_random();
+        let file_path = temp.path().join("config").join("formats.json");
+
+        // ファイルがなければ組み込みの形式
+        assert_eq!(
+            FormatRegistry::load(&file_path).unwrap(),
+            FormatRegistry::builtin()
+        );
+
+        let mut registry = FormatRegistry::builtin();
+        registry.unregister("gif");
+        registry.save(&file_path).unwrap();
+        assert_eq!(FormatRegistry::load(&file_path).unwrap(), registry);
+
+        std::fs::write(&file_path, b"not json").unwrap();
+        assert!(FormatRegistry::load(&file_path).is_err());
+    }
+
+    #[test]
+    fn test_free_functions_use_shared_registry() {
+        assert!(is_supported_image("/photos/a.png"));
+        assert!(is_multi_page_image("/scans/doc.tiff"));
+        assert!(is_archive("/books/a.zip"));
+        assert!(can_decode("/photos/a.png"));
+    }
+}
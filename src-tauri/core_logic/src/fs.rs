@@ -1,3 +1,4 @@
+use crate::image_container::metadata::ContainerMetadata;
 use crate::image_container::reader_config::ImageContainerReaderConfig;
 use crate::image_container::ImageContainerReader;
 use crate::CommandError;
@@ -26,6 +27,18 @@ pub fn list_images_in_container<P: AsRef<std::path::Path>, Q: AsRef<std::path::P
     reader.list_images_in_container(container_path)
 }
 
+///
+/// 指定されたコンテナの情報（書名・著者・ページの進行方向など）を取得します。
+///
+pub fn get_container_metadata<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    container_path: P,
+    cache_dir: Q,
+) -> Result<ContainerMetadata, CommandError> {
+    let reader = ImageContainerReader::new(ImageContainerReaderConfig::new(cache_dir.as_ref()));
+
+    reader.get_container_metadata(container_path.as_ref())
+}
+
 ///
 /// 指定されたパスの兄弟コンテナ（フォルダやアーカイブ）を取得します。
 ///
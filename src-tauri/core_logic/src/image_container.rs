@@ -1,12 +1,25 @@
 pub mod archive;
+mod comic_info;
+pub mod epub;
 pub mod folder;
+pub mod metadata;
+pub mod pdf;
 pub mod reader_config;
+pub mod tiff;
 
+use crate::format::{registry, FormatKind};
 use crate::image_container::{
+    epub::EpubImageContainer,
     folder::{get_sibling_archives, get_sibling_folders, FolderImageContainer},
+    metadata::ContainerMetadata,
+    pdf::PdfImageContainer,
     reader_config::ImageContainerReaderConfig,
+    tiff::TiffImageContainer,
 };
-use std::path::PathBuf;
+use crate::utils::hash_path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
 
 // A custom error type for command errors
 #[derive(Debug, serde::Serialize, PartialEq)]
@@ -17,6 +30,7 @@ pub enum CommandError {
     UnsupportedExtension(String),
     NotSpecifiedArchive(String),
     NotAnArchive(String),
+    InvalidImage(String),
 }
 
 impl From<std::io::Error> for CommandError {
@@ -84,10 +98,156 @@ impl ImageContainerReader {
             return folder_container.list_images();
         }
 
+        match registry().get(container_path).map(|entry| entry.kind) {
+            Some(FormatKind::MultiPageImage) => {
+                let tiff_container = TiffImageContainer::new(container_path, self.config.clone())?;
+                return tiff_container.list_images();
+            }
+            Some(FormatKind::Pdf) => {
+                let pdf_container = PdfImageContainer::new(container_path, self.config.clone())?;
+                return pdf_container.list_images();
+            }
+            Some(FormatKind::Epub) => {
+                let epub_container = EpubImageContainer::new(container_path, self.config.clone())?;
+                return epub_container.list_images();
+            }
+            _ => {}
+        }
+
         let archive_container =
             archive::ArchiveImageContainer::new(container_path, self.config.clone())?;
         archive_container.list_images_in_archive()
     }
+
+    ///
+    /// コンテナの情報（書名・著者・ページの進行方向など）を返す。
+    /// 情報を持たないコンテナ（フォルダなど）の場合は空の情報を返す。
+    ///
+    pub fn get_container_metadata<P: AsRef<std::path::Path>>(
+        &self,
+        container_path: P,
+    ) -> Result<ContainerMetadata, CommandError> {
+        let container_path = container_path.as_ref();
+
+        if !container_path.exists() {
+            return Err(CommandError::PathNotFound(
+                container_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        if container_path.is_dir() {
+            return Ok(ContainerMetadata::default());
+        }
+
+        match registry().get(container_path).map(|entry| entry.kind) {
+            Some(FormatKind::Epub) => {
+                EpubImageContainer::new(container_path, self.config.clone())?.metadata()
+            }
+            Some(FormatKind::Archive) => {
+                archive::ArchiveImageContainer::new(container_path, self.config.clone())?.metadata()
+            }
+            _ => Ok(ContainerMetadata::default()),
+        }
+    }
+}
+
+///
+/// 展開元のファイルの展開先ディレクトリ名を返す。
+///
+/// パスに加えて更新日時とサイズを含めるため、ファイルが変更されると別のディレクトリになる。
+///
+pub(crate) fn source_extract_key(source_path: &Path) -> Result<String, CommandError> {
+    let metadata = std::fs::metadata(source_path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let fingerprint = hash_path(&format!("{}:{}", modified, metadata.len()));
+    Ok(format!(
+        "{}-{}",
+        hash_path(&source_path),
+        &fingerprint[..16]
+    ))
+}
+
+///
+/// ページ画像を展開先ディレクトリに書き出す（TIFF・PDF・EPUB で共通）。
+///
+/// `write_pages` は渡されたディレクトリに各ページの画像を書き出す。
+/// 既に展開済みのディレクトリが存在する場合は、書き出さずにそのディレクトリのパスを返す。
+/// 展開し直すときは、変更前のファイルの展開結果を削除する。
+///
+pub(crate) fn extract_pages_once<F>(
+    config: &ImageContainerReaderConfig,
+    source_path: &Path,
+    write_pages: F,
+) -> Result<PathBuf, CommandError>
+where
+    F: FnOnce(&Path) -> Result<(), CommandError>,
+{
+    let key = source_extract_key(source_path)?;
+    let extract_dir = config.get_extract_dir().join(&key);
+    if extract_dir.exists() {
+        return Ok(extract_dir);
+    }
+    remove_stale_extractions(config.get_extract_dir(), source_path, &key);
+
+    // 途中で失敗しても中途半端なディレクトリが残らないよう、一時ディレクトリに書き出してから置き換える。
+    // 同じファイルを同時に展開する呼び出しと競合しないよう、一時ディレクトリは呼び出しごとに分ける
+    let partial_dir = config.get_extract_dir().join(format!(
+        "{}{}-{}",
+        partial_prefix(&key),
+        std::process::id(),
+        NEXT_PARTIAL_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&partial_dir)?;
+
+    if let Err(e) = write_pages(&partial_dir) {
+        let _ = std::fs::remove_dir_all(&partial_dir);
+        return Err(e);
+    }
+
+    if let Err(e) = std::fs::rename(&partial_dir, &extract_dir) {
+        let _ = std::fs::remove_dir_all(&partial_dir);
+        // 他の呼び出しが先に展開を終えた場合は、その展開結果を使う
+        if !extract_dir.exists() {
+            return Err(e.into());
+        }
+    }
+    Ok(extract_dir)
+}
+
+/// 展開中の一時ディレクトリの番号（同じプロセス内の呼び出しを区別する）
+static NEXT_PARTIAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 展開中の一時ディレクトリ名の接頭辞（後ろにプロセス ID と番号が続く）
+fn partial_prefix(key: &str) -> String {
+    format!("{}.partial-", key)
+}
+
+/// 同じファイルの、現在の `key` 以外（変更前）の展開結果と、以前のプロセスが残した一時ディレクトリを削除する
+///
+/// このプロセスの一時ディレクトリは、他のスレッドが展開中の場合があるため削除しない。
+fn remove_stale_extractions(extract_base: &Path, source_path: &Path, key: &str) {
+    let prefix = format!("{}-", hash_path(&source_path));
+    let own_partial = format!("{}{}-", partial_prefix(key), std::process::id());
+    let Ok(entries) = std::fs::read_dir(extract_base) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_stale = if name.starts_with(key) {
+            name.starts_with(&partial_prefix(key)) && !name.starts_with(&own_partial)
+        } else {
+            name.starts_with(&prefix)
+        };
+        if is_stale {
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                log::warn!("Failed to remove stale pages {:?}: {}", entry.path(), e);
+            }
+        }
+    }
 }
 
 ///
@@ -163,6 +323,184 @@ mod tests {
             // Assert
             assert!(matches!(result, Err(CommandError::PathNotFound(_))));
         }
+
+        #[test]
+        fn returns_legacy_raster_images_in_folder() {
+            // Arrange
+            let temp_dir = TempTestDir::new_random();
+            for name in [
+                "scan.bmp",
+                "scan.TIFF",
+                "texture.tga",
+                "icon.ico",
+                "gray.pgm",
+            ] {
+                File::create(temp_dir.path().join(name)).unwrap();
+            }
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act
+            let images = reader.list_images_in_container(temp_dir.path()).unwrap();
+
+            // Assert
+            assert_eq!(images.len(), 5);
+        }
+
+        #[test]
+        fn returns_camera_raw_files() {
+            // Arrange
+            let temp_dir = TempTestDir::new_random();
+            for name in ["a.CR2", "b.nef", "c.arw", "d.dng", "e.xmp"] {
+                File::create(temp_dir.path().join(name)).unwrap();
+            }
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act
+            let images = reader.list_images_in_container(temp_dir.path()).unwrap();
+
+            // Assert
+            assert_eq!(images.len(), 4, "Sidecar files should be skipped");
+        }
+
+        #[test]
+        fn returns_pages_of_multi_page_tiff() {
+            // Arrange
+            let temp_dir = TempTestDir::new_random();
+            let tiff_path = temp_dir.path().join("scan.tiff");
+            let mut encoder =
+                ::tiff::encoder::TiffEncoder::new(File::create(&tiff_path).unwrap()).unwrap();
+            for _ in 0..2 {
+                encoder
+                    .write_image::<::tiff::encoder::colortype::Gray8>(2, 2, &[0; 4])
+                    .unwrap();
+            }
+            drop(encoder);
+            let extract_dir = TempTestDir::new_random();
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(extract_dir.path()));
+
+            // Act
+            let images = reader.list_images_in_container(&tiff_path).unwrap();
+
+            // Assert
+            assert_eq!(images.len(), 2);
+        }
+
+        #[test]
+        fn returns_pages_of_pdf() {
+            // Arrange
+            use crate::image_container::pdf::test::{create_pdf, TestPage};
+            let temp_dir = TempTestDir::new_random();
+            let pdf_path = temp_dir.path().join("book.PDF");
+            create_pdf(&pdf_path, &[TestPage::Jpeg(80, 80), TestPage::Rgb(80, 80)]);
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act
+            let images = reader.list_images_in_container(&pdf_path).unwrap();
+
+            // Assert
+            assert_eq!(images.len(), 2);
+        }
+
+        #[test]
+        fn returns_pages_of_epub() {
+            // Arrange
+            use crate::image_container::epub::test::create_epub;
+            let temp_dir = TempTestDir::new_random();
+            let epub_path = temp_dir.path().join("book.epub");
+            create_epub(&epub_path, "");
+            let reader = ImageContainerReader::new(ImageContainerReaderConfig::new(
+                temp_dir.path().join("extract"),
+            ));
+
+            // Act
+            let images = reader.list_images_in_container(&epub_path).unwrap();
+
+            // Assert
+            assert_eq!(images.len(), 3);
+        }
+    }
+
+    #[cfg(test)]
+    mod get_container_metadata_test {
+        use super::*;
+        use crate::image_container::metadata::PageProgression;
+        use std::fs::File;
+
+        #[test]
+        fn returns_metadata_of_epub() {
+            // Arrange
+            use crate::image_container::epub::test::create_epub;
+            let temp_dir = TempTestDir::new_random();
+            let epub_path = temp_dir.path().join("book.epub");
+            create_epub(&epub_path, r#" page-progression-direction="rtl""#);
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act
+            let metadata = reader.get_container_metadata(&epub_path).unwrap();
+
+            // Assert
+            assert_eq!(metadata.title.as_deref(), Some("Test Comic"));
+            assert_eq!(metadata.page_progression, Some(PageProgression::Rtl));
+        }
+
+        #[test]
+        fn returns_comic_info_of_cbz() {
+            // Arrange
+            let temp_dir = TempTestDir::new_random();
+            let comic_info = temp_dir.path().join("ComicInfo.xml");
+            std::fs::write(
+                &comic_info,
+                r#"<ComicInfo><Series>Series</Series><Manga>YesAndRightToLeft</Manga>
+                <Pages><Page Image="0" Type="FrontCover"/><Page Image="2" DoublePage="true"/></Pages>
+                </ComicInfo>"#,
+            )
+            .unwrap();
+            let image = temp_dir.path().join("001.jpg");
+            File::create(&image).unwrap();
+            let cbz_path = temp_dir.path().join("book.cbz");
+            TempTestDir::create_zip(&cbz_path, vec![&comic_info, &image]).unwrap();
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act
+            let metadata = reader.get_container_metadata(&cbz_path).unwrap();
+
+            // Assert
+            assert_eq!(metadata.series.as_deref(), Some("Series"));
+            assert_eq!(metadata.page_progression, Some(PageProgression::Rtl));
+            assert_eq!(metadata.pages[0].page_type.as_deref(), Some("FrontCover"));
+            assert!(metadata.pages[1].double_page);
+        }
+
+        #[test]
+        fn returns_empty_metadata_for_folder_and_zip_without_comic_info() {
+            // Arrange
+            let temp_dir = TempTestDir::new_random();
+            TempTestDir::create_zip(
+                temp_dir.path().join("a.zip"),
+                Vec::<std::path::PathBuf>::new(),
+            )
+            .unwrap();
+            let reader =
+                ImageContainerReader::new(ImageContainerReaderConfig::new(temp_dir.path()));
+
+            // Act & Assert
+            assert_eq!(
+                reader.get_container_metadata(temp_dir.path()).unwrap(),
+                ContainerMetadata::default()
+            );
+            assert_eq!(
+                reader
+                    .get_container_metadata(temp_dir.path().join("a.zip"))
+                    .unwrap(),
+                ContainerMetadata::default()
+            );
+        }
     }
 
     #[cfg(test)]
@@ -181,6 +519,9 @@ mod tests {
             create_dir_all(base.path().join("B")).unwrap();
             File::create(base.path().join("a.zip")).unwrap();
             File::create(base.path().join("b.ZIP")).unwrap();
+            File::create(base.path().join("c.pdf")).unwrap();
+            File::create(base.path().join("d.tiff")).unwrap();
+            File::create(base.path().join("e.epub")).unwrap();
             // Act
             let current_path = base.path().join("B").to_string_lossy().to_string();
             let mut result = get_sibling_containers(current_path).unwrap();
@@ -191,6 +532,8 @@ mod tests {
                 // B is the current folder, so it should be excluded
                 base.path().join("a.zip").to_string_lossy().to_string(),
                 base.path().join("b.ZIP").to_string_lossy().to_string(),
+                base.path().join("c.pdf").to_string_lossy().to_string(),
+                base.path().join("e.epub").to_string_lossy().to_string(),
             ];
             expected.sort();
             assert_eq!(expected, result);
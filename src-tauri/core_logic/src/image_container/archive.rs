@@ -1,11 +1,12 @@
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use zip::ZipArchive;
 
 use crate::{
     image_container::{
-        folder::FolderImageContainer, reader_config::ImageContainerReaderConfig, CommandError,
-        ImageContainer,
+        comic_info, folder::FolderImageContainer, metadata::ContainerMetadata,
+        reader_config::ImageContainerReaderConfig, CommandError, ImageContainer,
     },
     utils::hash_path,
 };
@@ -38,6 +39,62 @@ impl ArchiveImageContainer {
     ///
     pub fn list_images_in_archive(&self) -> Result<Vec<String>, CommandError> {
         let container_path = self.source_archive_path.as_path();
+        self.check_archive()?;
+
+        let hash = hash_path(&container_path);
+        // 解凍に失敗した場合はエラーを返す
+        let extracted_dir = self.extract_archive(container_path, &hash)?;
+        let folder_container = FolderImageContainer::new(extracted_dir)?;
+        folder_container.list_images()
+    }
+
+    ///
+    /// 圧縮ファイルに含まれる ComicInfo.xml から、コンテナの情報を返す。
+    /// ComicInfo.xml がない場合や読み込めない場合は空の情報を返す。
+    ///
+    pub fn metadata(&self) -> Result<ContainerMetadata, CommandError> {
+        self.check_archive()?;
+
+        let file = std::fs::File::open(&self.source_archive_path)?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CommandError::NotAnArchive(e.to_string()))?;
+
+        // 階層の浅いものを優先する
+        let Some(name) = archive
+            .file_names()
+            .filter(|name| {
+                name.rsplit('/')
+                    .next()
+                    .is_some_and(|file_name| file_name.eq_ignore_ascii_case(comic_info::FILE_NAME))
+            })
+            .min_by_key(|name| name.matches('/').count())
+            .map(str::to_string)
+        else {
+            return Ok(ContainerMetadata::default());
+        };
+
+        let mut text = String::new();
+        archive
+            .by_name(&name)
+            .map_err(|e| CommandError::NotAnArchive(e.to_string()))?
+            .read_to_string(&mut text)?;
+
+        match comic_info::parse(&text) {
+            Ok(metadata) => Ok(metadata),
+            Err(e) => {
+                log::warn!(
+                    "Ignoring malformed {} in {:?}: {}",
+                    name,
+                    self.source_archive_path,
+                    e
+                );
+                Ok(ContainerMetadata::default())
+            }
+        }
+    }
+
+    fn check_archive(&self) -> Result<(), CommandError> {
+        let container_path = self.source_archive_path.as_path();
 
         // if the container is directory, list images in the directory
         if container_path.is_dir() {
@@ -53,12 +110,7 @@ impl ArchiveImageContainer {
                 container_path.to_string_lossy().to_string(),
             ));
         }
-
-        let hash = hash_path(&container_path);
-        // 解凍に失敗した場合はエラーを返す
-        let extracted_dir = self.extract_archive(container_path, &hash)?;
-        let folder_container = FolderImageContainer::new(extracted_dir)?;
-        folder_container.list_images()
+        Ok(())
     }
 
     ///
@@ -0,0 +1,144 @@
+// ComicInfo.xml（ComicRack 形式のコミック情報）の読み込み
+//
+// https://anansi-project.github.io/docs/comicinfo/intro
+
+use roxmltree::{Document, Node};
+
+use crate::image_container::metadata::{ContainerMetadata, PageInfo, PageProgression};
+
+/// アーカイブ内の ComicInfo.xml のファイル名（大文字・小文字は区別しない）
+pub(crate) const FILE_NAME: &str = "ComicInfo.xml";
+
+///
+/// ComicInfo.xml の内容をコンテナの情報に変換する。
+///
+/// `Writer` はカンマ区切りで著者に、`Manga` が `YesAndRightToLeft` の場合はページの進行方向を右から左にする。
+///
+pub(crate) fn parse(text: &str) -> Result<ContainerMetadata, roxmltree::Error> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    let field = |name: &str| {
+        root.children()
+            .find(|node| node.has_tag_name(name))
+            .and_then(|node| node.text())
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let manga = field("Manga");
+    // `Yes` だけでは進行方向が分からないため、明示された場合のみ右から左にする
+    let page_progression =
+        (manga.as_deref() == Some("YesAndRightToLeft")).then_some(PageProgression::Rtl);
+
+    Ok(ContainerMetadata {
+        title: field("Title"),
+        authors: field("Writer")
+            .map(|writers| {
+                writers
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|writer| !writer.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        page_progression,
+        series: field("Series"),
+        volume: field("Volume").and_then(|volume| volume.parse().ok()),
+        number: field("Number"),
+        manga: match manga.as_deref() {
+            Some("Yes" | "YesAndRightToLeft") => Some(true),
+            Some("No") => Some(false),
+            _ => None,
+        },
+        pages: root
+            .children()
+            .filter(|node| node.has_tag_name("Pages"))
+            .flat_map(|pages| pages.children())
+            .filter(|node| node.has_tag_name("Page"))
+            .filter_map(|node| page_info(&node))
+            .collect(),
+    })
+}
+
+fn page_info(node: &Node) -> Option<PageInfo> {
+    Some(PageInfo {
+        index: node.attribute("Image")?.trim().parse().ok()?,
+        page_type: node
+            .attribute("Type")
+            .map(str::trim)
+            .filter(|page_type| !page_type.is_empty())
+            .map(str::to_string),
+        double_page: node
+            .attribute("DoublePage")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_comic_info() {
+        let metadata = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Title>Episode 1</Title>
+  <Series>Test Series</Series>
+  <Number>1.5</Number>
+  <Volume>3</Volume>
+  <Writer>Author A, Author B</Writer>
+  <Manga>YesAndRightToLeft</Manga>
+  <Pages>
+    <Page Image="0" Type="FrontCover" ImageWidth="800" ImageHeight="1200"/>
+    <Page Image="1" Type="Story"/>
+    <Page Image="4" DoublePage="True"/>
+    <Page Type="Story"/>
+  </Pages>
+</ComicInfo>"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("Episode 1"));
+        assert_eq!(metadata.series.as_deref(), Some("Test Series"));
+        assert_eq!(metadata.number.as_deref(), Some("1.5"));
+        assert_eq!(metadata.volume, Some(3));
+        assert_eq!(metadata.authors, vec!["Author A", "Author B"]);
+        assert_eq!(metadata.manga, Some(true));
+        assert_eq!(metadata.page_progression, Some(PageProgression::Rtl));
+        assert_eq!(
+            metadata.pages,
+            vec![
+                PageInfo {
+                    index: 0,
+                    page_type: Some("FrontCover".to_string()),
+                    double_page: false,
+                },
+                PageInfo {
+                    index: 1,
+                    page_type: Some("Story".to_string()),
+                    double_page: false,
+                },
+                PageInfo {
+                    index: 4,
+                    page_type: None,
+                    double_page: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_minimal_comic_info() {
+        let metadata =
+            parse("<ComicInfo><Manga>Unknown</Manga><Volume>x</Volume></ComicInfo>").unwrap();
+        assert_eq!(metadata.manga, None);
+        assert_eq!(metadata.page_progression, None);
+        assert_eq!(metadata.volume, None);
+        assert!(metadata.pages.is_empty());
+
+        assert!(parse("<ComicInfo>").is_err());
+    }
+}
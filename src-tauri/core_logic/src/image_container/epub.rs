@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node, ParsingOptions};
+use zip::ZipArchive;
+
+use crate::{
+    format::{is_supported_image, registry, FormatKind},
+    image_container::{
+        extract_pages_once,
+        folder::FolderImageContainer,
+        metadata::{ContainerMetadata, PageProgression},
+        reader_config::ImageContainerReaderConfig,
+        CommandError, ImageContainer,
+    },
+    utils::natural_cmp_file_name,
+};
+
+const CONTAINER_XML: &str = "META-INF/container.xml";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+///
+/// EPUB を、ページ画像のコンテナとして扱う。
+///
+/// パッケージ文書（OPF）のスパインの順序（読み順）で画像を展開先ディレクトリに書き出す。
+/// スパインの項目が XHTML の場合は、そのページに含まれる最初の画像をページ画像とする。
+/// 画像を含まないページ（目次や本文のみのページ）は含めない。
+///
+pub struct EpubImageContainer {
+    source_path: PathBuf,
+    config: ImageContainerReaderConfig,
+}
+
+impl EpubImageContainer {
+    pub fn new<P: AsRef<Path>>(
+        epub_file_path: P,
+        config: ImageContainerReaderConfig,
+    ) -> Result<Self, CommandError> {
+        let epub_file_path = epub_file_path.as_ref();
+        if !epub_file_path.exists() {
+            return Err(CommandError::PathNotFound(
+                epub_file_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(EpubImageContainer {
+            source_path: epub_file_path.to_path_buf(),
+            config,
+        })
+    }
+
+    ///
+    /// 各ページの画像ファイルのパスを読み順で返す。
+    ///
+    pub fn list_pages(&self) -> Result<Vec<String>, CommandError> {
+        self.check_extension()?;
+        let pages_dir = self.extract_pages()?;
+        // ページは spine の順に `{番号:04}` の名前で書き出してある
+        let mut pages = FolderImageContainer::new(pages_dir)?.list_images()?;
+        pages.sort_by(|a, b| natural_cmp_file_name(a, b));
+        Ok(pages)
+    }
+
+    ///
+    /// 書名・著者・ページの進行方向を返す。
+    ///
+    pub fn metadata(&self) -> Result<ContainerMetadata, CommandError> {
+        self.check_extension()?;
+        let mut archive = open_archive(&self.source_path)?;
+        Ok(read_package(&mut archive)?.metadata)
+    }
+
+    fn check_extension(&self) -> Result<(), CommandError> {
+        let source_path = self.source_path.as_path();
+        let is_epub = registry()
+            .get(source_path)
+            .is_some_and(|entry| entry.kind == FormatKind::Epub);
+        if !source_path.is_file() || !is_epub {
+            return Err(CommandError::UnsupportedExtension(
+                source_path.to_string_lossy().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    ///
+    /// ページ画像を `{読み順:04}.{拡張子}` として展開する。
+    /// 既に展開済みのディレクトリが存在する場合は、展開せずにそのディレクトリのパスを返す。
+    ///
+    fn extract_pages(&self) -> Result<PathBuf, CommandError> {
+        let source_path = self.source_path.as_path();
+        extract_pages_once(&self.config, source_path, |pages_dir| {
+            let mut archive = open_archive(source_path)?;
+            let package = read_package(&mut archive)?;
+            for (index, image) in page_images(&mut archive, &package).iter().enumerate() {
+                let extension = Path::new(image)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let data = read_entry(&mut archive, image)?;
+                std::fs::write(
+                    pages_dir.join(format!("{:04}.{}", index + 1, extension)),
+                    data,
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl ImageContainer for EpubImageContainer {
+    fn list_images(&self) -> Result<Vec<String>, CommandError> {
+        self.list_pages()
+    }
+
+    fn get_first_image(&self) -> Result<Option<String>, CommandError> {
+        let images = self.list_pages()?;
+        Ok(images.into_iter().next())
+    }
+}
+
+/// パッケージ文書（OPF）から読み取った内容
+struct Package {
+    /// OPF のアーカイブ内のパス（href の基準）
+    path: String,
+    /// スパインの項目（読み順）
+    spine: Vec<ManifestItem>,
+    metadata: ContainerMetadata,
+}
+
+#[derive(Clone)]
+struct ManifestItem {
+    href: String,
+    media_type: String,
+    properties: String,
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<File>, CommandError> {
+    let file = File::open(path)?;
+    ZipArchive::new(file).map_err(|e| CommandError::NotAnArchive(e.to_string()))
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, CommandError> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| CommandError::InvalidImage(format!("{}: {}", name, e)))?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn read_text(archive: &mut ZipArchive<File>, name: &str) -> Result<String, CommandError> {
+    String::from_utf8(read_entry(archive, name)?)
+        .map_err(|e| CommandError::InvalidImage(format!("{}: {}", name, e)))
+}
+
+fn parse_xml<'a>(text: &'a str, name: &str) -> Result<Document<'a>, CommandError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(text, options)
+        .map_err(|e| CommandError::InvalidImage(format!("{}: {}", name, e)))
+}
+
+fn read_package(archive: &mut ZipArchive<File>) -> Result<Package, CommandError> {
+    let container_text = read_text(archive, CONTAINER_XML)?;
+    let container = parse_xml(&container_text, CONTAINER_XML)?;
+    let opf_path = container
+        .descendants()
+        .find(|node| node.has_tag_name("rootfile"))
+        .and_then(|node| node.attribute("full-path"))
+        .ok_or_else(|| {
+            CommandError::InvalidImage(format!("{}: rootfile not found", CONTAINER_XML))
+        })?
+        .to_string();
+
+    let opf_text = read_text(archive, &opf_path)?;
+    let opf = parse_xml(&opf_text, &opf_path)?;
+
+    let manifest: HashMap<&str, ManifestItem> = opf
+        .descendants()
+        .filter(|node| node.has_tag_name("item"))
+        .filter_map(|node| {
+            let id = node.attribute("id")?;
+            let href = resolve_href(&opf_path, node.attribute("href")?)?;
+            Some((
+                id,
+                ManifestItem {
+                    href,
+                    media_type: node.attribute("media-type").unwrap_or_default().to_string(),
+                    properties: node.attribute("properties").unwrap_or_default().to_string(),
+                },
+            ))
+        })
+        .collect();
+
+    let spine_node = opf.descendants().find(|node| node.has_tag_name("spine"));
+    let spine = spine_node
+        .iter()
+        .flat_map(|spine| spine.children())
+        .filter(|node| node.has_tag_name("itemref") && node.attribute("linear") != Some("no"))
+        .filter_map(|node| manifest.get(node.attribute("idref")?).cloned())
+        .collect();
+
+    let page_progression =
+        match spine_node.and_then(|spine| spine.attribute("page-progression-direction")) {
+            Some("rtl") => Some(PageProgression::Rtl),
+            Some("ltr") => Some(PageProgression::Ltr),
+            _ => None,
+        };
+    let metadata = ContainerMetadata {
+        title: dc_elements(&opf, "title").into_iter().next(),
+        authors: dc_elements(&opf, "creator"),
+        page_progression,
+        ..ContainerMetadata::default()
+    };
+
+    Ok(Package {
+        path: opf_path,
+        spine,
+        metadata,
+    })
+}
+
+/// Dublin Core の要素のテキストを記載順に返す（空のものは除く）
+fn dc_elements(opf: &Document, name: &str) -> Vec<String> {
+    opf.descendants()
+        .filter(|node| node.has_tag_name((DC_NAMESPACE, name)))
+        .filter_map(|node| node.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+/// スパインの各項目からページ画像のアーカイブ内のパスを読み順で集める（重複は除く）
+fn page_images(archive: &mut ZipArchive<File>, package: &Package) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut images = Vec::new();
+    for item in &package.spine {
+        if item.properties.split_whitespace().any(|p| p == "nav") {
+            continue;
+        }
+        let image = if item.media_type.starts_with("image/") {
+            Some(item.href.clone())
+        } else if item.media_type == "application/xhtml+xml" {
+            first_image_in_page(archive, &item.href)
+        } else {
+            None
+        };
+
+        if let Some(image) = image {
+            if is_supported_image(&image) && seen.insert(image.clone()) {
+                images.push(image);
+            }
+        }
+    }
+    if images.is_empty() {
+        log::warn!("No page images found in EPUB spine: {}", package.path);
+    }
+    images
+}
+
+/// XHTML のページに含まれる最初の画像（`<img>` または SVG の `<image>`）のパスを返す
+fn first_image_in_page(archive: &mut ZipArchive<File>, page_path: &str) -> Option<String> {
+    let text = match read_text(archive, page_path) {
+        Ok(text) => text,
+        Err(e) => {
+            log::warn!("Skipping unreadable EPUB page: {:?}", e);
+            return None;
+        }
+    };
+    let page = match parse_xml(&text, page_path) {
+        Ok(page) => page,
+        Err(e) => {
+            log::warn!("Skipping malformed EPUB page: {:?}", e);
+            return None;
+        }
+    };
+    page.descendants()
+        .find_map(|node| image_source(&node))
+        .and_then(|href| resolve_href(page_path, href))
+}
+
+fn image_source<'a>(node: &Node<'a, '_>) -> Option<&'a str> {
+    match node.tag_name().name() {
+        "img" => node.attribute("src"),
+        "image" => node
+            .attribute((XLINK_NAMESPACE, "href"))
+            .or_else(|| node.attribute("href")),
+        _ => None,
+    }
+}
+
+///
+/// `base` のファイルからの相対参照 `href` を、アーカイブ内のパスに変換する。
+/// 外部の URL やアーカイブの外を指す参照の場合は `None` を返す。
+///
+fn resolve_href(base: &str, href: &str) -> Option<String> {
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    if href.is_empty() || href.contains(':') {
+        return None;
+    }
+
+    let mut segments: Vec<String> = base.split('/').map(str::to_string).collect();
+    // ファイル名を除いてディレクトリにする
+    segments.pop();
+    if href.starts_with('/') {
+        segments.clear();
+    }
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(percent_decode(segment)),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        image::RgbImage::new(width, height)
+            .write_to(&mut data, image::ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    fn xhtml(body: &str) -> Vec<u8> {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:xlink="http://www.w3.org/1999/xlink">
+<head><title>page</title></head><body>{}</body></html>"#,
+            body
+        )
+        .into_bytes()
+    }
+
+    /// 画像の幅でページを識別できる EPUB を作成する
+    ///
+    /// スパインの順序は p1（表紙・SVG ラップ）→ nav → p2（本文のみ）→ p3（`../` 参照）→ img4（画像を直接参照）。
+    /// マニフェストの記載順や画像のファイル名の順序とは一致させていない。
+    pub(crate) fn create_epub(path: &Path, direction: &str) {
+        let opf = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title> Test Comic </dc:title>
+    <dc:creator>Author A</dc:creator>
+    <dc:creator>Author B</dc:creator>
+  </metadata>
+  <manifest>
+    <item id="img4" href="images/a%20first.png" media-type="image/png"/>
+    <item id="p3" href="text/p3.xhtml" media-type="application/xhtml+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="p2" href="text/p2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="p1" href="cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="hidden" href="text/hidden.xhtml" media-type="application/xhtml+xml"/>
+    <item id="i1" href="images/z_cover.png" media-type="image/png"/>
+    <item id="i3" href="images/b.png" media-type="image/png"/>
+  </manifest>
+  <spine{}>
+    <itemref idref="p1"/>
+    <itemref idref="nav"/>
+    <itemref idref="p2"/>
+    <itemref idref="hidden" linear="no"/>
+    <itemref idref="p3"/>
+    <itemref idref="img4"/>
+  </spine>
+</package>"#,
+            direction
+        );
+
+        let files: Vec<(&str, Vec<u8>)> = vec![
+            ("mimetype", b"application/epub+zip".to_vec()),
+            (
+                CONTAINER_XML,
+                br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#
+                    .to_vec(),
+            ),
+            ("OEBPS/content.opf", opf.into_bytes()),
+            (
+                "OEBPS/cover.xhtml",
+                xhtml(
+                    r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"><image width="1" height="1" xlink:href="images/z_cover.png"/></svg>"#,
+                ),
+            ),
+            (
+                "OEBPS/nav.xhtml",
+                xhtml(r#"<nav><img src="images/nav.png"/></nav>"#),
+            ),
+            ("OEBPS/text/p2.xhtml", xhtml("<p>text only</p>")),
+            (
+                "OEBPS/text/hidden.xhtml",
+                xhtml(r#"<img src="../images/hidden.png"/>"#),
+            ),
+            (
+                "OEBPS/text/p3.xhtml",
+                xhtml(r#"<div><img src="../images/b.png#frag" alt=""/></div>"#),
+            ),
+            ("OEBPS/images/z_cover.png", png(1, 1)),
+            ("OEBPS/images/b.png", png(3, 1)),
+            ("OEBPS/images/a first.png", png(4, 1)),
+            ("OEBPS/images/nav.png", png(5, 1)),
+            ("OEBPS/images/hidden.png", png(6, 1)),
+        ];
+
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn widths(pages: &[String]) -> Vec<u32> {
+        pages
+            .iter()
+            .map(|page| image::image_dimensions(page).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn returns_images_in_spine_order() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let epub_path = base.path().join("book.epub");
+        create_epub(&epub_path, "");
+        let config = ImageContainerReaderConfig::new(base.path().join("extract"));
+        let container = EpubImageContainer::new(&epub_path, config).unwrap();
+
+        // Act
+        let pages = container.list_pages().unwrap();
+
+        // Assert
+        // 表紙（SVG ラップ）→ p3（../ 参照）→ 直接参照の画像。nav・本文のみ・linear="no" のページは含めない
+        assert_eq!(widths(&pages), vec![1, 3, 4]);
+        assert!(pages[0].ends_with("0001.png"));
+        // 2回目は展開済みのページを返す
+        assert_eq!(container.list_pages().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn returns_book_metadata() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let epub_path = base.path().join("book.EPUB");
+        create_epub(&epub_path, r#" page-progression-direction="rtl""#);
+        let config = ImageContainerReaderConfig::new(base.path());
+        let container = EpubImageContainer::new(&epub_path, config).unwrap();
+
+        // Act
+        let metadata = container.metadata().unwrap();
+
+        // Assert
+        assert_eq!(metadata.title.as_deref(), Some("Test Comic"));
+        assert_eq!(metadata.authors, vec!["Author A", "Author B"]);
+        assert_eq!(metadata.page_progression, Some(PageProgression::Rtl));
+    }
+
+    #[test]
+    fn returns_error_when_package_is_missing() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let epub_path = base.path().join("broken.epub");
+        let mut zip = zip::ZipWriter::new(File::create(&epub_path).unwrap());
+        zip.start_file("mimetype", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"application/epub+zip").unwrap();
+        zip.finish().unwrap();
+        let config = ImageContainerReaderConfig::new(base.path());
+        let container = EpubImageContainer::new(&epub_path, config).unwrap();
+
+        // Act
+        let result = container.list_pages();
+
+        // Assert
+        assert!(matches!(result, Err(CommandError::InvalidImage(_))));
+    }
+
+    #[test]
+    fn returns_error_when_not_zip() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let epub_path = base.path().join("broken.epub");
+        std::fs::write(&epub_path, b"not a zip").unwrap();
+        let config = ImageContainerReaderConfig::new(base.path());
+        let container = EpubImageContainer::new(&epub_path, config).unwrap();
+
+        // Act
+        let result = container.metadata();
+
+        // Assert
+        assert!(matches!(result, Err(CommandError::NotAnArchive(_))));
+    }
+
+    #[test]
+    fn resolves_relative_hrefs() {
+        assert_eq!(
+            resolve_href("OEBPS/text/p.xhtml", "../images/a%20b.png#x").as_deref(),
+            Some("OEBPS/images/a b.png")
+        );
+        assert_eq!(
+            resolve_href("content.opf", "./img.jpg").as_deref(),
+            Some("img.jpg")
+        );
+        assert_eq!(resolve_href("content.opf", "../../etc/passwd"), None);
+        assert_eq!(
+            resolve_href("content.opf", "https://example.com/a.png"),
+            None
+        );
+    }
+}
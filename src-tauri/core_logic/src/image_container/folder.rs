@@ -1,5 +1,6 @@
 use std::{fs, path::PathBuf};
 
+use crate::format::is_supported_image;
 use crate::image_container::{CommandError, ImageContainer};
 
 pub struct FolderImageContainer {
@@ -40,7 +41,8 @@ impl ImageContainer for FolderImageContainer {
 /// Lists all image files in a specified folder.
 ///
 /// This function scans the given folder and returns a list of file paths
-/// for all image files found. Supported image formats are JPG, JPEG, PNG, GIF, and WEBP.
+/// for all image files found. Supported image formats are looked up in
+/// the [`crate::format`] registry.
 ///
 /// # Arguments
 ///
@@ -53,18 +55,13 @@ impl ImageContainer for FolderImageContainer {
 fn list_images_in_folder<P: AsRef<std::path::Path>>(
     folder_path: P,
 ) -> Result<Vec<String>, CommandError> {
-    const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
-
     let entries = fs::read_dir(&folder_path)?;
 
     let images = entries
         .filter_map(|entry| {
             let path = entry.ok()?.path();
-            if path.is_file() {
-                let ext = path.extension()?.to_str()?.to_lowercase();
-                if SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
-                    return Some(path.to_string_lossy().to_string());
-                }
+            if path.is_file() && is_supported_image(&path) {
+                return Some(path.to_string_lossy().to_string());
             }
             None
         })
@@ -139,11 +136,8 @@ pub fn get_sibling_archives<P: AsRef<std::path::Path>>(
         .filter_map(|entry| {
             entry.ok().and_then(|e| {
                 let path = e.path();
-                if path.is_file() && path != current {
-                    let ext = path.extension()?.to_str()?.to_lowercase();
-                    if ext == "zip" {
-                        return Some(path.to_string_lossy().to_string());
-                    }
+                if path.is_file() && path != current && crate::format::is_container_file(&path) {
+                    return Some(path.to_string_lossy().to_string());
                 }
                 None
             })
@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+///
+/// コンテナ（書籍）の情報。
+/// コンテナの形式によって取得できる項目は異なり、取得できない項目は空になる。
+/// EPUB はパッケージ文書（OPF）、アーカイブは ComicInfo.xml から取得する。
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerMetadata {
+    /// 書名
+    pub title: Option<String>,
+    /// 著者（記載順）
+    pub authors: Vec<String>,
+    /// ページの進行方向
+    pub page_progression: Option<PageProgression>,
+    /// シリーズ名
+    pub series: Option<String>,
+    /// 巻数
+    pub volume: Option<u32>,
+    /// 号数（"1.5" のような表記もあるため文字列）
+    pub number: Option<String>,
+    /// マンガかどうか
+    pub manga: Option<bool>,
+    /// 情報が記載されたページ
+    pub pages: Vec<PageInfo>,
+}
+
+/// ページごとの情報
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    /// コンテナ内の画像をファイル名順に並べたときの位置（0始まり）
+    pub index: usize,
+    /// ページの種類（"FrontCover"、"Story" など ComicInfo.xml の値）
+    pub page_type: Option<String>,
+    /// 見開きページかどうか
+    pub double_page: bool,
+}
+
+/// ページの進行方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PageProgression {
+    /// 左から右（左綴じ）
+    Ltr,
+    /// 右から左（右綴じ。日本のマンガなど）
+    Rtl,
+}
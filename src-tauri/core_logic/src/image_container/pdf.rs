@@ -0,0 +1,636 @@
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::{xobject::PdfImage, Dictionary, Document, Object, ObjectId};
+
+use crate::{
+    format::{registry, FormatKind},
+    image_container::{
+        extract_pages_once, folder::FolderImageContainer,
+        reader_config::ImageContainerReaderConfig, source_extract_key, CommandError,
+        ImageContainer,
+    },
+};
+
+/// ページの埋め込み画像として採用する最小の解像度（ページ幅 1pt あたりのピクセル数）
+///
+/// ロゴや挿絵など、ページの一部に配置された小さい画像をページ全体として扱わないようにする。
+/// 0.5 は 36dpi 相当で、スキャンされたページ（通常 150dpi 以上）であれば十分に上回る。
+const MIN_PIXELS_PER_POINT: f32 = 0.5;
+
+/// 描画する際のページの幅（ピクセル）
+#[cfg(feature = "pdf-render")]
+const RENDER_WIDTH: i32 = 1600;
+
+///
+/// PDF を、ページ画像のコンテナとして扱う。
+///
+/// 各ページに埋め込まれた画像（スキャンされた PDF のページ画像）を展開先ディレクトリに書き出す。
+/// JPEG はそのまま、それ以外は PNG に変換する。
+/// 埋め込み画像のないページは、`pdf-render` feature が有効な場合のみ pdfium で描画する（無効な場合は含めない）。
+///
+pub struct PdfImageContainer {
+    source_path: PathBuf,
+    config: ImageContainerReaderConfig,
+}
+
+impl PdfImageContainer {
+    pub fn new<P: AsRef<Path>>(
+        pdf_file_path: P,
+        config: ImageContainerReaderConfig,
+    ) -> Result<Self, CommandError> {
+        let pdf_file_path = pdf_file_path.as_ref();
+        if !pdf_file_path.exists() {
+            return Err(CommandError::PathNotFound(
+                pdf_file_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(PdfImageContainer {
+            source_path: pdf_file_path.to_path_buf(),
+            config,
+        })
+    }
+
+    ///
+    /// 各ページの画像ファイルのパスを返す。
+    ///
+    pub fn list_pages(&self) -> Result<Vec<String>, CommandError> {
+        self.check_extension()?;
+        let pages_dir = self.extract_pages()?;
+        FolderImageContainer::new(pages_dir)?.list_images()
+    }
+
+    ///
+    /// ページ数を返す。
+    ///
+    pub fn page_count(&self) -> Result<usize, CommandError> {
+        self.check_extension()?;
+        Ok(load_document(&self.source_path)?.get_pages().len())
+    }
+
+    ///
+    /// `list_pages` に含まれるページの番号（0始まり）を、画像を書き出さずに返す。
+    /// `pdf-render` feature が無効な場合は、埋め込み画像のないページを含めない。
+    ///
+    pub fn page_indexes(&self) -> Result<Vec<usize>, CommandError> {
+        self.check_extension()?;
+        let document = load_document(&self.source_path)?;
+        Ok(document
+            .get_pages()
+            .into_values()
+            .enumerate()
+            .filter(|&(_, page_id)| {
+                cfg!(feature = "pdf-render") || page_image(&document, page_id).is_some()
+            })
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    ///
+    /// 指定したページ（0始まり）だけを画像として書き出し、そのパスを返す。
+    /// 書き出せる画像がないページの場合は `None` を返す。
+    ///
+    pub fn render_page(&self, index: usize) -> Result<Option<PathBuf>, CommandError> {
+        self.render_pages(&[index])?.pop().unwrap_or(Ok(None))
+    }
+
+    ///
+    /// 指定したページ（0始まり）を1ページずつ画像として書き出す（全ページは展開しない）。
+    /// 書き出し済みのページはそのまま返し、PDF の読み込みは1回にまとめる。
+    /// ページごとの結果は `render_page` と同じ。
+    ///
+    pub fn render_pages(
+        &self,
+        indexes: &[usize],
+    ) -> Result<Vec<Result<Option<PathBuf>, CommandError>>, CommandError> {
+        self.check_extension()?;
+        let pages_dir = self.single_pages_dir()?;
+
+        let mut document = None;
+        let mut results = Vec::with_capacity(indexes.len());
+        for &index in indexes {
+            if let Some(path) = written_page(&pages_dir, index) {
+                results.push(Ok(Some(path)));
+                continue;
+            }
+            let document = match &mut document {
+                Some(document) => document,
+                None => document.insert(load_document(&self.source_path)?),
+            };
+            let result = match document.get_pages().values().nth(index) {
+                Some(&page_id) => {
+                    write_page(&self.source_path, document, index, page_id, &pages_dir)
+                }
+                None => Err(CommandError::InvalidImage(format!(
+                    "Page {} not found",
+                    index + 1
+                ))),
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// 1ページずつ書き出す場合の書き出し先
+    ///
+    /// 全ページの展開先とは分け、展開済みかどうかの判定に影響しないようにする。
+    fn single_pages_dir(&self) -> Result<PathBuf, CommandError> {
+        let pages_dir = self
+            .config
+            .get_extract_dir()
+            .join(format!("{}-pages", source_extract_key(&self.source_path)?));
+        std::fs::create_dir_all(&pages_dir)?;
+        Ok(pages_dir)
+    }
+
+    fn check_extension(&self) -> Result<(), CommandError> {
+        let source_path = self.source_path.as_path();
+        let is_pdf = registry()
+            .get(source_path)
+            .is_some_and(|entry| entry.kind == FormatKind::Pdf);
+        if !source_path.is_file() || !is_pdf {
+            return Err(CommandError::UnsupportedExtension(
+                source_path.to_string_lossy().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    ///
+    /// 全てのページを画像として展開する。
+    /// 既に展開済みのディレクトリが存在する場合は、展開せずにそのディレクトリのパスを返す。
+    ///
+    fn extract_pages(&self) -> Result<PathBuf, CommandError> {
+        let source_path = self.source_path.as_path();
+        extract_pages_once(&self.config, source_path, |pages_dir| {
+            let document = load_document(source_path)?;
+            for (index, page_id) in document.get_pages().into_values().enumerate() {
+                if write_page(source_path, &document, index, page_id, pages_dir)?.is_none() {
+                    log::warn!(
+                        "Skipping page {} of {:?}: no page image found",
+                        index + 1,
+                        source_path
+                    );
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl ImageContainer for PdfImageContainer {
+    fn list_images(&self) -> Result<Vec<String>, CommandError> {
+        self.list_pages()
+    }
+
+    ///
+    /// 画像を書き出せる最初のページだけを書き出す（全ページは展開しない）。
+    ///
+    fn get_first_image(&self) -> Result<Option<String>, CommandError> {
+        self.check_extension()?;
+        let document = load_document(&self.source_path)?;
+        let pages_dir = self.single_pages_dir()?;
+        for (index, page_id) in document.get_pages().into_values().enumerate() {
+            if let Some(path) =
+                write_page(&self.source_path, &document, index, page_id, &pages_dir)?
+            {
+                return Ok(Some(path.to_string_lossy().to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn load_document(path: &Path) -> Result<Document, CommandError> {
+    Document::load(path).map_err(|e| CommandError::InvalidImage(e.to_string()))
+}
+
+/// `write_page` で書き出し済みのページ画像のパス
+fn written_page(output_dir: &Path, index: usize) -> Option<PathBuf> {
+    ["jpg", "png"]
+        .iter()
+        .map(|extension| output_dir.join(format!("{:04}.{}", index + 1, extension)))
+        .find(|path| path.is_file())
+}
+
+/// ページの画像を `{ページ番号:04}.jpg` または `.png` として書き出す
+fn write_page(
+    source_path: &Path,
+    document: &Document,
+    index: usize,
+    page_id: ObjectId,
+    output_dir: &Path,
+) -> Result<Option<PathBuf>, CommandError> {
+    let stem = format!("{:04}", index + 1);
+    match extract_page_image(document, page_id) {
+        Some(PageImage::Jpeg(bytes)) => {
+            let path = output_dir.join(format!("{}.jpg", stem));
+            std::fs::write(&path, bytes)?;
+            return Ok(Some(path));
+        }
+        Some(PageImage::Decoded(image)) => {
+            let path = output_dir.join(format!("{}.png", stem));
+            image
+                .save_with_format(&path, ImageFormat::Png)
+                .map_err(|e| CommandError::InvalidImage(e.to_string()))?;
+            return Ok(Some(path));
+        }
+        None => {}
+    }
+
+    #[cfg(feature = "pdf-render")]
+    {
+        let path = output_dir.join(format!("{}.png", stem));
+        render::render_page(source_path, index, &path)?;
+        Ok(Some(path))
+    }
+    #[cfg(not(feature = "pdf-render"))]
+    {
+        let _ = source_path;
+        Ok(None)
+    }
+}
+
+/// ページから取り出した画像
+enum PageImage {
+    /// DCTDecode のストリーム（JPEG ファイルとしてそのまま保存できる）
+    Jpeg(Vec<u8>),
+    Decoded(DynamicImage),
+}
+
+/// ページ全体を覆う埋め込み画像（複数ある場合は最も大きいもの）
+fn page_image(document: &Document, page_id: ObjectId) -> Option<PdfImage<'_>> {
+    let image = document
+        .get_page_images(page_id)
+        .ok()?
+        .into_iter()
+        .max_by_key(|image| image.width.saturating_mul(image.height))?;
+
+    if let Some(page_width) = page_width(document, page_id) {
+        if (image.width as f32) < page_width * MIN_PIXELS_PER_POINT {
+            return None;
+        }
+    }
+    Some(image)
+}
+
+/// ページ全体を覆う埋め込み画像を取り出す
+fn extract_page_image(document: &Document, page_id: ObjectId) -> Option<PageImage> {
+    let image = page_image(document, page_id)?;
+
+    let filters = image.filters.as_deref().unwrap_or_default();
+    if filters.iter().any(|filter| filter == "DCTDecode") {
+        // DCTDecode の前に他のフィルタ（FlateDecode など）がかかっている場合は先に解く
+        let stream = document.get_object(image.id).ok()?.as_stream().ok()?;
+        let bytes = if filters.len() == 1 {
+            stream.content.clone()
+        } else {
+            stream.decompressed_content().ok()?
+        };
+        return Some(PageImage::Jpeg(bytes));
+    }
+
+    let stream = document.get_object(image.id).ok()?.as_stream().ok()?;
+    let samples = stream.get_plain_content().ok()?;
+    let width = u32::try_from(image.width).ok()?;
+    let height = u32::try_from(image.height).ok()?;
+    let components = color_components(document, image.origin_dict)?;
+    let decoded = decode_samples(
+        &samples,
+        width,
+        height,
+        components,
+        image.bits_per_component.unwrap_or(8),
+    )?;
+    Some(PageImage::Decoded(decoded))
+}
+
+/// 画像の色空間の成分数（対応していない色空間は `None`）
+fn color_components(document: &Document, dict: &Dictionary) -> Option<u8> {
+    let color_space = dict.get(b"ColorSpace").ok()?;
+    let (_, color_space) = document.dereference(color_space).ok()?;
+    match color_space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" => Some(1),
+            b"DeviceRGB" | b"CalRGB" => Some(3),
+            b"DeviceCMYK" => Some(4),
+            _ => None,
+        },
+        // [/ICCBased <stream>] の場合は、ストリームの N が成分数
+        Object::Array(array) => match array.first()?.as_name().ok()? {
+            b"ICCBased" => {
+                let (_, profile) = document.dereference(array.get(1)?).ok()?;
+                let n = profile
+                    .as_stream()
+                    .ok()?
+                    .dict
+                    .get(b"N")
+                    .ok()?
+                    .as_i64()
+                    .ok()?;
+                u8::try_from(n).ok()
+            }
+            b"CalGray" => Some(1),
+            b"CalRGB" => Some(3),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 非圧縮のサンプル列を画像に変換する（8bit のグレー・RGB・CMYK、1bit のグレーに対応）
+fn decode_samples(
+    samples: &[u8],
+    width: u32,
+    height: u32,
+    components: u8,
+    bits_per_component: i64,
+) -> Option<DynamicImage> {
+    let pixel_count = width as usize * height as usize;
+    match (components, bits_per_component) {
+        (1, 8) => GrayImage::from_raw(width, height, samples.get(..pixel_count)?.to_vec())
+            .map(DynamicImage::ImageLuma8),
+        (1, 1) => {
+            // 各行はバイト境界に揃えられている
+            let row_bytes = (width as usize).div_ceil(8);
+            let pixels = samples
+                .chunks(row_bytes)
+                .take(height as usize)
+                .flat_map(|row| {
+                    (0..width as usize).map(move |x| {
+                        if row
+                            .get(x / 8)
+                            .is_some_and(|byte| byte & (0x80 >> (x % 8)) != 0)
+                        {
+                            255
+                        } else {
+                            0
+                        }
+                    })
+                })
+                .collect();
+            GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        }
+        (3, 8) => RgbImage::from_raw(width, height, samples.get(..pixel_count * 3)?.to_vec())
+            .map(DynamicImage::ImageRgb8),
+        (4, 8) => {
+            let pixels = samples
+                .get(..pixel_count * 4)?
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - cmyk[3] as u16;
+                    [0, 1, 2].map(|i| ((255 - cmyk[i] as u16) * k / 255) as u8)
+                })
+                .collect();
+            RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+        }
+        _ => None,
+    }
+}
+
+/// ページの幅（pt）。MediaBox は親の Pages ノードから継承される場合がある
+fn page_width(document: &Document, page_id: ObjectId) -> Option<f32> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // 壊れたファイルで親が循環していても止まるよう、たどる深さに上限を設ける
+    for _ in 0..32 {
+        if let Ok(media_box) = node.get(b"MediaBox") {
+            let (_, media_box) = document.dereference(media_box).ok()?;
+            let values = media_box
+                .as_array()
+                .ok()?
+                .iter()
+                .map(|value| value.as_float().ok())
+                .collect::<Option<Vec<f32>>>()?;
+            return match values.as_slice() {
+                [x0, _, x1, _] => Some((x1 - x0).abs()),
+                _ => None,
+            };
+        }
+        let parent = node.get(b"Parent").ok()?.as_reference().ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+#[cfg(feature = "pdf-render")]
+mod render {
+    use super::RENDER_WIDTH;
+    use crate::image_container::CommandError;
+    use image::ImageFormat;
+    use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+    use std::path::Path;
+
+    /// pdfium でページ全体を描画して PNG として保存する
+    pub(super) fn render_page(
+        source_path: &Path,
+        index: usize,
+        output_path: &Path,
+    ) -> Result<(), CommandError> {
+        let render_error = |e: pdfium_render::prelude::PdfiumError| {
+            CommandError::InvalidImage(format!("Failed to render PDF page: {}", e))
+        };
+        let pdfium = Pdfium::new(Pdfium::bind_to_system_library().map_err(render_error)?);
+        let document = pdfium
+            .load_pdf_from_file(source_path, None)
+            .map_err(render_error)?;
+        let index = u16::try_from(index)
+            .map_err(|_| CommandError::InvalidImage(format!("Page {} not found", index + 1)))?;
+        let page = document.pages().get(index).map_err(render_error)?;
+        let bitmap = page
+            .render_with_config(&PdfRenderConfig::new().set_target_width(RENDER_WIDTH))
+            .map_err(render_error)?;
+        bitmap
+            .as_image()
+            .save_with_format(output_path, ImageFormat::Png)
+            .map_err(|e| CommandError::InvalidImage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use lopdf::{dictionary, Stream};
+
+    /// テスト用の PDF のページ
+    pub(crate) enum TestPage {
+        /// JPEG（DCTDecode）のページ画像
+        Jpeg(u32, u32),
+        /// FlateDecode で圧縮した RGB のページ画像
+        Rgb(u32, u32),
+        /// 画像のないページ
+        #[cfg_attr(feature = "pdf-render", allow(dead_code))]
+        Blank,
+    }
+
+    /// テスト用の PDF を作成する（ページサイズは 100 x 100 pt）
+    pub(crate) fn create_pdf(path: &Path, pages: &[TestPage]) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+
+        let page_ids: Vec<Object> = pages
+            .iter()
+            .map(|page| {
+                let image = match page {
+                    TestPage::Jpeg(width, height) => {
+                        let mut bytes = Vec::new();
+                        RgbImage::from_pixel(*width, *height, image::Rgb([200, 10, 10]))
+                            .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Jpeg)
+                            .unwrap();
+                        Some(Stream::new(
+                            dictionary! {
+                                "Type" => "XObject",
+                                "Subtype" => "Image",
+                                "Width" => *width as i64,
+                                "Height" => *height as i64,
+                                "ColorSpace" => "DeviceRGB",
+                                "BitsPerComponent" => 8,
+                                "Filter" => "DCTDecode",
+                            },
+                            bytes,
+                        ))
+                    }
+                    TestPage::Rgb(width, height) => {
+                        let mut stream = Stream::new(
+                            dictionary! {
+                                "Type" => "XObject",
+                                "Subtype" => "Image",
+                                "Width" => *width as i64,
+                                "Height" => *height as i64,
+                                "ColorSpace" => "DeviceRGB",
+                                "BitsPerComponent" => 8,
+                            },
+                            vec![128; (*width * *height * 3) as usize],
+                        );
+                        stream.compress().unwrap();
+                        Some(stream)
+                    }
+                    TestPage::Blank => None,
+                };
+
+                let mut resources = Dictionary::new();
+                if let Some(image) = image {
+                    let image_id = doc.add_object(image);
+                    resources.set("XObject", dictionary! { "Im0" => image_id });
+                }
+                let content_id = doc.add_object(Stream::new(
+                    dictionary! {},
+                    b"q 100 0 0 100 0 0 cm /Im0 Do Q".to_vec(),
+                ));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => resources,
+                })
+                .into()
+            })
+            .collect();
+
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => page_ids.len() as i64,
+                "Kids" => page_ids,
+                "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.save(path).unwrap();
+    }
+
+    fn create_container(temp: &TempTestDir, pages: &[TestPage]) -> PdfImageContainer {
+        let pdf_path = temp.path().join("book.pdf");
+        create_pdf(&pdf_path, pages);
+        PdfImageContainer::new(&pdf_path, ImageContainerReaderConfig::new(temp.path())).unwrap()
+    }
+
+    #[test]
+    fn test_list_pages_extracts_embedded_images() {
+        let temp = TempTestDir::new_random();
+        let container = create_container(&temp, &[TestPage::Jpeg(120, 160), TestPage::Rgb(80, 60)]);
+
+        let pages = container.list_pages().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].ends_with("0001.jpg"));
+        assert!(pages[1].ends_with("0002.png"));
+        assert_eq!(image::image_dimensions(&pages[0]).unwrap(), (120, 160));
+        assert_eq!(image::image_dimensions(&pages[1]).unwrap(), (80, 60));
+
+        // 2回目は展開済みのディレクトリを返す
+        assert_eq!(container.list_pages().unwrap(), pages);
+    }
+
+    #[test]
+    #[cfg(not(feature = "pdf-render"))]
+    fn test_pages_without_images_are_skipped() {
+        let temp = TempTestDir::new_random();
+        let container = create_container(
+            &temp,
+            &[
+                TestPage::Blank,
+                TestPage::Jpeg(100, 100),
+                // ページに対して小さすぎる画像（挿絵など）
+                TestPage::Jpeg(10, 10),
+            ],
+        );
+
+        assert_eq!(container.page_count().unwrap(), 3);
+        let pages = container.list_pages().unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].ends_with("0002.jpg"));
+        assert_eq!(container.render_page(0).unwrap(), None);
+        assert!(container
+            .get_first_image()
+            .unwrap()
+            .unwrap()
+            .ends_with("0002.jpg"));
+    }
+
+    #[test]
+    fn test_render_single_page() {
+        let temp = TempTestDir::new_random();
+        let container = create_container(&temp, &[TestPage::Rgb(50, 50), TestPage::Jpeg(60, 60)]);
+
+        let page = container.render_page(1).unwrap().unwrap();
+        assert_eq!(image::image_dimensions(&page).unwrap(), (60, 60));
+        assert_eq!(
+            container.get_first_image().unwrap().map(PathBuf::from),
+            Some(page.with_file_name("0001.png"))
+        );
+        assert!(container.render_page(5).is_err());
+    }
+
+    #[test]
+    fn test_invalid_pdf() {
+        let temp = TempTestDir::new_random();
+        let pdf_path = temp.path().join("broken.pdf");
+        std::fs::write(&pdf_path, b"not a pdf").unwrap();
+        let container =
+            PdfImageContainer::new(&pdf_path, ImageContainerReaderConfig::new(temp.path()))
+                .unwrap();
+
+        assert!(matches!(
+            container.list_pages(),
+            Err(CommandError::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_samples() {
+        let cmyk = decode_samples(&[0, 0, 0, 0, 255, 255, 255, 255], 2, 1, 4, 8).unwrap();
+        assert_eq!(cmyk.to_rgb8().into_raw(), vec![255, 255, 255, 0, 0, 0]);
+
+        let bilevel = decode_samples(&[0b1010_0000], 3, 1, 1, 1).unwrap();
+        assert_eq!(bilevel.to_luma8().into_raw(), vec![255, 0, 255]);
+
+        assert!(decode_samples(&[0; 4], 2, 2, 3, 8).is_none(), "Too short");
+        assert!(decode_samples(&[0; 4], 2, 2, 1, 16).is_none());
+    }
+}
@@ -6,18 +6,12 @@ pub struct ImageContainerReaderConfig {
     /// 解凍先のディレクトリパス。圧縮ファイルを扱う際に必要。
     ///
     extract_dir: PathBuf,
-
-    ///
-    /// サポートされている圧縮ファイルの拡張子。
-    ///
-    supported_archive_extensions: Vec<String>,
 }
 
 impl ImageContainerReaderConfig {
     pub fn new<P: AsRef<std::path::Path>>(extract_dir: P) -> Self {
         ImageContainerReaderConfig {
             extract_dir: extract_dir.as_ref().to_path_buf(),
-            supported_archive_extensions: vec!["zip".to_string()],
         }
     }
 
@@ -25,17 +19,8 @@ impl ImageContainerReaderConfig {
         &self.extract_dir
     }
 
+    /// アーカイブとして開ける拡張子か（[`crate::format`] のレジストリを参照する）
     pub fn is_supported_extension<P: AsRef<Path>>(&self, path: P) -> bool {
-        let extension = path
-            .as_ref()
-            .extension()
-            .and_then(|ext| ext.to_str())
-            // 拡張子がない場合は空文字列にfallbackする
-            .unwrap_or("");
-
-        // 大文字小文字を区別せずに、サポートされている拡張子のいずれかと一致するかを確認する
-        self.supported_archive_extensions
-            .iter()
-            .any(|ext| ext.eq_ignore_ascii_case(extension))
+        crate::format::is_archive(path)
     }
 }
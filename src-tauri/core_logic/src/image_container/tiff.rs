@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageFormat;
+
+use crate::{
+    decode::{count_tiff_pages, for_each_tiff_page},
+    format::is_multi_page_image,
+    image_container::{
+        extract_pages_once, folder::FolderImageContainer,
+        reader_config::ImageContainerReaderConfig, CommandError, ImageContainer,
+    },
+};
+
+///
+/// 複数ページの TIFF を、ページ画像のコンテナとして扱う。
+/// 各ページは展開先ディレクトリに PNG として書き出す（1ページだけの場合はファイル自体を返す）。
+///
+pub struct TiffImageContainer {
+    source_path: PathBuf,
+    config: ImageContainerReaderConfig,
+}
+
+impl TiffImageContainer {
+    pub fn new<P: AsRef<Path>>(
+        tiff_file_path: P,
+        config: ImageContainerReaderConfig,
+    ) -> Result<Self, CommandError> {
+        let tiff_file_path = tiff_file_path.as_ref();
+        if !tiff_file_path.exists() {
+            return Err(CommandError::PathNotFound(
+                tiff_file_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(TiffImageContainer {
+            source_path: tiff_file_path.to_path_buf(),
+            config,
+        })
+    }
+
+    ///
+    /// 各ページの画像ファイルのパスを返す。
+    ///
+    pub fn list_pages(&self) -> Result<Vec<String>, CommandError> {
+        let source_path = self.source_path.as_path();
+        if !source_path.is_file() || !is_multi_page_image(source_path) {
+            return Err(CommandError::UnsupportedExtension(
+                source_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let page_count =
+            count_tiff_pages(source_path).map_err(|e| CommandError::InvalidImage(e.to_string()))?;
+        if page_count <= 1 {
+            return Ok(vec![source_path.to_string_lossy().to_string()]);
+        }
+
+        // 各ページを PNG として展開する
+        let pages_dir = extract_pages_once(&self.config, source_path, |pages_dir| {
+            for_each_tiff_page(source_path, |index, page| {
+                page.save_with_format(
+                    pages_dir.join(format!("{:04}.png", index + 1)),
+                    ImageFormat::Png,
+                )
+            })
+            .map(|_| ())
+            .map_err(|e| CommandError::InvalidImage(e.to_string()))
+        })?;
+        FolderImageContainer::new(pages_dir)?.list_images()
+    }
+}
+
+impl ImageContainer for TiffImageContainer {
+    fn list_images(&self) -> Result<Vec<String>, CommandError> {
+        self.list_pages()
+    }
+
+    fn get_first_image(&self) -> Result<Option<String>, CommandError> {
+        let images = self.list_pages()?;
+        Ok(images.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use std::fs::File;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    fn create_tiff(path: &Path, pages: usize) {
+        let mut encoder = TiffEncoder::new(File::create(path).unwrap()).unwrap();
+        for _ in 0..pages {
+            encoder
+                .write_image::<colortype::RGB8>(4, 4, &[128; 4 * 4 * 3])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn returns_each_page_of_multi_page_tiff() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let tiff_path = base.path().join("scan.tiff");
+        create_tiff(&tiff_path, 3);
+        let extract_base = TempTestDir::new_random();
+        let config = ImageContainerReaderConfig::new(extract_base.path());
+        let container = TiffImageContainer::new(&tiff_path, config).unwrap();
+
+        // Act
+        let mut pages = container.list_pages().unwrap();
+        pages.sort();
+
+        // Assert
+        assert_eq!(pages.len(), 3);
+        assert!(pages[0].ends_with("0001.png"));
+        assert!(pages.iter().all(|p| Path::new(p).exists()));
+        // 2回目は展開済みのページを返す
+        assert_eq!(container.list_pages().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn re_extracts_pages_when_tiff_changes() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let tiff_path = base.path().join("scan.tiff");
+        create_tiff(&tiff_path, 3);
+        let extract_base = TempTestDir::new_random();
+        let config = ImageContainerReaderConfig::new(extract_base.path());
+        let container = TiffImageContainer::new(&tiff_path, config).unwrap();
+        let old_pages = container.list_pages().unwrap();
+
+        // Act
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        create_tiff(&tiff_path, 2);
+        let pages = container.list_pages().unwrap();
+
+        // Assert
+        assert_eq!(pages.len(), 2);
+        // 変更前の展開結果は削除される
+        assert!(old_pages.iter().all(|p| !Path::new(p).exists()));
+        assert_eq!(std::fs::read_dir(extract_base.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn extracts_same_tiff_concurrently() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let tiff_path = base.path().join("scan.tiff");
+        create_tiff(&tiff_path, 5);
+        let extract_base = TempTestDir::new_random();
+        let barrier = std::sync::Barrier::new(4);
+
+        // Act
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let config = ImageContainerReaderConfig::new(extract_base.path());
+                        let container = TiffImageContainer::new(&tiff_path, config).unwrap();
+                        barrier.wait();
+                        container.list_pages()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        // Assert
+        for pages in &results {
+            let pages = pages.as_ref().unwrap();
+            assert_eq!(pages.len(), 5);
+            assert!(pages.iter().all(|p| Path::new(p).is_file()));
+        }
+        // 一時ディレクトリは残らない
+        assert_eq!(std::fs::read_dir(extract_base.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn returns_file_itself_for_single_page_tiff() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let tiff_path = base.path().join("page.tif");
+        create_tiff(&tiff_path, 1);
+        let extract_base = TempTestDir::new_random();
+        let config = ImageContainerReaderConfig::new(extract_base.path());
+        let container = TiffImageContainer::new(&tiff_path, config).unwrap();
+
+        // Act
+        let pages = container.list_pages().unwrap();
+
+        // Assert
+        assert_eq!(pages, vec![tiff_path.to_string_lossy().to_string()]);
+    }
+
+    #[test]
+    fn returns_error_when_tiff_is_corrupted() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let tiff_path = base.path().join("broken.tiff");
+        std::fs::write(&tiff_path, b"not a tiff").unwrap();
+        let extract_base = TempTestDir::new_random();
+        let config = ImageContainerReaderConfig::new(extract_base.path());
+        let container = TiffImageContainer::new(&tiff_path, config).unwrap();
+
+        // Act
+        let result = container.list_pages();
+
+        // Assert
+        assert!(matches!(result, Err(CommandError::InvalidImage(_))));
+    }
+
+    #[test]
+    fn returns_error_when_not_tiff() {
+        // Arrange
+        let base = TempTestDir::new_random();
+        let file_path = base.path().join("file.zip");
+        File::create(&file_path).unwrap();
+        let config = ImageContainerReaderConfig::new(base.path());
+        let container = TiffImageContainer::new(&file_path, config).unwrap();
+
+        // Act
+        let result = container.list_pages();
+
+        // Assert
+        assert!(matches!(result, Err(CommandError::UnsupportedExtension(_))));
+    }
+}
@@ -1,10 +1,15 @@
+pub mod animation;
+pub mod decode;
+pub mod format;
 pub mod fs;
 pub mod image_container;
+pub mod metadata;
 #[cfg(test)]
 pub mod test_helper;
 pub mod thumbnail;
+pub mod tile;
 pub mod utils;
 
 // 後方互換性のための再エクスポート
-pub use fs::{get_sibling_containers, list_images_in_container};
+pub use fs::{get_container_metadata, get_sibling_containers, list_images_in_container};
 pub use image_container::CommandError;
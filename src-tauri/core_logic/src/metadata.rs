@@ -0,0 +1,146 @@
+// 画像に埋め込まれたメタデータ（EXIF / XMP / IPTC）の読み込み
+//
+// EXIF は kamadak-exif で JPEG / TIFF / PNG / WebP / HEIF とカメラ RAW から読み、
+// XMP と IPTC は image クレートのデコーダーが取り出したデータを解析する。
+// アーカイブ内の画像は、コンテナの展開先のパス（`list_images_in_container` が返すパス）を指定する。
+
+mod exif;
+mod iptc;
+mod xmp;
+
+pub use exif::{ExifMetadata, GpsPosition};
+pub use iptc::IptcMetadata;
+pub use xmp::XmpMetadata;
+
+use image::{ImageDecoder, ImageReader};
+use serde::Serialize;
+use std::path::Path;
+
+/// 画像のメタデータ
+///
+/// 画像に含まれない（または読み取れない）種類は `None` になる。
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMetadata {
+    pub exif: Option<ExifMetadata>,
+    pub xmp: Option<XmpMetadata>,
+    pub iptc: Option<IptcMetadata>,
+}
+
+/// 画像のメタデータを読み込む
+///
+/// 壊れたメタデータは読み飛ばす。ファイルを開けない場合のみエラーになる。
+pub fn read_image_metadata<P: AsRef<Path>>(image_path: P) -> Result<ImageMetadata, String> {
+    let image_path = image_path.as_ref();
+    let error = |e: &dyn std::fmt::Display| {
+        format!(
+            "Failed to read metadata of '{}': {}",
+            image_path.display(),
+            e
+        )
+    };
+    if !image_path.is_file() {
+        return Err(error(&"File not found"));
+    }
+
+    let exif = exif::read(image_path).map_err(|e| error(&e))?;
+
+    // image クレートで開けない形式（カメラ RAW など）は XMP / IPTC を読まない
+    let (xmp, iptc) = match ImageReader::open(image_path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.into_decoder())
+    {
+        Ok(mut decoder) => (
+            read_chunk(decoder.xmp_metadata(), image_path).and_then(|data| xmp::parse(&data)),
+            read_chunk(decoder.iptc_metadata(), image_path).and_then(|data| iptc::parse(&data)),
+        ),
+        Err(_) => (None, None),
+    };
+
+    Ok(ImageMetadata { exif, xmp, iptc })
+}
+
+fn read_chunk(chunk: image::ImageResult<Option<Vec<u8>>>, path: &Path) -> Option<Vec<u8>> {
+    chunk
+        .inspect_err(|e| log::warn!("Skipping metadata of {}: {}", path.display(), e))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+
+    /// JPEG の SOI の直後にセグメントを挿入する
+    fn insert_segment(jpeg: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+        let mut segment = vec![0xFF, marker];
+        segment.extend(((payload.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(payload);
+        jpeg.splice(2..2, segment);
+    }
+
+    fn jpeg_bytes() -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(8, 8)
+            .write_to(&mut data, image::ImageFormat::Jpeg)
+            .unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_reads_all_kinds_from_jpeg() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("photo.jpg");
+        let mut jpeg = jpeg_bytes();
+        insert_segment(&mut jpeg, 0xED, &iptc::tests::app13_payload());
+        insert_segment(&mut jpeg, 0xE1, &xmp::tests::app1_payload());
+        insert_segment(&mut jpeg, 0xE1, &exif::tests::app1_payload());
+        std::fs::write(&path, jpeg).unwrap();
+
+        let metadata = read_image_metadata(&path).unwrap();
+
+        let exif = metadata.exif.unwrap();
+        assert_eq!(exif.make.as_deref(), Some("Canon"));
+        let xmp = metadata.xmp.unwrap();
+        assert_eq!(xmp.rating, Some(4));
+        let iptc = metadata.iptc.unwrap();
+        assert_eq!(iptc.caption.as_deref(), Some("A caption"));
+    }
+
+    #[test]
+    fn test_image_without_metadata() {
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("plain.png");
+        image::RgbImage::new(4, 4).save(&path).unwrap();
+
+        assert_eq!(
+            read_image_metadata(&path).unwrap(),
+            ImageMetadata::default()
+        );
+        assert!(read_image_metadata(temp.path().join("missing.png")).is_err());
+    }
+
+    #[test]
+    fn test_reads_image_extracted_from_archive() {
+        use crate::image_container::{
+            reader_config::ImageContainerReaderConfig, ImageContainerReader,
+        };
+
+        let temp = TempTestDir::new_random();
+        let image_path = temp.path().join("001.jpg");
+        let mut jpeg = jpeg_bytes();
+        insert_segment(&mut jpeg, 0xE1, &exif::tests::app1_payload());
+        std::fs::write(&image_path, jpeg).unwrap();
+        let zip_path = temp.path().join("book.zip");
+        TempTestDir::create_zip(&zip_path, vec![&image_path]).unwrap();
+        let reader =
+            ImageContainerReader::new(ImageContainerReaderConfig::new(temp.path().join("cache")));
+
+        let pages = reader.list_images_in_container(&zip_path).unwrap();
+        let metadata = read_image_metadata(&pages[0]).unwrap();
+
+        assert_eq!(metadata.exif.unwrap().model.as_deref(), Some("EOS R5"));
+    }
+}
@@ -0,0 +1,225 @@
+// EXIF の読み込み（kamadak-exif）
+
+use exif::{Exif, In, Reader, Tag, Value};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// 撮影情報
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExifMetadata {
+    /// カメラのメーカー
+    pub make: Option<String>,
+    /// カメラの機種
+    pub model: Option<String>,
+    pub lens_model: Option<String>,
+    /// 撮影日時（"YYYY:MM:DD HH:MM:SS"。EXIF の表記のまま）
+    pub date_taken: Option<String>,
+    /// 露出時間（秒）
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    /// 焦点距離（mm）
+    pub focal_length: Option<f64>,
+    /// 向き（1〜8）
+    pub orientation: Option<u8>,
+    /// 画像の説明（キャプション）
+    pub description: Option<String>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub software: Option<String>,
+    pub gps: Option<GpsPosition>,
+}
+
+/// 撮影場所
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpsPosition {
+    /// 緯度（度。南緯は負）
+    pub latitude: f64,
+    /// 経度（度。西経は負）
+    pub longitude: f64,
+    /// 高度（m。海面下は負）
+    pub altitude: Option<f64>,
+}
+
+/// EXIF を読み込む（EXIF がない・壊れている場合は `None`）
+pub(super) fn read(path: &Path) -> std::io::Result<Option<ExifMetadata>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match Reader::new().read_from_container(&mut reader) {
+        Ok(exif) => Ok(Some(from_exif(&exif))),
+        Err(exif::Error::Io(e)) => Err(e),
+        Err(exif::Error::NotFound(_)) => Ok(None),
+        Err(e) => {
+            log::warn!("Skipping malformed EXIF in {}: {}", path.display(), e);
+            Ok(None)
+        }
+    }
+}
+
+fn from_exif(exif: &Exif) -> ExifMetadata {
+    let value = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let text = |tag: Tag| value(tag).and_then(ascii);
+    let number = |tag: Tag| value(tag).and_then(rational);
+
+    ExifMetadata {
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        lens_model: text(Tag::LensModel),
+        date_taken: text(Tag::DateTimeOriginal).or_else(|| text(Tag::DateTime)),
+        exposure_time: number(Tag::ExposureTime),
+        f_number: number(Tag::FNumber),
+        iso: value(Tag::PhotographicSensitivity).and_then(|value| value.get_uint(0)),
+        focal_length: number(Tag::FocalLength),
+        orientation: value(Tag::Orientation)
+            .and_then(|value| value.get_uint(0))
+            .and_then(|value| u8::try_from(value).ok()),
+        description: text(Tag::ImageDescription),
+        artist: text(Tag::Artist),
+        copyright: text(Tag::Copyright),
+        software: text(Tag::Software),
+        gps: gps_position(exif),
+    }
+}
+
+fn gps_position(exif: &Exif) -> Option<GpsPosition> {
+    let value = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let reference = |tag: Tag| value(tag).and_then(ascii);
+    let coordinate = |tag: Tag, ref_tag: Tag, negative: &str| {
+        let degrees = match value(tag)? {
+            Value::Rational(dms) if dms.len() >= 3 => {
+                dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        Some(if reference(ref_tag).as_deref() == Some(negative) {
+            -degrees
+        } else {
+            degrees
+        })
+    };
+
+    let altitude = value(Tag::GPSAltitude).and_then(rational).map(|altitude| {
+        // GPSAltitudeRef が 1 の場合は海面下
+        if value(Tag::GPSAltitudeRef).and_then(|value| value.get_uint(0)) == Some(1) {
+            -altitude
+        } else {
+            altitude
+        }
+    });
+
+    Some(GpsPosition {
+        latitude: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?,
+        longitude: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?,
+        altitude,
+    })
+}
+
+/// ASCII 型の値を文字列にする（空の値は `None`）
+fn ascii(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+            .filter(|text| !text.is_empty()),
+        _ => None,
+    }
+}
+
+/// RATIONAL 型の最初の値を数値にする（分母が 0 の値は `None`）
+fn rational(value: &Value) -> Option<f64> {
+    match value {
+        Value::Rational(values) => values
+            .first()
+            .filter(|value| value.denom != 0)
+            .map(|value| value.to_f64()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, Rational};
+    use std::io::Cursor;
+
+    /// JPEG の APP1 セグメントに入れる EXIF
+    pub(in crate::metadata) fn app1_payload() -> Vec<u8> {
+        let ascii = |tag: Tag, text: &str| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![text.as_bytes().to_vec()]),
+        };
+        let rational = |tag: Tag, values: &[(u32, u32)]| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(
+                values
+                    .iter()
+                    .map(|&(num, denom)| Rational { num, denom })
+                    .collect(),
+            ),
+        };
+        let fields = [
+            ascii(Tag::Make, "Canon"),
+            ascii(Tag::Model, "EOS R5"),
+            ascii(Tag::DateTimeOriginal, "2024:05:01 10:20:30"),
+            Field {
+                tag: Tag::PhotographicSensitivity,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![400]),
+            },
+            rational(Tag::ExposureTime, &[(1, 250)]),
+            rational(Tag::FNumber, &[(28, 10)]),
+            ascii(Tag::GPSLatitudeRef, "N"),
+            rational(Tag::GPSLatitude, &[(35, 1), (30, 1), (0, 1)]),
+            ascii(Tag::GPSLongitudeRef, "W"),
+            rational(Tag::GPSLongitude, &[(139, 1), (45, 1), (36, 1)]),
+        ];
+
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(tiff.into_inner());
+        payload
+    }
+
+    #[test]
+    fn test_reads_camera_fields_and_gps() {
+        let exif = Reader::new()
+            .read_raw(app1_payload()[6..].to_vec())
+            .unwrap();
+        let metadata = from_exif(&exif);
+
+        assert_eq!(metadata.make.as_deref(), Some("Canon"));
+        assert_eq!(metadata.model.as_deref(), Some("EOS R5"));
+        assert_eq!(metadata.date_taken.as_deref(), Some("2024:05:01 10:20:30"));
+        assert_eq!(metadata.iso, Some(400));
+        assert_eq!(metadata.exposure_time, Some(0.004));
+        assert_eq!(metadata.f_number, Some(2.8));
+        assert_eq!(metadata.lens_model, None);
+        let gps = metadata.gps.unwrap();
+        assert_eq!(gps.latitude, 35.5);
+        assert_eq!(gps.longitude, -139.76);
+        assert_eq!(gps.altitude, None);
+    }
+
+    #[test]
+    fn test_reads_exif_from_raw() {
+        let temp = crate::test_helper::test_helpers::TempTestDir::new_random();
+        let path = temp.path().join("photo.cr2");
+        crate::decode::create_raw_fixture(&path, false, (8, 8));
+
+        // RAW の IFD0 も EXIF として読める
+        let metadata = read(&path).unwrap().unwrap();
+        assert_eq!(metadata.orientation, Some(1));
+    }
+}
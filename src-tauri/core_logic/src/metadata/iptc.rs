@@ -0,0 +1,185 @@
+// IPTC（IIM）の読み込み
+//
+// JPEG では Photoshop のイメージリソース（8BIM、ID 0x0404）に、
+// PNG では ImageMagick 形式のテキストチャンク（16進数）に格納されている。
+
+use serde::Serialize;
+
+/// IIM のデータセットの開始を示すタグマーカー
+const TAG_MARKER: u8 = 0x1C;
+/// IPTC を格納するイメージリソースの ID
+const RESOURCE_IPTC: u16 = 0x0404;
+
+/// IPTC の主な項目（アプリケーションレコード）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IptcMetadata {
+    /// 題名（Object Name）
+    pub title: Option<String>,
+    /// 説明（Caption/Abstract）
+    pub caption: Option<String>,
+    /// 作者（By-line）
+    pub creators: Vec<String>,
+    pub keywords: Vec<String>,
+    /// 作成日（"YYYYMMDD"）
+    pub date_created: Option<String>,
+    pub city: Option<String>,
+    pub country: Option<String>,
+    pub copyright: Option<String>,
+}
+
+/// IPTC のデータを解析する（項目が1つもない場合は `None`）
+pub(super) fn parse(data: &[u8]) -> Option<IptcMetadata> {
+    let iim = if data.starts_with(b"8BIM") {
+        photoshop_resource(data, RESOURCE_IPTC)?.to_vec()
+    } else if data.first() == Some(&TAG_MARKER) {
+        data.to_vec()
+    } else {
+        imagemagick_profile(data)?
+    };
+
+    let mut metadata = IptcMetadata::default();
+    for (record, dataset, value) in datasets(&iim) {
+        if record != 2 {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value).trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match dataset {
+            5 => metadata.title = Some(value),
+            25 => metadata.keywords.push(value),
+            55 => metadata.date_created = Some(value),
+            80 => metadata.creators.push(value),
+            90 => metadata.city = Some(value),
+            101 => metadata.country = Some(value),
+            116 => metadata.copyright = Some(value),
+            120 => metadata.caption = Some(value),
+            _ => {}
+        }
+    }
+    (metadata != IptcMetadata::default()).then_some(metadata)
+}
+
+/// IIM のデータセット（レコード番号, データセット番号, 値）を列挙する
+fn datasets(data: &[u8]) -> Vec<(u8, u8, &[u8])> {
+    let mut datasets = Vec::new();
+    let mut pos = 0;
+    while let Some(&[TAG_MARKER, record, dataset, len_hi, len_lo]) = data.get(pos..pos + 5) {
+        // 拡張長（最上位ビットが立っている）の値は扱わない
+        if len_hi & 0x80 != 0 {
+            break;
+        }
+        let start = pos + 5;
+        let end = start + u16::from_be_bytes([len_hi, len_lo]) as usize;
+        let Some(value) = data.get(start..end) else {
+            break;
+        };
+        datasets.push((record, dataset, value));
+        pos = end;
+    }
+    datasets
+}
+
+/// Photoshop のイメージリソースから、指定した ID のデータを取り出す
+fn photoshop_resource(data: &[u8], id: u16) -> Option<&[u8]> {
+    let mut pos = 0;
+    while data.get(pos..pos + 4)? == b"8BIM" {
+        let resource_id = u16::from_be_bytes(data.get(pos + 4..pos + 6)?.try_into().ok()?);
+        // 名前（パスカル文字列。長さのバイトを含めて偶数に揃える）
+        let name_len = *data.get(pos + 6)? as usize;
+        let size_pos = pos + 6 + (name_len + 1).next_multiple_of(2);
+        let size = u32::from_be_bytes(data.get(size_pos..size_pos + 4)?.try_into().ok()?) as usize;
+        let start = size_pos + 4;
+        let value = data.get(start..start + size)?;
+        if resource_id == id {
+            return Some(value);
+        }
+        pos = start + size.next_multiple_of(2);
+    }
+    None
+}
+
+/// ImageMagick のプロファイル（"\niptc\n    長さ\n16進数..."）を復元する
+fn imagemagick_profile(data: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.trim_start().splitn(3, '\n');
+    let _name = lines.next()?;
+    let length: usize = lines.next()?.trim().parse().ok()?;
+    let hex: Vec<u8> = lines
+        .next()?
+        .bytes()
+        .filter(u8::is_ascii_hexdigit)
+        .collect();
+    let bytes: Vec<u8> = hex
+        .chunks_exact(2)
+        .filter_map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect();
+    let start = bytes.iter().position(|&byte| byte == TAG_MARKER)?;
+    bytes
+        .get(start..length.min(bytes.len()))
+        .map(<[u8]>::to_vec)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    fn iim() -> Vec<u8> {
+        let mut data = Vec::new();
+        for (dataset, value) in [
+            (0, "\0\x04"),
+            (5, "Title"),
+            (25, "sea"),
+            (25, "sky"),
+            (80, "Photographer"),
+            (120, "A caption"),
+        ] {
+            let record = if dataset == 0 { 1 } else { 2 };
+            data.extend([TAG_MARKER, record, dataset]);
+            data.extend((value.len() as u16).to_be_bytes());
+            data.extend(value.as_bytes());
+        }
+        data
+    }
+
+    /// JPEG の APP13 セグメントに入れる IPTC
+    pub(in crate::metadata) fn app13_payload() -> Vec<u8> {
+        let iim = iim();
+        let mut payload = b"Photoshop 3.0\0".to_vec();
+        // IPTC の前に別のリソース（名前付き）を置く
+        payload.extend(b"8BIM\x03\xED\x03abc\0\0\0\x03xyz\0");
+        payload.extend(b"8BIM\x04\x04\0\0");
+        payload.extend((iim.len() as u32).to_be_bytes());
+        payload.extend(&iim);
+        payload
+    }
+
+    fn expected() -> IptcMetadata {
+        IptcMetadata {
+            title: Some("Title".to_string()),
+            caption: Some("A caption".to_string()),
+            creators: vec!["Photographer".to_string()],
+            keywords: vec!["sea".to_string(), "sky".to_string()],
+            ..IptcMetadata::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_photoshop_resource() {
+        let payload = app13_payload();
+        assert_eq!(parse(&payload[14..]), Some(expected()));
+    }
+
+    #[test]
+    fn test_parse_raw_and_imagemagick_profile() {
+        assert_eq!(parse(&iim()), Some(expected()));
+
+        let hex: String = iim().iter().map(|byte| format!("{:02x}", byte)).collect();
+        let profile = format!("\niptc\n{:8}\n{}\n", iim().len(), hex);
+        assert_eq!(parse(profile.as_bytes()), Some(expected()));
+
+        assert_eq!(parse(b"garbage"), None);
+    }
+}
@@ -0,0 +1,145 @@
+// XMP（RDF/XML）の読み込み
+
+use roxmltree::{Document, Node};
+use serde::Serialize;
+
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_NAMESPACE: &str = "http://ns.adobe.com/xap/1.0/";
+const PHOTOSHOP_NAMESPACE: &str = "http://ns.adobe.com/photoshop/1.0/";
+const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// XMP の主な項目
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XmpMetadata {
+    pub title: Option<String>,
+    /// 説明（キャプション）
+    pub description: Option<String>,
+    pub creators: Vec<String>,
+    pub keywords: Vec<String>,
+    /// 評価（-1〜5。-1 は却下）
+    pub rating: Option<i32>,
+    pub date_created: Option<String>,
+}
+
+/// XMP パケットを解析する（解析できない場合は `None`）
+pub(super) fn parse(data: &[u8]) -> Option<XmpMetadata> {
+    let text = String::from_utf8_lossy(data);
+    // パケットの前後に余分なデータ（xpacket の処理命令や NUL 埋め）があっても読めるようにする
+    let start = text.find("<x:xmpmeta").or_else(|| text.find("<rdf:RDF"))?;
+    let end = text
+        .rfind("</x:xmpmeta>")
+        .map(|end| end + "</x:xmpmeta>".len())
+        .or_else(|| text.rfind("</rdf:RDF>").map(|end| end + "</rdf:RDF>".len()))?;
+    let document = match Document::parse(text.get(start..end)?) {
+        Ok(document) => document,
+        Err(e) => {
+            log::warn!("Skipping malformed XMP: {}", e);
+            return None;
+        }
+    };
+
+    Some(XmpMetadata {
+        title: values(&document, DC_NAMESPACE, "title").into_iter().next(),
+        description: values(&document, DC_NAMESPACE, "description")
+            .into_iter()
+            .next(),
+        creators: values(&document, DC_NAMESPACE, "creator"),
+        keywords: values(&document, DC_NAMESPACE, "subject"),
+        rating: values(&document, XMP_NAMESPACE, "Rating")
+            .first()
+            .and_then(|rating| rating.parse::<f64>().ok())
+            .map(|rating| rating.round() as i32),
+        date_created: values(&document, PHOTOSHOP_NAMESPACE, "DateCreated")
+            .into_iter()
+            .next()
+            .or_else(|| {
+                values(&document, XMP_NAMESPACE, "CreateDate")
+                    .into_iter()
+                    .next()
+            }),
+    })
+}
+
+/// プロパティの値を返す
+///
+/// `rdf:Description` の属性、単純な要素、`rdf:Alt` / `rdf:Seq` / `rdf:Bag` の `rdf:li` のいずれにも対応する。
+fn values(document: &Document, namespace: &str, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for node in document.descendants() {
+        if node.has_tag_name((RDF_NAMESPACE, "Description")) {
+            values.extend(node.attribute((namespace, name)).map(str::to_string));
+        } else if node.has_tag_name((namespace, name)) {
+            let items: Vec<Node> = node
+                .descendants()
+                .filter(|item| item.has_tag_name((RDF_NAMESPACE, "li")))
+                .collect();
+            if items.is_empty() {
+                values.extend(node.text().map(str::to_string));
+            } else {
+                values.extend(
+                    items
+                        .iter()
+                        .filter_map(|item| item.text())
+                        .map(str::to_string),
+                );
+            }
+        }
+    }
+    values
+        .into_iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    const PACKET: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:dc="http://purl.org/dc/elements/1.1/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+        xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/"
+        xmp:Rating="4" photoshop:DateCreated="2024-05-01T10:20:30">
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Sunset</rdf:li></rdf:Alt></dc:title>
+      <dc:description><rdf:Alt><rdf:li xml:lang="x-default">At the beach</rdf:li></rdf:Alt></dc:description>
+      <dc:creator><rdf:Seq><rdf:li>Photographer</rdf:li></rdf:Seq></dc:creator>
+      <dc:subject><rdf:Bag><rdf:li>sea</rdf:li><rdf:li>sky</rdf:li></rdf:Bag></dc:subject>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    /// JPEG の APP1 セグメントに入れる XMP
+    pub(in crate::metadata) fn app1_payload() -> Vec<u8> {
+        let mut payload = b"http://ns.adobe.com/xap/1.0/\0".to_vec();
+        payload.extend(PACKET.as_bytes());
+        payload
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        let metadata = parse(PACKET.as_bytes()).unwrap();
+        assert_eq!(
+            metadata,
+            XmpMetadata {
+                title: Some("Sunset".to_string()),
+                description: Some("At the beach".to_string()),
+                creators: vec!["Photographer".to_string()],
+                keywords: vec!["sea".to_string(), "sky".to_string()],
+                rating: Some(4),
+                date_created: Some("2024-05-01T10:20:30".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_packet() {
+        assert_eq!(parse(b"not xmp"), None);
+        assert_eq!(parse(b"<x:xmpmeta><broken</x:xmpmeta>"), None);
+    }
+}
@@ -9,6 +9,11 @@ pub mod test_helpers {
 
     use zip::write::SimpleFileOptions;
 
+    use crate::thumbnail::{
+        RenditionGenerator, RenditionService, ThumbnailConfig, ThumbnailService,
+    };
+    use crate::tile::{TileService, DEFAULT_TILE_SIZE};
+
     pub struct TempTestDir {
         path: PathBuf,
     }
@@ -98,4 +103,49 @@ pub mod test_helpers {
             }
         }
     }
+
+    /// テスト用の ThumbnailService（キャッシュは一時ディレクトリの下に作る）
+    pub fn create_test_thumbnail_service(
+        config: ThumbnailConfig,
+    ) -> (ThumbnailService, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let service = ThumbnailService::new(
+            config,
+            temp.path().join("thumbnails"),
+            temp.path().join("archive"),
+        )
+        .unwrap();
+        (service, temp)
+    }
+
+    /// テスト用の RenditionGenerator（キャッシュは一時ディレクトリの下に作る）
+    pub fn create_test_rendition_generator(
+        config: ThumbnailConfig,
+    ) -> (RenditionGenerator, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let generator = RenditionGenerator::new(config, temp.path().join("renditions")).unwrap();
+        (generator, temp)
+    }
+
+    /// テスト用の RenditionService（キャッシュは一時ディレクトリの下に作る）
+    pub fn create_test_rendition_service(
+        config: ThumbnailConfig,
+    ) -> (RenditionService, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let service = RenditionService::new(
+            config,
+            temp.path().join("renditions"),
+            temp.path().join("archive"),
+        )
+        .unwrap();
+        (service, temp)
+    }
+
+    /// テスト用の TileService（標準のタイルの大きさ、キャッシュは一時ディレクトリの下に作る）
+    pub fn create_test_tile_service(config: ThumbnailConfig) -> (TileService, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let service =
+            TileService::new(config, temp.path().join("tiles"), DEFAULT_TILE_SIZE).unwrap();
+        (service, temp)
+    }
 }
@@ -1,11 +1,30 @@
 pub mod batch;
+pub mod cache_index;
 pub mod config;
 pub mod error;
 pub mod folder;
 pub mod generator;
+pub mod job;
+mod memory;
+pub mod page;
+mod queue;
+pub mod rendition;
+pub mod rendition_service;
+pub mod service;
 
-pub use batch::{BatchResult, BatchTask, BatchThumbnailGenerator, TaskPriority};
-pub use config::ThumbnailConfig;
+pub use batch::{BatchResult, BatchTask, BatchThumbnailGenerator, TaskPriority, ThumbnailKind};
+pub use cache_index::{CachedThumbnail, ThumbnailCacheIndex};
+pub use config::{
+    FolderThumbnailMode, ThumbnailBackground, ThumbnailConfig, ThumbnailFilter, ThumbnailFit,
+};
 pub use error::{Result, ThumbnailError};
-pub use folder::FolderThumbnailResult;
+pub use folder::{CoverOverrides, CoverSelection, CoverSource, FolderCover, FolderThumbnailResult};
 pub use generator::ThumbnailGenerator;
+pub use job::{
+    CancellationToken, JobId, ThumbnailJobFinished, ThumbnailJobProgress, ThumbnailJobRegistry,
+};
+pub use memory::MemoryBudget;
+pub use page::{PageRange, PageThumbnailResult};
+pub use rendition::{RenditionGenerator, Viewport};
+pub use rendition_service::{PageRenditionResult, RenditionService};
+pub use service::ThumbnailService;
@@ -1,11 +1,26 @@
 // バッチサムネイル生成のための並列処理実装
+//
+// 長期間動作するワーカースレッド群が、優先度付きキューからタスクを取り出して生成する。
+// 複数の呼び出し元から投入されたタスクは同じキューで優先度順に処理される。
+// ワーカーは1つの ThumbnailGenerator を共有するため、同時に行うデコードのメモリ使用量は
+// 設定の `decode_memory_budget` の範囲に制限される。
 
 use crate::thumbnail::config::ThumbnailConfig;
 use crate::thumbnail::error::{Result, ThumbnailError};
 use crate::thumbnail::generator::ThumbnailGenerator;
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use crate::thumbnail::job::CancellationToken;
+use crate::thumbnail::memory::MemoryBudget;
+use crate::thumbnail::queue::{TaskQueue, Waiter};
+use serde::Serialize;
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 結果待ちの間にキャンセルを確認する間隔
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// 優先度レベル
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -15,11 +30,25 @@ pub enum TaskPriority {
     Low = 1,    // バックグラウンド生成
 }
 
+/// 生成するサムネイルの種類
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ThumbnailKind {
+    /// 1枚の画像のサムネイル
+    #[default]
+    Single,
+    /// 複数の画像を並べたモザイクサムネイル（フォルダサムネイル用）
+    Mosaic(Vec<String>),
+}
+
 /// バッチ生成タスク
 #[derive(Debug, Clone)]
 pub struct BatchTask {
+    /// 画像パス（モザイクの場合は代表画像）
     pub image_path: String,
     pub priority: TaskPriority,
+    /// 画像が属するコンテナ（フォルダサムネイルの場合）
+    pub container_path: Option<String>,
+    pub kind: ThumbnailKind,
 }
 
 impl BatchTask {
@@ -27,16 +56,47 @@ impl BatchTask {
         Self {
             image_path,
             priority,
+            container_path: None,
+            kind: ThumbnailKind::Single,
+        }
+    }
+
+    /// 画像が属するコンテナを設定
+    pub fn with_container(mut self, container_path: String) -> Self {
+        self.container_path = Some(container_path);
+        self
+    }
+
+    /// モザイクサムネイルとして生成する画像を設定
+    pub fn with_mosaic(mut self, image_paths: Vec<String>) -> Self {
+        self.kind = ThumbnailKind::Mosaic(image_paths);
+        self
+    }
+
+    /// キュー上でタスクを識別するキー
+    ///
+    /// 同じキーのタスクは1回の生成に統合される。
+    pub fn queue_key(&self) -> String {
+        match &self.kind {
+            ThumbnailKind::Single => self.image_path.clone(),
+            ThumbnailKind::Mosaic(image_paths) => format!("mosaic:{}", image_paths.join("\n")),
         }
     }
 }
 
 /// バッチサムネイル生成結果
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BatchResult {
     pub image_path: String,
     pub thumbnail_path: Option<PathBuf>,
     pub error: Option<String>,
+    /// 失敗の原因がソース画像にあり、画像が変更されるまで再試行しても失敗するか
+    pub permanent_failure: bool,
+    pub container_path: Option<String>,
+    /// 失敗の原因（`error` の元になった型付きのエラー）
+    #[serde(skip)]
+    pub cause: Option<ThumbnailError>,
 }
 
 impl BatchResult {
@@ -45,6 +105,9 @@ impl BatchResult {
             image_path,
             thumbnail_path: Some(thumbnail_path),
             error: None,
+            permanent_failure: false,
+            container_path: None,
+            cause: None,
         }
     }
 
@@ -53,14 +116,59 @@ impl BatchResult {
             image_path,
             thumbnail_path: None,
             error: Some(error),
+            permanent_failure: false,
+            container_path: None,
+            cause: None,
+        }
+    }
+
+    /// 生成時のエラーから失敗の結果を作成
+    pub fn from_error(image_path: String, error: &ThumbnailError) -> Self {
+        Self {
+            permanent_failure: error.is_source_error(),
+            cause: Some(error.clone()),
+            ..Self::failure(image_path, error.to_string())
         }
     }
+
+    /// 画像が属するコンテナを設定
+    pub fn with_container(mut self, container_path: Option<String>) -> Self {
+        self.container_path = container_path;
+        self
+    }
+}
+
+/// キューに積むタスクの内容（画像パスと種類）
+type QueuedTask = (String, ThumbnailKind);
+
+/// タスク1件のサムネイルを生成する処理
+type GenerateFn = dyn Fn(&str, &ThumbnailKind) -> Result<PathBuf> + Send + Sync;
+
+/// ワーカースレッド間で共有する状態
+struct SchedulerState {
+    queue: TaskQueue<QueuedTask>,
+    shutdown: bool,
+}
+
+/// ワーカースレッドと呼び出し元で共有するデータ
+struct Shared {
+    state: Mutex<SchedulerState>,
+    available: Condvar,
+    generate: Box<GenerateFn>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 /// バッチサムネイル生成マネージャー
+///
+/// 生成時に起動したワーカースレッドは、このマネージャーが破棄されるまで動作し続ける。
 pub struct BatchThumbnailGenerator {
-    thread_pool: Arc<ThreadPool>,
-    generator: Arc<ThumbnailGenerator>,
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
 }
 
 impl BatchThumbnailGenerator {
@@ -73,26 +181,60 @@ impl BatchThumbnailGenerator {
     /// # Returns
     /// 初期化されたBatchThumbnailGenerator
     pub fn new(config: ThumbnailConfig, cache_dir: PathBuf) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, cache_dir, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してBatchThumbnailGeneratorを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        cache_dir: PathBuf,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
+        // ThumbnailGeneratorの作成
+        let generator = ThumbnailGenerator::with_memory_budget(config, cache_dir, memory)?;
+        Self::with_generate_fn(Box::new(move |image_path, kind| match kind {
+            ThumbnailKind::Single => generator.get_or_create_thumbnail(image_path),
+            ThumbnailKind::Mosaic(image_paths) => {
+                generator.get_or_create_mosaic_thumbnail(image_paths)
+            }
+        }))
+    }
+
+    /// 生成処理を指定してワーカースレッドを起動する
+    fn with_generate_fn(generate: Box<GenerateFn>) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(SchedulerState {
+                queue: TaskQueue::new(),
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+            generate,
+        });
+
         // 動的スレッド数の計算: min(max(2, num_cpus), 8)
         let num_cpus = num_cpus::get();
         let thread_count = num_cpus.clamp(2, 8);
 
-        // rayon ThreadPoolの初期化
-        let thread_pool = ThreadPoolBuilder::new()
-            .num_threads(thread_count)
-            .thread_name(|i| format!("thumbnail-worker-{}", i))
-            .build()
-            .map_err(|e| {
-                ThumbnailError::GenerationError(format!("Failed to build thread pool: {}", e))
-            })?;
-
-        // ThumbnailGeneratorの作成
-        let generator = ThumbnailGenerator::new(config, cache_dir)?;
+        let mut batch = Self {
+            shared,
+            workers: Vec::with_capacity(thread_count),
+        };
+        for i in 0..thread_count {
+            let shared = Arc::clone(&batch.shared);
+            let worker = std::thread::Builder::new()
+                .name(format!("thumbnail-worker-{}", i))
+                .spawn(move || worker_loop(shared))
+                .map_err(|e| {
+                    ThumbnailError::GenerationError(format!(
+                        "Failed to spawn thumbnail worker: {}",
+                        e
+                    ))
+                })?;
+            batch.workers.push(worker);
+        }
 
-        Ok(Self {
-            thread_pool: Arc::new(thread_pool),
-            generator: Arc::new(generator),
-        })
+        Ok(batch)
     }
 
     /// デフォルト設定でBatchThumbnailGeneratorを作成
@@ -106,37 +248,150 @@ impl BatchThumbnailGenerator {
     /// * `tasks` - 生成タスクのリスト（優先度付き）
     ///
     /// # Returns
-    /// 各画像の生成結果のベクタ
-    pub fn batch_create_thumbnails(&self, mut tasks: Vec<BatchTask>) -> Vec<BatchResult> {
-        // 優先度でソート（High -> Normal -> Low）
-        tasks.sort_by(|a, b| b.priority.cmp(&a.priority));
-
-        // rayonの並列イテレータで処理
-        use rayon::prelude::*;
-
-        let generator = Arc::clone(&self.generator);
-
-        self.thread_pool.install(|| {
-            tasks
-                .par_iter()
-                .map(
-                    |task| match generator.get_or_create_thumbnail(&task.image_path) {
-                        Ok(thumbnail_path) => {
-                            BatchResult::success(task.image_path.clone(), thumbnail_path)
-                        }
-                        Err(e) => BatchResult::failure(task.image_path.clone(), e.to_string()),
-                    },
-                )
-                .collect()
-        })
-    }
-
-    /// スレッドプールのスレッド数を取得
+    /// 各画像の生成結果のベクタ（完了順）
+    pub fn batch_create_thumbnails(&self, tasks: Vec<BatchTask>) -> Vec<BatchResult> {
+        self.batch_create_thumbnails_cancellable(tasks, &CancellationToken::new(), |_| {})
+    }
+
+    /// キャンセル可能なバッチ生成
+    ///
+    /// タスクは共有キューに投入され、他の呼び出し元のタスクと合わせて優先度順に処理される。
+    /// 同じ画像のタスクが既に待機中・生成中の場合は、1回の生成に統合される。
+    /// 各タスクの完了ごとに `on_result` が呼ばれる。
+    /// キャンセル後に未着手だったタスクは生成されず、結果にも含まれない。
+    ///
+    /// # Arguments
+    /// * `tasks` - 生成タスクのリスト（優先度付き）
+    /// * `token` - キャンセルトークン
+    /// * `on_result` - タスク完了ごとに呼ばれるコールバック（呼び出し元のスレッドで呼ばれる）
+    ///
+    /// # Returns
+    /// 実行されたタスクの生成結果のベクタ（完了順）
+    pub fn batch_create_thumbnails_cancellable<F>(
+        &self,
+        tasks: Vec<BatchTask>,
+        token: &CancellationToken,
+        on_result: F,
+    ) -> Vec<BatchResult>
+    where
+        F: Fn(&BatchResult),
+    {
+        let expected = tasks.len();
+        let (sender, receiver) = mpsc::channel();
+        {
+            let mut state = self.shared.lock();
+            for task in tasks {
+                let key = task.queue_key();
+                let waiter = Waiter::new(token.clone(), task.container_path, sender.clone());
+                state
+                    .queue
+                    .push(&key, task.priority, (task.image_path, task.kind), waiter);
+            }
+        }
+        self.shared.available.notify_all();
+        // 全ての Waiter が破棄されたら受信が終了するよう、手元の送信側は破棄する
+        drop(sender);
+
+        let mut results = Vec::with_capacity(expected);
+        while results.len() < expected && !token.is_cancelled() {
+            match receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(result) => {
+                    if token.is_cancelled() {
+                        break;
+                    }
+                    on_result(&result);
+                    results.push(result);
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        results
+    }
+
+    /// 待機中のタスクの優先度を引き上げる（例: 画面内にスクロールされた）
+    ///
+    /// # Arguments
+    /// * `key` - タスクのキー（`BatchTask::queue_key`、単一画像の場合は画像パス）
+    ///
+    /// # Returns
+    /// 優先度が引き上げられた場合は true
+    pub fn bump_priority(&self, key: &str, priority: TaskPriority) -> bool {
+        self.shared.lock().queue.bump(key, priority)
+    }
+
+    /// 待機中のタスク数を取得
+    pub fn pending_count(&self) -> usize {
+        self.shared.lock().queue.pending_count()
+    }
+
+    /// ワーカースレッド数を取得
     pub fn thread_count(&self) -> usize {
-        self.thread_pool.current_num_threads()
+        self.workers.len()
     }
 }
 
+impl Drop for BatchThumbnailGenerator {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// ワーカースレッドの処理ループ
+fn worker_loop(shared: Arc<Shared>) {
+    loop {
+        let (key, (image_path, kind)) = {
+            let mut state = shared.lock();
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                if let Some(task) = state.queue.pop() {
+                    break task;
+                }
+                state = shared
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        // 生成中にパニックしてもキーを完了させ、待機中の呼び出し元に結果を返す
+        // （完了させないと同じ画像の以降の要求が永久に待ち続け、ワーカーも失われる）
+        let generated =
+            std::panic::catch_unwind(AssertUnwindSafe(|| (shared.generate)(&image_path, &kind)))
+                .unwrap_or_else(|panic| {
+                    Err(ThumbnailError::GenerationError(format!(
+                        "Thumbnail generation panicked for {}: {}",
+                        image_path,
+                        panic_message(panic.as_ref())
+                    )))
+                });
+        let result = match generated {
+            Ok(thumbnail_path) => BatchResult::success(image_path, thumbnail_path),
+            Err(e) => BatchResult::from_error(image_path, &e),
+        };
+
+        let waiters = shared.lock().queue.complete(&key);
+        for waiter in waiters {
+            waiter.deliver(&result);
+        }
+    }
+}
+
+/// パニックのペイロードからメッセージを取り出す
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -176,4 +431,179 @@ mod tests {
         assert!(result.thumbnail_path.is_none());
         assert!(result.error.is_some());
     }
+
+    #[test]
+    fn test_batch_result_from_error() {
+        let result = BatchResult::from_error(
+            "/path/to/image.jpg".to_string(),
+            &ThumbnailError::DecodeError("broken".to_string()),
+        );
+        assert!(result.permanent_failure);
+        assert!(result.error.unwrap().contains("broken"));
+
+        let result = BatchResult::from_error(
+            "/path/to/image.jpg".to_string(),
+            &ThumbnailError::CacheAccessError("read-only".to_string()),
+        );
+        assert!(!result.permanent_failure);
+    }
+
+    #[test]
+    fn test_batch_task_with_container() {
+        let task = BatchTask::new("/photos/a/1.jpg".to_string(), TaskPriority::Low)
+            .with_container("/photos/a".to_string());
+        assert_eq!(task.container_path.as_deref(), Some("/photos/a"));
+    }
+
+    #[test]
+    fn test_batch_task_queue_key() {
+        let single = BatchTask::new("/photos/a/1.jpg".to_string(), TaskPriority::Low);
+        assert_eq!(single.kind, ThumbnailKind::Single);
+        assert_eq!(single.queue_key(), "/photos/a/1.jpg");
+
+        let mosaic = single.clone().with_mosaic(vec![
+            "/photos/a/1.jpg".to_string(),
+            "/photos/a/2.jpg".to_string(),
+        ]);
+        assert_ne!(mosaic.queue_key(), single.queue_key());
+    }
+
+    #[test]
+    fn test_mosaic_task_generates_thumbnail() {
+        use crate::test_helper::test_helpers::TempTestDir;
+
+        let temp = TempTestDir::new_random();
+        let mut paths = Vec::new();
+        for i in 0..3 {
+            let path = temp.path().join(format!("image{}.png", i));
+            image::RgbImage::new(20, 20).save(&path).unwrap();
+            paths.push(path.to_string_lossy().to_string());
+        }
+        let batch =
+            BatchThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let task = BatchTask::new(paths[0].clone(), TaskPriority::High).with_mosaic(paths.clone());
+        let results = batch.batch_create_thumbnails(vec![task]);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].image_path, paths[0]);
+        assert!(results[0].thumbnail_path.is_some());
+    }
+
+    #[test]
+    fn test_cancellable_batch_reports_each_result() {
+        use crate::test_helper::test_helpers::TempTestDir;
+        use std::sync::Mutex;
+
+        let temp = TempTestDir::new_random();
+        let mut tasks = Vec::new();
+        for i in 0..3 {
+            let path = temp.path().join(format!("image{}.png", i));
+            image::RgbImage::new(20, 20).save(&path).unwrap();
+            tasks.push(
+                BatchTask::new(path.to_string_lossy().to_string(), TaskPriority::High)
+                    .with_container(temp.path().to_string_lossy().to_string()),
+            );
+        }
+        let batch =
+            BatchThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let reported = Mutex::new(Vec::new());
+        let results =
+            batch.batch_create_thumbnails_cancellable(tasks, &CancellationToken::new(), |r| {
+                reported.lock().unwrap().push(r.image_path.clone())
+            });
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(reported.lock().unwrap().len(), 3);
+        assert!(results.iter().all(|r| r.thumbnail_path.is_some()));
+        assert!(results.iter().all(|r| r.container_path.is_some()));
+    }
+
+    #[test]
+    fn test_duplicate_tasks_in_batch_are_reported_to_each_task() {
+        use crate::test_helper::test_helpers::TempTestDir;
+
+        let temp = TempTestDir::new_random();
+        let path = temp.path().join("image.png");
+        image::RgbImage::new(20, 20).save(&path).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let tasks = vec![
+            BatchTask::new(path.clone(), TaskPriority::Low).with_container("/a".to_string()),
+            BatchTask::new(path.clone(), TaskPriority::High).with_container("/b".to_string()),
+        ];
+        let batch =
+            BatchThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let results = batch.batch_create_thumbnails(tasks);
+
+        assert_eq!(results.len(), 2);
+        let mut containers: Vec<_> = results
+            .iter()
+            .filter_map(|r| r.container_path.clone())
+            .collect();
+        containers.sort();
+        assert_eq!(containers, vec!["/a".to_string(), "/b".to_string()]);
+        assert_eq!(batch.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_generator_uses_bounded_worker_count() {
+        use crate::test_helper::test_helpers::TempTestDir;
+
+        let temp = TempTestDir::new_random();
+        let batch =
+            BatchThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+        assert!((2..=8).contains(&batch.thread_count()));
+    }
+
+    #[test]
+    fn test_cancelled_batch_skips_tasks() {
+        use crate::test_helper::test_helpers::TempTestDir;
+
+        let temp = TempTestDir::new_random();
+        let tasks = vec![BatchTask::new(
+            "/nonexistent/image.jpg".to_string(),
+            TaskPriority::High,
+        )];
+        let batch =
+            BatchThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let results = batch.batch_create_thumbnails_cancellable(tasks, &token, |_| {
+            panic!("Cancelled job should not report results")
+        });
+
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_panicking_generation_completes_task() {
+        let batch = BatchThumbnailGenerator::with_generate_fn(Box::new(|image_path, _| {
+            if image_path == "/panic.jpg" {
+                panic!("decoder bug");
+            }
+            Ok(PathBuf::from(format!("{}.thumb", image_path)))
+        }))
+        .unwrap();
+
+        let results = batch.batch_create_thumbnails(vec![BatchTask::new(
+            "/panic.jpg".to_string(),
+            TaskPriority::High,
+        )]);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].thumbnail_path.is_none());
+        assert!(results[0].error.as_deref().unwrap().contains("decoder bug"));
+
+        // 同じ画像の再要求も完了し、ワーカーも失われていない
+        let tasks = (0..batch.thread_count() * 2)
+            .map(|i| BatchTask::new(format!("/image{}.jpg", i), TaskPriority::High))
+            .chain([BatchTask::new("/panic.jpg".to_string(), TaskPriority::High)])
+            .collect();
+        let results = batch.batch_create_thumbnails(tasks);
+        assert_eq!(results.len(), batch.thread_count() * 2 + 1);
+        assert_eq!(results.iter().filter(|r| r.error.is_some()).count(), 1);
+        assert_eq!(batch.pending_count(), 0);
+    }
 }
@@ -0,0 +1,211 @@
+// 生成済みサムネイルのメモリ上インデックス
+//
+// 生成に失敗した画像も失敗の理由とともに記録し、画像が変更されるまで再生成しない（ネガティブキャッシュ）。
+// インデックスはディスクに保存しないため、ネガティブキャッシュはアプリを起動している間だけ有効で、
+// 再起動後は失敗した画像も一度だけ生成をやり直す。
+
+use crate::thumbnail::error::ThumbnailError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// ソース画像が変更されていないかを判定するための情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceFingerprint {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl SourceFingerprint {
+    /// ファイルのメタデータから作成（取得できない場合は None）
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// インデックスから引いた結果
+#[derive(Debug, Clone)]
+pub enum CachedThumbnail {
+    /// 生成済みのサムネイルのパス
+    Generated(PathBuf),
+    /// 以前の生成の失敗
+    Failed(ThumbnailError),
+}
+
+/// インデックスのエントリ
+struct IndexEntry {
+    fingerprint: SourceFingerprint,
+    thumbnail: CachedThumbnail,
+}
+
+/// 画像パスから生成済みサムネイル（または生成の失敗）を引くためのインデックス
+///
+/// ソース画像の更新日時とサイズを記録し、変更されていればヒットしない。
+/// エントリはメモリ上にだけ保持し、インスタンスを破棄する（アプリを終了する）と失われる。
+#[derive(Default)]
+pub struct ThumbnailCacheIndex {
+    entries: Mutex<HashMap<String, IndexEntry>>,
+}
+
+impl ThumbnailCacheIndex {
+    /// 空のインデックスを作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 有効なサムネイルのパスを取得
+    ///
+    /// ソース画像が変更された、サムネイルファイルが削除された、または生成に失敗している場合は None
+    pub fn get(&self, image_path: &str) -> Option<PathBuf> {
+        match self.lookup(image_path)? {
+            CachedThumbnail::Generated(thumbnail_path) => Some(thumbnail_path),
+            CachedThumbnail::Failed(_) => None,
+        }
+    }
+
+    /// 生成済みのサムネイル、または以前の生成の失敗を取得
+    ///
+    /// ソース画像が変更された、またはサムネイルファイルが削除された場合は None
+    pub fn lookup(&self, image_path: &str) -> Option<CachedThumbnail> {
+        let fingerprint = SourceFingerprint::from_path(image_path)?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.get(image_path)?;
+
+        let is_stale = match &entry.thumbnail {
+            CachedThumbnail::Generated(thumbnail_path) => !thumbnail_path.exists(),
+            CachedThumbnail::Failed(_) => false,
+        };
+        if entry.fingerprint != fingerprint || is_stale {
+            entries.remove(image_path);
+            return None;
+        }
+        Some(entry.thumbnail.clone())
+    }
+
+    /// 生成済みサムネイルを登録
+    pub fn insert(&self, image_path: &str, thumbnail_path: PathBuf) {
+        self.insert_entry(image_path, CachedThumbnail::Generated(thumbnail_path));
+    }
+
+    /// 生成の失敗を理由とともに登録
+    pub fn insert_failure(&self, image_path: &str, error: ThumbnailError) {
+        self.insert_entry(image_path, CachedThumbnail::Failed(error));
+    }
+
+    fn insert_entry(&self, image_path: &str, thumbnail: CachedThumbnail) {
+        let Some(fingerprint) = SourceFingerprint::from_path(image_path) else {
+            return;
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(
+            image_path.to_string(),
+            IndexEntry {
+                fingerprint,
+                thumbnail,
+            },
+        );
+    }
+
+    /// 登録済みのエントリ数
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// エントリが空か
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn test_get_returns_registered_thumbnail() {
+        let temp = TempTestDir::new_random();
+        let image = temp.path().join("image.jpg");
+        let thumbnail = temp.path().join("thumb.jpg");
+        File::create(&image).unwrap();
+        File::create(&thumbnail).unwrap();
+
+        let index = ThumbnailCacheIndex::new();
+        let image = image.to_str().unwrap();
+        assert!(index.get(image).is_none());
+
+        index.insert(image, thumbnail.clone());
+        assert_eq!(index.get(image), Some(thumbnail));
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_get_misses_when_source_changed() {
+        let temp = TempTestDir::new_random();
+        let image = temp.path().join("image.jpg");
+        let thumbnail = temp.path().join("thumb.jpg");
+        File::create(&image).unwrap();
+        File::create(&thumbnail).unwrap();
+
+        let index = ThumbnailCacheIndex::new();
+        index.insert(image.to_str().unwrap(), thumbnail);
+
+        // サイズが変わればフィンガープリントも変わる
+        File::create(&image).unwrap().write_all(b"changed").unwrap();
+
+        assert!(index.get(image.to_str().unwrap()).is_none());
+        assert!(index.is_empty(), "Stale entry should be evicted");
+    }
+
+    #[test]
+    fn test_get_misses_when_thumbnail_deleted() {
+        let temp = TempTestDir::new_random();
+        let image = temp.path().join("image.jpg");
+        let thumbnail = temp.path().join("thumb.jpg");
+        File::create(&image).unwrap();
+        File::create(&thumbnail).unwrap();
+
+        let index = ThumbnailCacheIndex::new();
+        index.insert(image.to_str().unwrap(), thumbnail.clone());
+        std::fs::remove_file(&thumbnail).unwrap();
+
+        assert!(index.get(image.to_str().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_lookup_returns_failure_until_source_changed() {
+        let temp = TempTestDir::new_random();
+        let image = temp.path().join("broken.jpg");
+        File::create(&image).unwrap().write_all(b"broken").unwrap();
+        let image = image.to_str().unwrap();
+
+        let index = ThumbnailCacheIndex::new();
+        index.insert_failure(image, ThumbnailError::DecodeError("broken".to_string()));
+        assert!(matches!(
+            index.lookup(image),
+            Some(CachedThumbnail::Failed(ThumbnailError::DecodeError(_)))
+        ));
+        assert!(index.get(image).is_none());
+
+        File::create(image)
+            .unwrap()
+            .write_all(b"fixed image")
+            .unwrap();
+        assert!(index.lookup(image).is_none());
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_insert_ignores_missing_source() {
+        let index = ThumbnailCacheIndex::new();
+        index.insert("/nonexistent/image.jpg", PathBuf::from("/cache/thumb.jpg"));
+        assert!(index.is_empty());
+    }
+}
@@ -1,5 +1,58 @@
 // サムネイル生成の設定
 
+use image::Limits;
+
+/// モザイクサムネイルに並べる画像の最大数（2x2のグリッド）
+pub const MAX_MOSAIC_IMAGES: usize = 4;
+
+/// フォルダサムネイルの表示方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FolderThumbnailMode {
+    /// 表紙画像1枚のサムネイル
+    #[default]
+    Single,
+    /// 先頭の複数画像を並べたモザイクサムネイル
+    Mosaic,
+}
+
+/// サムネイルの寸法の決め方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailFit {
+    /// 縦横比を維持して幅 x 高さに収める
+    #[default]
+    Contain,
+    /// 幅 x 高さを覆うように縮小し、はみ出した部分を中央で切り抜く
+    Cover,
+    /// 幅だけを合わせる（高さは縦横比から決まる）
+    Width,
+}
+
+/// リサイズに使うフィルター
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailFilter {
+    /// 最近傍（最も速いが粗い）
+    Nearest,
+    /// 線形補間
+    Triangle,
+    /// Catmull-Rom（3次補間）
+    CatmullRom,
+    /// Lanczos（窓幅3。最も高品質だが遅い）
+    #[default]
+    Lanczos3,
+}
+
+/// 透過画像のサムネイルの背景
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailBackground {
+    /// 市松模様と合成して JPEG で保存する
+    #[default]
+    Checkerboard,
+    /// 指定した色（RGB）と合成して JPEG で保存する
+    Matte([u8; 3]),
+    /// 透過を保持したまま PNG で保存する
+    Transparent,
+}
+
 /// サムネイル生成の設定
 #[derive(Debug, Clone)]
 pub struct ThumbnailConfig {
@@ -14,6 +67,42 @@ pub struct ThumbnailConfig {
 
     /// キャッシュの最大サイズ（バイト）
     pub max_cache_size: u64,
+
+    /// 表示用の縮小画像（レンディション）のキャッシュの最大サイズ（バイト）
+    pub max_rendition_cache_size: u64,
+
+    /// タイルのキャッシュの最大サイズ（バイト）
+    pub max_tile_cache_size: u64,
+
+    /// フォルダサムネイルの表示方法
+    pub folder_mode: FolderThumbnailMode,
+
+    /// モザイクサムネイルに並べる画像の最大数（1-4）
+    pub mosaic_max_images: usize,
+
+    /// モザイクサムネイルの右下に画像枚数バッジ用の領域を確保するか
+    pub mosaic_badge_area: bool,
+
+    /// サムネイルの寸法の決め方
+    pub fit: ThumbnailFit,
+
+    /// 幅 x 高さより小さい画像を拡大するか
+    pub allow_upscale: bool,
+
+    /// リサイズに使うフィルター
+    pub filter: ThumbnailFilter,
+
+    /// デコードする画像の最大ピクセル数（これを超える画像はサムネイルを生成しない）
+    pub max_decode_pixels: u64,
+
+    /// 1枚のデコードに使う最大メモリ量（バイト、RGBA 8bit 換算の見積もり）
+    pub max_decode_bytes: u64,
+
+    /// 同時に行うデコード全体で使う最大メモリ量（バイト）
+    pub decode_memory_budget: u64,
+
+    /// 透過画像の背景（`Transparent` の場合は全てのサムネイルを PNG で保存する）
+    pub background: ThumbnailBackground,
 }
 
 impl Default for ThumbnailConfig {
@@ -22,7 +111,19 @@ impl Default for ThumbnailConfig {
             width: 200,
             height: 200,
             quality: 80,
-            max_cache_size: 1024 * 1024 * 1024, // 1GB
+            max_cache_size: 1024 * 1024 * 1024,           // 1GB
+            max_rendition_cache_size: 1024 * 1024 * 1024, // 1GB
+            max_tile_cache_size: 2 * 1024 * 1024 * 1024,  // 2GB
+            folder_mode: FolderThumbnailMode::Single,
+            mosaic_max_images: MAX_MOSAIC_IMAGES,
+            mosaic_badge_area: true,
+            fit: ThumbnailFit::Contain,
+            allow_upscale: true,
+            filter: ThumbnailFilter::Lanczos3,
+            max_decode_pixels: 16384 * 16384,
+            max_decode_bytes: 1024 * 1024 * 1024,         // 1GB
+            decode_memory_budget: 2 * 1024 * 1024 * 1024, // 2GB
+            background: ThumbnailBackground::Checkerboard,
         }
     }
 }
@@ -35,9 +136,40 @@ impl ThumbnailConfig {
             height,
             quality,
             max_cache_size,
+            ..Self::default()
         }
     }
 
+    /// フォルダサムネイルの表示方法を設定
+    pub fn with_folder_mode(mut self, folder_mode: FolderThumbnailMode) -> Self {
+        self.folder_mode = folder_mode;
+        self
+    }
+
+    /// サムネイルの寸法の決め方を設定
+    pub fn with_fit(mut self, fit: ThumbnailFit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// 小さい画像を拡大するかを設定
+    pub fn with_upscale(mut self, allow_upscale: bool) -> Self {
+        self.allow_upscale = allow_upscale;
+        self
+    }
+
+    /// リサイズに使うフィルターを設定
+    pub fn with_filter(mut self, filter: ThumbnailFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 透過画像の背景を設定
+    pub fn with_background(mut self, background: ThumbnailBackground) -> Self {
+        self.background = background;
+        self
+    }
+
     /// 品質値を検証（1-100の範囲）
     pub fn validate_quality(&self) -> Result<(), String> {
         if self.quality < 1 || self.quality > 100 {
@@ -59,6 +191,40 @@ impl ThumbnailConfig {
         }
         Ok(())
     }
+
+    /// デコードの上限を検証（正の値）
+    pub fn validate_decode_limits(&self) -> Result<(), String> {
+        if self.max_decode_pixels == 0
+            || self.max_decode_bytes == 0
+            || self.decode_memory_budget == 0
+        {
+            return Err(format!(
+                "Decode limits must be positive, got {} pixels, {} bytes, budget {} bytes",
+                self.max_decode_pixels, self.max_decode_bytes, self.decode_memory_budget
+            ));
+        }
+        Ok(())
+    }
+
+    /// デコーダーに渡す上限（image クレートの既定の上限の代わりに使う）
+    ///
+    /// ピクセル数の上限はデコード前の見積もり（寸法の確認）で適用する。
+    pub fn decode_limits(&self) -> Limits {
+        let mut limits = Limits::no_limits();
+        limits.max_alloc = Some(self.max_decode_bytes);
+        limits
+    }
+
+    /// モザイクの画像数を検証（1-4の範囲）
+    pub fn validate_mosaic(&self) -> Result<(), String> {
+        if self.mosaic_max_images < 1 || self.mosaic_max_images > MAX_MOSAIC_IMAGES {
+            return Err(format!(
+                "Mosaic image count must be between 1 and {}, got {}",
+                MAX_MOSAIC_IMAGES, self.mosaic_max_images
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -72,6 +238,44 @@ mod tests {
         assert_eq!(config.height, 200);
         assert_eq!(config.quality, 80);
         assert_eq!(config.max_cache_size, 1024 * 1024 * 1024);
+        assert_eq!(config.max_rendition_cache_size, 1024 * 1024 * 1024);
+        assert_eq!(config.max_tile_cache_size, 2 * 1024 * 1024 * 1024);
+        assert_eq!(config.folder_mode, FolderThumbnailMode::Single);
+        assert_eq!(config.mosaic_max_images, 4);
+        assert!(config.mosaic_badge_area);
+        assert_eq!(config.fit, ThumbnailFit::Contain);
+        assert!(config.allow_upscale);
+        assert_eq!(config.filter, ThumbnailFilter::Lanczos3);
+        assert_eq!(config.background, ThumbnailBackground::Checkerboard);
+        assert_eq!(config.max_decode_pixels, 16384 * 16384);
+        assert!(config.max_decode_bytes <= config.decode_memory_budget);
+    }
+
+    #[test]
+    fn test_validate_decode_limits() {
+        let config = ThumbnailConfig::default();
+        assert!(config.validate_decode_limits().is_ok());
+
+        let mut invalid_config = config.clone();
+        invalid_config.max_decode_pixels = 0;
+        assert!(invalid_config.validate_decode_limits().is_err());
+
+        let mut invalid_config = config.clone();
+        invalid_config.decode_memory_budget = 0;
+        assert!(invalid_config.validate_decode_limits().is_err());
+    }
+
+    #[test]
+    fn test_validate_mosaic() {
+        let config = ThumbnailConfig::default().with_folder_mode(FolderThumbnailMode::Mosaic);
+        assert!(config.validate_mosaic().is_ok());
+
+        let mut invalid_config = config.clone();
+        invalid_config.mosaic_max_images = 0;
+        assert!(invalid_config.validate_mosaic().is_err());
+
+        invalid_config.mosaic_max_images = 5;
+        assert!(invalid_config.validate_mosaic().is_err());
     }
 
     #[test]
@@ -1,39 +1,98 @@
 // サムネイル生成のエラー型定義
 
+use std::sync::Arc;
 use thiserror::Error;
 
 /// サムネイル生成と管理に関するエラー
-#[derive(Error, Debug)]
+///
+/// バッチ生成の結果を複数の呼び出し元で共有できるように `Clone` にしている。
+#[derive(Error, Debug, Clone)]
 pub enum ThumbnailError {
     /// 画像ファイルが見つからない
     #[error("Image file not found: {0}")]
     ImageNotFound(String),
 
+    /// デコードできない形式（[`crate::format`] のレジストリに未登録、またはデコード不可）
+    #[error("Unsupported image format: {0}")]
+    UnsupportedFormat(String),
+
     /// 画像のデコードに失敗
     #[error("Failed to decode image: {0}")]
     DecodeError(String),
 
+    /// デコードの上限（ピクセル数・メモリ量）を超える大きな画像
+    #[error("Image is too large to decode: {path} ({width}x{height})")]
+    ImageTooLarge {
+        path: String,
+        width: u32,
+        height: u32,
+    },
+
+    /// 以前に生成に失敗し、その後ソース画像が変更されていない（以前の失敗の理由を保持する）
+    #[error("Thumbnail generation failed previously: {0}")]
+    CachedFailure(String),
+
     /// サムネイル生成に失敗
     #[error("Failed to generate thumbnail: {0}")]
     GenerationError(String),
 
+    /// コンテナ（フォルダ・アーカイブ）の読み込みに失敗
+    #[error("Failed to read container: {0}")]
+    ContainerError(String),
+
     /// キャッシュディレクトリへのアクセスに失敗
     #[error("Cache directory access failed: {0}")]
     CacheAccessError(String),
 
     /// I/Oエラー
     #[error("I/O error: {0}")]
-    IoError(#[from] std::io::Error),
+    IoError(#[source] Arc<std::io::Error>),
 
     /// 画像処理エラー
     #[error("Image processing error: {0}")]
-    ImageError(#[from] image::ImageError),
+    ImageError(#[source] Arc<image::ImageError>),
 
     /// 設定バリデーションエラー
     #[error("Configuration validation error: {0}")]
     ConfigError(String),
 }
 
+impl ThumbnailError {
+    /// ソース画像に原因があり、画像が変更されるまで再試行しても失敗するエラーか
+    pub fn is_source_error(&self) -> bool {
+        matches!(
+            self,
+            ThumbnailError::UnsupportedFormat(_)
+                | ThumbnailError::DecodeError(_)
+                | ThumbnailError::ImageTooLarge { .. }
+                | ThumbnailError::ImageError(_)
+                | ThumbnailError::CachedFailure(_)
+        )
+    }
+
+    /// キャッシュした失敗を後の呼び出しで返すときのエラー
+    ///
+    /// 画像の大きさによる失敗はそのまま返し、それ以外は理由を保持した `CachedFailure` にする。
+    pub fn into_cached(self) -> Self {
+        match self {
+            ThumbnailError::ImageTooLarge { .. } | ThumbnailError::CachedFailure(_) => self,
+            other => ThumbnailError::CachedFailure(other.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for ThumbnailError {
+    fn from(e: std::io::Error) -> Self {
+        ThumbnailError::IoError(Arc::new(e))
+    }
+}
+
+impl From<image::ImageError> for ThumbnailError {
+    fn from(e: image::ImageError) -> Self {
+        ThumbnailError::ImageError(Arc::new(e))
+    }
+}
+
 impl From<String> for ThumbnailError {
     fn from(s: String) -> Self {
         ThumbnailError::ConfigError(s)
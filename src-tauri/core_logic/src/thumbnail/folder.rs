@@ -1,16 +1,63 @@
 // フォルダサムネイル取得のためのユーティリティ
 
 use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
-use crate::{list_images_in_container, thumbnail::batch::TaskPriority};
+use crate::{
+    list_images_in_container,
+    thumbnail::batch::TaskPriority,
+    utils::{natural_cmp_file_name, write_json_atomic},
+};
+
+/// カバー画像として優先するファイル名（拡張子を除く、優先度順）
+/// これらの次に `000` のようなゼロのみのファイル名を優先する
+const COVER_FILE_STEMS: &[&str] = &["cover", "folder"];
 
 /// フォルダサムネイル取得結果
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FolderThumbnailResult {
+    /// カバーとして選ばれた画像のパス
     pub image_path: String,
     pub thumbnail_path: String,
     pub image_name: String,
+    /// カバー画像が選ばれた理由
+    pub cover_source: CoverSource,
+    /// フォルダ内の画像の枚数
+    pub image_count: usize,
+    /// 複数の画像を並べたモザイクサムネイルか
+    pub mosaic: bool,
+}
+
+/// カバー画像の選択理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoverSource {
+    /// ユーザーが指定したカバー画像
+    Override,
+    /// `cover` / `folder` / `000` などのファイル名による選択
+    Named,
+    /// 自然順で最初の画像
+    FirstImage,
+}
+
+/// カバー画像の選択結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverSelection {
+    pub image_path: String,
+    pub source: CoverSource,
+}
+
+/// フォルダサムネイルに使用する画像
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderCover {
+    pub selection: CoverSelection,
+    /// フォルダ内の画像の枚数
+    pub image_count: usize,
+    /// サムネイルに並べる画像（先頭はカバー画像、以降は自然順）
+    pub images: Vec<String>,
 }
 
 /// インデックスから優先度を決定
@@ -27,24 +74,172 @@ pub fn assign_priority(index: usize) -> TaskPriority {
     }
 }
 
-/// フォルダ内の最初の画像ファイルパスを取得
+/// フォルダ内の最初の画像ファイルパス（ファイル名の自然順）を取得
 /// crate::fs::list_images_in_container を内部で使用
 pub fn get_first_image_in_folder<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
     folder_path: P,
     cache_dir: Q,
 ) -> Result<Option<String>, String> {
-    let folder_path = folder_path.as_ref();
-    let cache_dir = cache_dir.as_ref();
+    let images = list_folder_images(folder_path.as_ref(), cache_dir.as_ref())?;
+    Ok(images
+        .into_iter()
+        .min_by(|a, b| natural_cmp_file_name(a, b)))
+}
+
+/// フォルダのカバー画像を選択
+///
+/// 1. `cover_override` がフォルダ内の画像であればそれを使う
+/// 2. `cover` / `folder` / `000` という名前の画像（この順で優先）
+/// 3. 自然順で最初の画像
+pub fn select_folder_cover<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    folder_path: P,
+    cache_dir: Q,
+    cover_override: Option<&str>,
+) -> Result<Option<CoverSelection>, String> {
+    let images = list_folder_images(folder_path.as_ref(), cache_dir.as_ref())?;
+    Ok(select_cover_image(images, cover_override))
+}
+
+/// フォルダのカバー画像と、サムネイルに並べる最大 `max_images` 枚の画像を選択
+///
+/// カバー画像を先頭に、残りを自然順で続ける（モザイクサムネイル用）。
+pub fn select_folder_cover_images<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    folder_path: P,
+    cache_dir: Q,
+    cover_override: Option<&str>,
+    max_images: usize,
+) -> Result<Option<FolderCover>, String> {
+    let mut images = list_folder_images(folder_path.as_ref(), cache_dir.as_ref())?;
+    let Some(selection) = select_cover_image(images.clone(), cover_override) else {
+        return Ok(None);
+    };
+
+    let image_count = images.len();
+    images.sort_by(|a, b| natural_cmp_file_name(a, b));
+    let rest = images
+        .into_iter()
+        .filter(|image| *image != selection.image_path);
+    let images = std::iter::once(selection.image_path.clone())
+        .chain(rest)
+        .take(max_images.max(1))
+        .collect();
+
+    Ok(Some(FolderCover {
+        selection,
+        image_count,
+        images,
+    }))
+}
+
+/// 画像のリストからカバー画像を選択（選択規則は [`select_folder_cover`] を参照）
+pub fn select_cover_image(
+    mut images: Vec<String>,
+    cover_override: Option<&str>,
+) -> Option<CoverSelection> {
+    if let Some(cover_override) = cover_override {
+        if images.iter().any(|image| image == cover_override) {
+            return Some(CoverSelection {
+                image_path: cover_override.to_string(),
+                source: CoverSource::Override,
+            });
+        }
+    }
+
+    images.sort_by(|a, b| natural_cmp_file_name(a, b));
+
+    let named = images
+        .iter()
+        .filter_map(|image| cover_name_rank(image).map(|rank| (rank, image)))
+        .min_by_key(|(rank, _)| *rank);
+    if let Some((_, image)) = named {
+        return Some(CoverSelection {
+            image_path: image.clone(),
+            source: CoverSource::Named,
+        });
+    }
+
+    images.into_iter().next().map(|image_path| CoverSelection {
+        image_path,
+        source: CoverSource::FirstImage,
+    })
+}
 
-    let images = list_images_in_container(folder_path, cache_dir).map_err(|e| {
+/// カバー画像らしいファイル名の優先順位（小さいほど優先、該当しない場合は None）
+fn cover_name_rank(image_path: &str) -> Option<usize> {
+    let stem = Path::new(image_path).file_stem()?.to_str()?.to_lowercase();
+    if let Some(rank) = COVER_FILE_STEMS.iter().position(|name| *name == stem) {
+        return Some(rank);
+    }
+    if !stem.is_empty() && stem.chars().all(|c| c == '0') {
+        return Some(COVER_FILE_STEMS.len());
+    }
+    None
+}
+
+fn list_folder_images(folder_path: &Path, cache_dir: &Path) -> Result<Vec<String>, String> {
+    list_images_in_container(folder_path, cache_dir).map_err(|e| {
         format!(
             "Failed to list images in '{}': {:?}",
             folder_path.display(),
             e
         )
-    })?;
+    })
+}
 
-    Ok(images.into_iter().next())
+/// ユーザーが指定したフォルダごとのカバー画像
+///
+/// ファイルパスを指定して読み込んだ場合、変更のたびにJSONとして保存する。
+#[derive(Default)]
+pub struct CoverOverrides {
+    file_path: Option<PathBuf>,
+    entries: RwLock<HashMap<String, String>>,
+}
+
+impl CoverOverrides {
+    /// 保存しないカバー指定を作成
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// JSONファイルからカバー指定を読み込む（ファイルが存在しない場合は空）
+    pub fn load<P: AsRef<Path>>(file_path: P) -> std::io::Result<Self> {
+        let file_path = file_path.as_ref();
+        let entries = match std::fs::read(file_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            file_path: Some(file_path.to_path_buf()),
+            entries: RwLock::new(entries),
+        })
+    }
+
+    /// フォルダのカバー指定を取得
+    pub fn get(&self, container_path: &str) -> Option<String> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.get(container_path).cloned()
+    }
+
+    /// フォルダのカバー指定を設定（`None` で解除）し、保存する
+    pub fn set(&self, container_path: &str, image_path: Option<String>) -> std::io::Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        match image_path {
+            Some(image_path) => {
+                entries.insert(container_path.to_string(), image_path);
+            }
+            None => {
+                entries.remove(container_path);
+            }
+        }
+
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+        write_json_atomic(file_path, &*entries)
+    }
 }
 
 #[cfg(test)]
@@ -61,6 +256,9 @@ mod tests {
             image_path: "/photos/folder1/image1.jpg".to_string(),
             thumbnail_path: "/cache/thumbnails/abc123.jpg".to_string(),
             image_name: "image1.jpg".to_string(),
+            cover_source: CoverSource::FirstImage,
+            image_count: 3,
+            mosaic: false,
         };
 
         let json = serde_json::to_value(&result).unwrap();
@@ -76,6 +274,9 @@ mod tests {
         assert_eq!(json["imagePath"], "/photos/folder1/image1.jpg");
         assert_eq!(json["thumbnailPath"], "/cache/thumbnails/abc123.jpg");
         assert_eq!(json["imageName"], "image1.jpg");
+        assert_eq!(json["coverSource"], "firstImage");
+        assert_eq!(json["imageCount"], 3);
+        assert_eq!(json["mosaic"], false);
     }
 
     // --- assign_priority のテスト ---
@@ -148,4 +349,156 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_none(), "Empty folder should return None");
     }
+
+    #[test]
+    fn test_get_first_image_uses_natural_order() {
+        let temp = TempTestDir::new_random();
+        for name in ["10.jpg", "2.jpg", "credits.png"] {
+            File::create(temp.path().join(name)).unwrap();
+        }
+
+        let first = get_first_image_in_folder(temp.path(), "").unwrap().unwrap();
+        assert!(first.ends_with("2.jpg"), "got: {}", first);
+    }
+
+    // --- カバー画像選択のテスト ---
+
+    fn images(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| format!("/book/{}", n)).collect()
+    }
+
+    #[test]
+    fn test_select_cover_prefers_named_cover() {
+        let selection =
+            select_cover_image(images(&["001.jpg", "credits.jpg", "Cover.JPG"]), None).unwrap();
+        assert_eq!(selection.image_path, "/book/Cover.JPG");
+        assert_eq!(selection.source, CoverSource::Named);
+    }
+
+    #[test]
+    fn test_select_cover_named_priority() {
+        let selection = select_cover_image(images(&["000.jpg", "folder.png"]), None).unwrap();
+        assert_eq!(selection.image_path, "/book/folder.png");
+
+        let selection = select_cover_image(images(&["001.jpg", "000.jpg"]), None).unwrap();
+        assert_eq!(selection.image_path, "/book/000.jpg");
+        assert_eq!(selection.source, CoverSource::Named);
+    }
+
+    #[test]
+    fn test_select_cover_falls_back_to_natural_first() {
+        let selection =
+            select_cover_image(images(&["page10.jpg", "page2.jpg", "page1.jpg"]), None).unwrap();
+        assert_eq!(selection.image_path, "/book/page1.jpg");
+        assert_eq!(selection.source, CoverSource::FirstImage);
+    }
+
+    #[test]
+    fn test_select_cover_honors_override() {
+        let selection =
+            select_cover_image(images(&["cover.jpg", "page2.jpg"]), Some("/book/page2.jpg"))
+                .unwrap();
+        assert_eq!(selection.image_path, "/book/page2.jpg");
+        assert_eq!(selection.source, CoverSource::Override);
+    }
+
+    #[test]
+    fn test_select_cover_ignores_stale_override() {
+        let selection =
+            select_cover_image(images(&["cover.jpg"]), Some("/book/deleted.jpg")).unwrap();
+        assert_eq!(selection.image_path, "/book/cover.jpg");
+        assert_eq!(selection.source, CoverSource::Named);
+    }
+
+    #[test]
+    fn test_select_cover_returns_none_for_no_images() {
+        assert!(select_cover_image(Vec::new(), Some("/book/cover.jpg")).is_none());
+    }
+
+    #[test]
+    fn test_select_folder_cover_reads_folder() {
+        let temp = TempTestDir::new_random();
+        for name in ["1.jpg", "cover.png"] {
+            File::create(temp.path().join(name)).unwrap();
+        }
+
+        let selection = select_folder_cover(temp.path(), "", None).unwrap().unwrap();
+        assert!(selection.image_path.ends_with("cover.png"));
+    }
+
+    #[test]
+    fn test_select_folder_cover_images_puts_cover_first() {
+        let temp = TempTestDir::new_random();
+        for name in ["10.jpg", "2.jpg", "1.jpg", "cover.png", "3.jpg"] {
+            File::create(temp.path().join(name)).unwrap();
+        }
+
+        let cover = select_folder_cover_images(temp.path(), "", None, 4)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cover.image_count, 5);
+        let names: Vec<_> = cover
+            .images
+            .iter()
+            .map(|p| Path::new(p).file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["cover.png", "1.jpg", "2.jpg", "3.jpg"]);
+        assert_eq!(cover.selection.image_path, cover.images[0]);
+
+        let single = select_folder_cover_images(temp.path(), "", None, 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(single.images.len(), 1);
+    }
+
+    #[test]
+    fn test_select_folder_cover_images_returns_none_for_empty_folder() {
+        let temp = TempTestDir::new_random();
+        assert!(select_folder_cover_images(temp.path(), "", None, 4)
+            .unwrap()
+            .is_none());
+    }
+
+    // --- CoverOverrides のテスト ---
+
+    #[test]
+    fn test_cover_overrides_persist_to_file() {
+        let temp = TempTestDir::new_random();
+        let file_path = temp.path().join("settings").join("covers.json");
+
+        let overrides = CoverOverrides::load(&file_path).unwrap();
+        assert!(overrides.get("/book").is_none());
+        overrides
+            .set("/book", Some("/book/page2.jpg".to_string()))
+            .unwrap();
+
+        let reloaded = CoverOverrides::load(&file_path).unwrap();
+        assert_eq!(reloaded.get("/book").as_deref(), Some("/book/page2.jpg"));
+
+        reloaded.set("/book", None).unwrap();
+        let reloaded = CoverOverrides::load(&file_path).unwrap();
+        assert!(reloaded.get("/book").is_none());
+    }
+
+    #[test]
+    fn test_cover_overrides_load_rejects_invalid_json() {
+        let temp = TempTestDir::new_random();
+        let file_path = temp.path().join("covers.json");
+        std::fs::write(&file_path, b"not json").unwrap();
+
+        let result = CoverOverrides::load(&file_path);
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_cover_overrides_in_memory() {
+        let overrides = CoverOverrides::in_memory();
+        overrides
+            .set("/book", Some("/book/1.jpg".to_string()))
+            .unwrap();
+        assert_eq!(overrides.get("/book").as_deref(), Some("/book/1.jpg"));
+    }
 }
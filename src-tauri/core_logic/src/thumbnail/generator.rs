@@ -1,24 +1,64 @@
 // サムネイル画像生成のコアロジック
 
-use crate::thumbnail::config::ThumbnailConfig;
+use crate::decode::{open_image_to_fit_with_limits, read_image_info, ImageInfo};
+use crate::format::FormatDecoder;
+use crate::thumbnail::config::{
+    ThumbnailBackground, ThumbnailConfig, ThumbnailFilter, ThumbnailFit,
+};
 use crate::thumbnail::error::{Result, ThumbnailError};
+use crate::thumbnail::memory::MemoryBudget;
 use crate::utils::hash_path;
 use image::imageops::FilterType;
-use image::{GenericImageView, ImageFormat};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgb, RgbImage, Rgba};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// モザイクサムネイルのタイル間の余白（ピクセル）
+const MOSAIC_GAP: u32 = 2;
+
+/// モザイクサムネイルの背景色
+const MOSAIC_BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+
+/// デコード後の1ピクセルあたりのメモリ量の見積もり（RGBA 8bit）
+const BYTES_PER_PIXEL: u64 = 4;
+
+/// 市松模様のマスの大きさ（ピクセル）
+const CHECKERBOARD_CELL: u32 = 8;
+
+/// 市松模様の2色
+const CHECKERBOARD_COLORS: [[u8; 3]; 2] = [[255, 255, 255], [204, 204, 204]];
 
 /// サムネイル画像の生成と管理
+///
+/// 複数のスレッドから同時に使う場合、デコードのメモリ使用量は `decode_memory_budget` の範囲に制限される。
 pub struct ThumbnailGenerator {
     config: ThumbnailConfig,
     cache_dir: PathBuf,
+    memory: Arc<MemoryBudget>,
 }
 
 impl ThumbnailGenerator {
     /// 新しいThumbnailGeneratorを作成
     pub fn new(config: ThumbnailConfig, cache_dir: PathBuf) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, cache_dir, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してThumbnailGeneratorを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        cache_dir: PathBuf,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
         config.validate_quality()?;
         config.validate_size()?;
-        Ok(Self { config, cache_dir })
+        config.validate_mosaic()?;
+        config.validate_decode_limits()?;
+        Ok(Self {
+            config,
+            cache_dir,
+            memory,
+        })
     }
 
     /// デフォルト設定でThumbnailGeneratorを作成
@@ -39,25 +79,16 @@ impl ThumbnailGenerator {
         if !source_path.exists() {
             return Err(ThumbnailError::ImageNotFound(image_path.to_string()));
         }
+        if !crate::format::can_decode(source_path) {
+            return Err(ThumbnailError::UnsupportedFormat(image_path.to_string()));
+        }
 
         // サムネイルのキャッシュパスを計算
         let cache_path = self.get_thumbnail_cache_path(image_path);
 
-        // TODO: ifのネストが深い。優先度は低いが、将来的にリファクタリングして早期リターンを増やすことを検討
         // キャッシュが存在し、ソースより新しい場合はそれを返す
-        if cache_path.exists() {
-            if let (Ok(cache_metadata), Ok(source_metadata)) = (
-                std::fs::metadata(&cache_path),
-                std::fs::metadata(source_path),
-            ) {
-                if let (Ok(cache_modified), Ok(source_modified)) =
-                    (cache_metadata.modified(), source_metadata.modified())
-                {
-                    if cache_modified >= source_modified {
-                        return Ok(cache_path);
-                    }
-                }
-            }
+        if is_cache_fresh(&cache_path, &[image_path]) {
+            return Ok(cache_path);
         }
 
         // サムネイルを生成
@@ -65,50 +96,259 @@ impl ThumbnailGenerator {
         Ok(cache_path)
     }
 
+    /// 複数の画像を並べたモザイクサムネイルを生成または取得
+    ///
+    /// 先頭から `mosaic_max_images` 枚までを使用する。
+    /// 読み込めない画像は読み飛ばし、1枚も読み込めない場合はエラーになる。
+    ///
+    /// # Arguments
+    /// * `image_paths` - 並べる画像のパス（左上から順に配置）
+    ///
+    /// # Returns
+    /// サムネイルのキャッシュパス
+    pub fn get_or_create_mosaic_thumbnail(&self, image_paths: &[String]) -> Result<PathBuf> {
+        let image_paths = &image_paths[..image_paths.len().min(self.config.mosaic_max_images)];
+        let Some(first) = image_paths.first() else {
+            return Err(ThumbnailError::GenerationError(
+                "No images for mosaic thumbnail".to_string(),
+            ));
+        };
+        if !image_paths.iter().any(|path| Path::new(path).exists()) {
+            return Err(ThumbnailError::ImageNotFound(first.clone()));
+        }
+
+        let cache_path = self.get_mosaic_cache_path(image_paths);
+        if is_cache_fresh(&cache_path, image_paths) {
+            return Ok(cache_path);
+        }
+
+        self.generate_mosaic_thumbnail(image_paths, &cache_path)?;
+        Ok(cache_path)
+    }
+
     /// サムネイルのキャッシュパスを計算
+    ///
+    /// 設定を変えたときに古い設定のサムネイルを使わないよう、出力に影響する設定をファイル名に含める。
     fn get_thumbnail_cache_path(&self, image_path: &str) -> PathBuf {
         let hash = hash_path(&image_path);
-        let cache_file = format!("{}.jpg", hash);
+        let cache_file = format!(
+            "{}-{}.{}",
+            hash,
+            self.config_fingerprint(),
+            self.output_extension()
+        );
+        self.cache_dir.join(cache_file)
+    }
+
+    /// モザイクサムネイルのキャッシュパスを計算
+    ///
+    /// 単一画像のサムネイルと衝突しないよう、画像の組み合わせとバッジ領域の有無から別のキーを作る。
+    fn get_mosaic_cache_path(&self, image_paths: &[String]) -> PathBuf {
+        let key = format!(
+            "mosaic:{}:{}",
+            self.config.mosaic_badge_area,
+            image_paths.join("\n")
+        );
+        let cache_file = format!(
+            "{}-{}-mosaic.{}",
+            hash_path(&key),
+            self.config_fingerprint(),
+            self.output_extension()
+        );
         self.cache_dir.join(cache_file)
     }
 
+    /// サムネイルの出力に影響する設定のハッシュ（先頭16文字）
+    fn config_fingerprint(&self) -> String {
+        let config = &self.config;
+        let key = format!(
+            "{}x{}:{}:{:?}:{}:{:?}:{:?}:{}",
+            config.width,
+            config.height,
+            config.quality,
+            config.fit,
+            config.allow_upscale,
+            config.filter,
+            config.background,
+            config.mosaic_badge_area
+        );
+        hash_path(&key)[..16].to_string()
+    }
+
+    /// サムネイルの保存形式（透過を保持する場合のみ PNG）
+    fn output_format(&self) -> ImageFormat {
+        match self.config.background {
+            ThumbnailBackground::Transparent => ImageFormat::Png,
+            _ => ImageFormat::Jpeg,
+        }
+    }
+
+    /// サムネイルの拡張子
+    fn output_extension(&self) -> &'static str {
+        self.output_format().extensions_str()[0]
+    }
+
     /// サムネイルを生成してキャッシュに保存
     ///
     /// # Arguments
     /// * `image_path` - ソース画像のパス
     /// * `output_path` - サムネイルの保存先パス
     fn generate_thumbnail(&self, image_path: &str, output_path: &Path) -> Result<()> {
-        // 画像を読み込み
-        let img = image::open(image_path).map_err(|e| {
-            ThumbnailError::DecodeError(format!("Failed to open image {}: {}", image_path, e))
-        })?;
+        let (info, decode_bytes) = estimate_decode(
+            &self.config,
+            image_path,
+            (self.config.width, self.config.height),
+        )?;
 
-        // サムネイルサイズを計算（アスペクト比を維持）
+        // 画像を読み込み（大きな画像は必要なサイズまで縮小して開く）
+        let (fit_width, fit_height) = match self.config.fit {
+            ThumbnailFit::Contain => (self.config.width, self.config.height),
+            ThumbnailFit::Width => (self.config.width, u32::MAX),
+            ThumbnailFit::Cover => self.cover_decode_size(info.width, info.height),
+        };
+        let _reservation = self.memory.reserve(decode_bytes);
+        let img = open_image_to_fit_with_limits(
+            image_path,
+            fit_width,
+            fit_height,
+            &self.config.decode_limits(),
+        )
+        .map_err(|e| open_error(image_path, &info, e))?;
+
+        // サムネイルサイズを計算してリサイズ
         let (width, height) = img.dimensions();
         let (thumb_width, thumb_height) = self.calculate_thumbnail_dimensions(width, height);
-
-        // Lanczos3フィルターでリサイズ（高品質）
-        let thumbnail = img.resize(thumb_width, thumb_height, FilterType::Lanczos3);
+        let filter = filter_type(self.config.filter);
+        let thumbnail = if (thumb_width, thumb_height) == (width, height) {
+            img
+        } else if self.config.fit == ThumbnailFit::Cover {
+            img.resize_to_fill(thumb_width, thumb_height, filter)
+        } else {
+            img.resize_exact(thumb_width, thumb_height, filter)
+        };
 
         // 出力ディレクトリが存在することを確認
         if let Some(parent) = output_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        // JPEGとして保存
-        thumbnail
-            .save_with_format(output_path, ImageFormat::Jpeg)
-            .map_err(|e| {
-                ThumbnailError::GenerationError(format!(
-                    "Failed to save thumbnail to {:?}: {}",
-                    output_path, e
-                ))
-            })?;
+        let thumbnail = composite_background(thumbnail, self.config.background);
+        save_thumbnail(&thumbnail, output_path, self.output_format())
+    }
+
+    /// モザイクサムネイルを生成してキャッシュに保存
+    ///
+    /// 1枚なら全体、2枚なら左右、3-4枚なら2x2のグリッドに並べる。
+    fn generate_mosaic_thumbnail(&self, image_paths: &[String], output_path: &Path) -> Result<()> {
+        // 上限を超える画像は読み飛ばし、残りの画像をまとめて予約してから開く
+        let decodable: Vec<(&String, u64)> = image_paths
+            .iter()
+            .filter_map(|path| {
+                match estimate_decode(&self.config, path, (self.config.width, self.config.height)) {
+                    Ok((_, bytes)) => Some((path, bytes)),
+                    Err(e) => {
+                        log::warn!("Skipping mosaic tile {}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        let _reservation = self
+            .memory
+            .reserve(decodable.iter().map(|(_, bytes)| bytes).sum());
+
+        let limits = self.config.decode_limits();
+        let images: Vec<DynamicImage> = decodable
+            .into_iter()
+            .filter_map(|(path, _)| {
+                match open_image_to_fit_with_limits(
+                    path,
+                    self.config.width,
+                    self.config.height,
+                    &limits,
+                ) {
+                    Ok(img) => Some(img),
+                    Err(e) => {
+                        log::warn!("Skipping mosaic tile {}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        if images.is_empty() {
+            return Err(ThumbnailError::DecodeError(format!(
+                "Failed to open any image for mosaic: {}",
+                image_paths.join(", ")
+            )));
+        }
+
+        let (width, height) = (self.config.width, self.config.height);
+        let (columns, rows) = match images.len() {
+            1 => (1, 1),
+            2 => (2, 1),
+            _ => (2, 2),
+        };
+        let cell_width = (width.saturating_sub(MOSAIC_GAP * (columns - 1)) / columns).max(1);
+        let cell_height = (height.saturating_sub(MOSAIC_GAP * (rows - 1)) / rows).max(1);
+
+        let mut canvas = RgbImage::from_pixel(width, height, MOSAIC_BACKGROUND);
+        for (i, img) in images.iter().enumerate() {
+            let column = i as u32 % columns;
+            let row = i as u32 / columns;
+            // セルを埋めるように切り抜いてリサイズ
+            // モザイク自体は不透明なため、透過を保持する設定ではキャンバスの背景色と合成する
+            let background = match self.config.background {
+                ThumbnailBackground::Transparent => ThumbnailBackground::Matte(MOSAIC_BACKGROUND.0),
+                background => background,
+            };
+            let tile = composite_background(
+                img.resize_to_fill(cell_width, cell_height, filter_type(self.config.filter)),
+                background,
+            )
+            .to_rgb8();
+            image::imageops::overlay(
+                &mut canvas,
+                &tile,
+                (column * (cell_width + MOSAIC_GAP)) as i64,
+                (row * (cell_height + MOSAIC_GAP)) as i64,
+            );
+        }
+
+        if self.config.mosaic_badge_area {
+            darken_badge_area(&mut canvas);
+        }
+
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        save_thumbnail(
+            &DynamicImage::ImageRgb8(canvas),
+            output_path,
+            self.output_format(),
+        )
+    }
 
-        Ok(())
+    /// `Cover` で画像を開くときに収めるサイズ
+    ///
+    /// 切り抜く前の、幅 x 高さを覆う大きさ（拡大しない設定では元の寸法以下）を返す。
+    fn cover_decode_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let mut scale = f64::max(
+            self.config.width as f64 / width as f64,
+            self.config.height as f64 / height as f64,
+        );
+        if !self.config.allow_upscale {
+            scale = scale.min(1.0);
+        }
+        (
+            ((width as f64 * scale).ceil() as u32).max(1),
+            ((height as f64 * scale).ceil() as u32).max(1),
+        )
     }
 
-    /// サムネイルの寸法を計算（アスペクト比を維持）
+    /// サムネイルの寸法を計算
+    ///
+    /// `Contain` と `Width` はアスペクト比を維持した寸法、`Cover` は切り抜いた後の寸法を返す。
+    /// 拡大しない設定では、元の画像より大きくならない。
     ///
     /// # Arguments
     /// * `width` - 元の画像の幅
@@ -119,6 +359,25 @@ impl ThumbnailGenerator {
     fn calculate_thumbnail_dimensions(&self, width: u32, height: u32) -> (u32, u32) {
         let target_width = self.config.width;
         let target_height = self.config.height;
+        let allow_upscale = self.config.allow_upscale;
+
+        match self.config.fit {
+            ThumbnailFit::Cover if allow_upscale => return (target_width, target_height),
+            ThumbnailFit::Cover => return (target_width.min(width), target_height.min(height)),
+            ThumbnailFit::Width if !allow_upscale && width <= target_width => {
+                return (width, height)
+            }
+            ThumbnailFit::Width => {
+                let new_height = (target_width as f64 * height as f64 / width as f64) as u32;
+                return (target_width, new_height.max(1));
+            }
+            ThumbnailFit::Contain
+                if !allow_upscale && width <= target_width && height <= target_height =>
+            {
+                return (width, height)
+            }
+            ThumbnailFit::Contain => {}
+        }
 
         // アスペクト比を計算
         let aspect_ratio = width as f64 / height as f64;
@@ -136,6 +395,125 @@ impl ThumbnailGenerator {
     }
 }
 
+/// デコードに必要なメモリ量を見積もる
+///
+/// 画像全体はデコードせず、寸法から見積もる。
+/// SVG は収めるサイズで描画するため、宣言サイズではなく `svg_size`（出力の大きさ）で見積もる。
+/// 上限（`max_decode_pixels` / `max_decode_bytes`）を超える画像は `ImageTooLarge` になる。
+pub(crate) fn estimate_decode(
+    config: &ThumbnailConfig,
+    image_path: &str,
+    svg_size: (u32, u32),
+) -> Result<(ImageInfo, u64)> {
+    let info = read_image_info(image_path).map_err(|e| decode_error(image_path, e))?;
+    if crate::format::registry().decoder(image_path) == Some(FormatDecoder::Svg) {
+        let pixels = u64::from(svg_size.0) * u64::from(svg_size.1);
+        return Ok((info, pixels * BYTES_PER_PIXEL));
+    }
+
+    let pixels = u64::from(info.width) * u64::from(info.height);
+    let bytes = pixels * BYTES_PER_PIXEL;
+    if pixels > config.max_decode_pixels || bytes > config.max_decode_bytes {
+        return Err(ThumbnailError::ImageTooLarge {
+            path: image_path.to_string(),
+            width: info.width,
+            height: info.height,
+        });
+    }
+    Ok((info, bytes))
+}
+
+pub(super) fn decode_error(image_path: &str, e: image::ImageError) -> ThumbnailError {
+    ThumbnailError::DecodeError(format!("Failed to open image {}: {}", image_path, e))
+}
+
+/// 画像を開くときのエラーを変換する（デコーダーの上限を超えた場合は `ImageTooLarge`）
+pub(super) fn open_error(
+    image_path: &str,
+    info: &ImageInfo,
+    e: image::ImageError,
+) -> ThumbnailError {
+    match e {
+        image::ImageError::Limits(_) => ThumbnailError::ImageTooLarge {
+            path: image_path.to_string(),
+            width: info.width,
+            height: info.height,
+        },
+        e => decode_error(image_path, e),
+    }
+}
+
+/// 設定のフィルターを image クレートのフィルターに変換する
+pub(super) fn filter_type(filter: ThumbnailFilter) -> FilterType {
+    match filter {
+        ThumbnailFilter::Nearest => FilterType::Nearest,
+        ThumbnailFilter::Triangle => FilterType::Triangle,
+        ThumbnailFilter::CatmullRom => FilterType::CatmullRom,
+        ThumbnailFilter::Lanczos3 => FilterType::Lanczos3,
+    }
+}
+
+/// キャッシュが存在し、全てのソースより新しいか
+pub(super) fn is_cache_fresh<P: AsRef<Path>>(cache_path: &Path, sources: &[P]) -> bool {
+    let Ok(cache_modified) = std::fs::metadata(cache_path).and_then(|m| m.modified()) else {
+        return false;
+    };
+    sources.iter().all(|source| {
+        std::fs::metadata(source)
+            .and_then(|m| m.modified())
+            .is_ok_and(|source_modified| cache_modified >= source_modified)
+    })
+}
+
+/// 画像枚数バッジを重ねる右下の領域を暗くする
+fn darken_badge_area(canvas: &mut RgbImage) {
+    let (width, height) = canvas.dimensions();
+    let (x_start, y_start) = (width * 3 / 5, height * 4 / 5);
+    for y in y_start..height {
+        for x in x_start..width {
+            let pixel = canvas.get_pixel_mut(x, y);
+            pixel.0 = pixel.0.map(|c| c / 2);
+        }
+    }
+}
+
+/// 透過部分を背景と合成する
+///
+/// 透過を保持する設定やアルファを持たない画像はそのまま返す。
+fn composite_background(img: DynamicImage, background: ThumbnailBackground) -> DynamicImage {
+    if background == ThumbnailBackground::Transparent || !img.color().has_alpha() {
+        return img;
+    }
+
+    let mut pixels = img.into_rgba8();
+    for (x, y, pixel) in pixels.enumerate_pixels_mut() {
+        let base = match background {
+            ThumbnailBackground::Matte(color) => color,
+            _ => {
+                CHECKERBOARD_COLORS[((x / CHECKERBOARD_CELL + y / CHECKERBOARD_CELL) % 2) as usize]
+            }
+        };
+        let Rgba([r, g, b, a]) = *pixel;
+        let blend = |fg: u8, bg: u8| {
+            ((fg as u32 * a as u32 + bg as u32 * (255 - a as u32) + 127) / 255) as u8
+        };
+        *pixel = Rgba([blend(r, base[0]), blend(g, base[1]), blend(b, base[2]), 255]);
+    }
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(pixels).into_rgb8())
+}
+
+/// サムネイルを保存
+///
+/// ICC プロファイルは埋め込まない（読み込み時に sRGB に変換済みのため、タグなしの sRGB として扱われる）。
+fn save_thumbnail(img: &DynamicImage, output_path: &Path, format: ImageFormat) -> Result<()> {
+    img.save_with_format(output_path, format).map_err(|e| {
+        ThumbnailError::GenerationError(format!(
+            "Failed to save thumbnail to {:?}: {}",
+            output_path, e
+        ))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,8 +612,174 @@ mod tests {
         assert_eq!(w, 2);
     }
 
+    /// テスト用の ThumbnailGenerator（200x200、寸法の決め方と拡大の有無を指定）
+    fn create_test_generator_with_fit(
+        fit: ThumbnailFit,
+        allow_upscale: bool,
+    ) -> (ThumbnailGenerator, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let config = ThumbnailConfig::default()
+            .with_fit(fit)
+            .with_upscale(allow_upscale);
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache")).unwrap();
+        (gen, temp)
+    }
+
+    #[test]
+    fn test_calculate_thumbnail_dimensions_without_upscale() {
+        let (gen, _temp) = create_test_generator_with_fit(ThumbnailFit::Contain, false);
+        assert_eq!(gen.calculate_thumbnail_dimensions(50, 50), (50, 50));
+        assert_eq!(gen.calculate_thumbnail_dimensions(150, 300), (100, 200));
+
+        let (gen, _temp) = create_test_generator_with_fit(ThumbnailFit::Width, false);
+        assert_eq!(gen.calculate_thumbnail_dimensions(120, 900), (120, 900));
+
+        let (gen, _temp) = create_test_generator_with_fit(ThumbnailFit::Cover, false);
+        assert_eq!(gen.calculate_thumbnail_dimensions(50, 300), (50, 200));
+    }
+
+    #[test]
+    fn test_calculate_thumbnail_dimensions_cover_and_width() {
+        let (gen, _temp) = create_test_generator_with_fit(ThumbnailFit::Cover, true);
+        assert_eq!(gen.calculate_thumbnail_dimensions(1920, 1080), (200, 200));
+        assert_eq!(gen.calculate_thumbnail_dimensions(50, 50), (200, 200));
+        // 覆うように開くサイズ（切り抜く前）
+        assert_eq!(gen.cover_decode_size(1920, 1080), (356, 200));
+
+        let (gen, _temp) = create_test_generator_with_fit(ThumbnailFit::Width, true);
+        // 高さは制限しない
+        assert_eq!(gen.calculate_thumbnail_dimensions(1080, 1920), (200, 355));
+    }
+
+    #[test]
+    fn test_generate_cover_thumbnail_crops_center() {
+        let (gen, temp) = create_test_generator_with_fit(ThumbnailFit::Cover, true);
+        // 中央だけが赤い横長の画像
+        let image_path = temp.path().join("wide.jpg");
+        image::RgbImage::from_fn(1200, 400, |x, _| {
+            if (400..800).contains(&x) {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        })
+        .save(&image_path)
+        .unwrap();
+
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_rgb8();
+        assert_eq!(thumbnail.dimensions(), (200, 200));
+        // 左右が切り抜かれ、中央の赤い部分で埋まる
+        for x in [10, 100, 190] {
+            let pixel = thumbnail.get_pixel(x, 100);
+            assert!(pixel[0] > 200 && pixel[2] < 60, "x={}: {:?}", x, pixel);
+        }
+    }
+
+    #[test]
+    fn test_generate_thumbnail_without_upscale_and_nearest_filter() {
+        let temp = TempTestDir::new_random();
+        let config = ThumbnailConfig::default()
+            .with_upscale(false)
+            .with_filter(ThumbnailFilter::Nearest);
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache")).unwrap();
+
+        let small_path = temp.path().join("small.png");
+        image::RgbImage::new(40, 30).save(&small_path).unwrap();
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(small_path.to_str().unwrap())
+            .unwrap();
+        assert_eq!(image::image_dimensions(&thumbnail_path).unwrap(), (40, 30));
+
+        // 最近傍では市松模様の画素が混ざらない
+        let large_path = temp.path().join("checker.png");
+        image::RgbImage::from_fn(400, 400, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        })
+        .save(&large_path)
+        .unwrap();
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(large_path.to_str().unwrap())
+            .unwrap();
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_luma8();
+        let value = thumbnail.get_pixel(100, 100)[0];
+        assert!(!(60..=195).contains(&value), "got {}", value);
+    }
+
     // --- get_or_create_thumbnail エラーテスト ---
 
+    #[test]
+    fn test_image_too_large_error() {
+        let temp = TempTestDir::new_random();
+        let config = ThumbnailConfig {
+            max_decode_pixels: 1000,
+            ..ThumbnailConfig::default()
+        };
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache")).unwrap();
+
+        let large = temp.path().join("large.png");
+        image::RgbImage::new(50, 40).save(&large).unwrap();
+        let small = temp.path().join("small.png");
+        image::RgbImage::new(30, 30).save(&small).unwrap();
+
+        match gen.get_or_create_thumbnail(large.to_str().unwrap()) {
+            Err(ThumbnailError::ImageTooLarge { width, height, .. }) => {
+                assert_eq!((width, height), (50, 40));
+            }
+            other => panic!("Expected ImageTooLarge, got: {:?}", other),
+        }
+        assert!(gen.get_or_create_thumbnail(small.to_str().unwrap()).is_ok());
+
+        // モザイクでは上限を超える画像を読み飛ばす
+        let images = [large, small].map(|path| path.to_string_lossy().to_string());
+        assert!(gen.get_or_create_mosaic_thumbnail(&images).is_ok());
+        assert!(gen.get_or_create_mosaic_thumbnail(&images[..1]).is_err());
+
+        // メモリ量の上限でも同じエラーになる
+        let config = ThumbnailConfig {
+            max_decode_bytes: 30 * 30 * 4 - 1,
+            ..ThumbnailConfig::default()
+        };
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache2")).unwrap();
+        assert!(matches!(
+            gen.get_or_create_thumbnail(&images[1]),
+            Err(ThumbnailError::ImageTooLarge { .. })
+        ));
+
+        // 16bit の画像は見積もり（8bit 換算）を超えるため、デコーダーの上限で同じエラーになる
+        let deep = temp.path().join("deep.png");
+        image::DynamicImage::new_rgba16(30, 30).save(&deep).unwrap();
+        let config = ThumbnailConfig {
+            max_decode_bytes: 30 * 30 * 4,
+            ..ThumbnailConfig::default()
+        };
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache3")).unwrap();
+        assert!(matches!(
+            gen.get_or_create_thumbnail(deep.to_str().unwrap()),
+            Err(ThumbnailError::ImageTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_uses_configured_limits() {
+        let (gen, temp) = create_test_generator();
+        // 約 560MiB の画像は image クレートの既定の上限（512MiB）を超えるが、設定の上限（1GB）には収まる
+        let large = temp.path().join("large.bmp");
+        crate::decode::write_bmp_header(&large, 14000, 14000);
+
+        // 上限では拒否されず、画素データがないためデコードのエラーになる
+        assert!(matches!(
+            gen.get_or_create_thumbnail(large.to_str().unwrap()),
+            Err(ThumbnailError::DecodeError(_))
+        ));
+    }
+
     #[test]
     fn test_image_not_found_error() {
         let (gen, _temp) = create_test_generator();
@@ -249,6 +793,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unsupported_format_error() {
+        let (gen, temp) = create_test_generator();
+        let archive_path = temp.path().join("book.zip");
+        std::fs::write(&archive_path, b"not an image").unwrap();
+
+        let result = gen.get_or_create_thumbnail(archive_path.to_str().unwrap());
+        assert!(matches!(result, Err(ThumbnailError::UnsupportedFormat(_))));
+    }
+
     // --- generate_thumbnail 正常系テスト ---
 
     #[test]
@@ -297,4 +851,329 @@ mod tests {
             .unwrap();
         assert_eq!(path1, path2, "Same image should return same cache path");
     }
+
+    #[test]
+    fn test_generate_thumbnail_for_legacy_formats() {
+        let temp = TempTestDir::new_random();
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        for name in ["scan.bmp", "scan.tiff", "texture.tga", "gray.pgm"] {
+            let image_path = temp.path().join(name);
+            image::RgbImage::new(30, 20).save(&image_path).unwrap();
+
+            let result = gen.get_or_create_thumbnail(image_path.to_str().unwrap());
+            assert!(result.is_ok(), "{}: {:?}", name, result);
+        }
+    }
+
+    #[test]
+    fn test_generate_thumbnail_for_raw_preview() {
+        let temp = TempTestDir::new_random();
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+        let image_path = temp.path().join("photo.nef");
+        crate::decode::create_raw_fixture(&image_path, true, (300, 200));
+
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        let (width, height) = image::image_dimensions(&thumbnail_path).unwrap();
+        let config = ThumbnailConfig::default();
+        assert!(width <= config.width && height <= config.height);
+        assert!(width > 16, "Should use the larger preview, got {}", width);
+    }
+
+    #[test]
+    fn test_generate_thumbnail_for_svg() {
+        let (gen, temp) = create_test_generator_with_config(120, 120);
+        let image_path = temp.path().join("icon.svg");
+        std::fs::write(
+            &image_path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="12" height="6">
+                <rect width="12" height="6" fill="blue"/>
+            </svg>"#,
+        )
+        .unwrap();
+
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        // 小さい SVG もサムネイルサイズで描画される
+        assert_eq!(image::image_dimensions(&thumbnail_path).unwrap(), (120, 60));
+    }
+
+    #[test]
+    fn test_generate_thumbnail_converts_icc_profile_to_srgb() {
+        use image::codecs::png::PngEncoder;
+        use image::{ExtendedColorType, ImageEncoder};
+
+        let (gen, temp) = create_test_generator_with_config(16, 16);
+        let image_path = temp.path().join("linear.png");
+        let mut encoder = PngEncoder::new(std::fs::File::create(&image_path).unwrap());
+        encoder
+            .set_icc_profile(crate::decode::linear_rgb_profile())
+            .unwrap();
+        encoder
+            .write_image(&[50; 32 * 32 * 3], 32, 32, ExtendedColorType::Rgb8)
+            .unwrap();
+
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_rgb8();
+        // 線形の値が sRGB に変換され、中間調が明るくなる
+        let value = thumbnail.get_pixel(8, 8).0[0];
+        assert!((110..=135).contains(&value), "got {}", value);
+    }
+
+    #[test]
+    fn test_generate_thumbnail_applies_exif_orientation() {
+        let (gen, temp) = create_test_generator_with_config(120, 120);
+        let image_path = temp.path().join("portrait.jpg");
+        crate::decode::create_oriented_jpeg(&image_path, (200, 100), 8);
+
+        let thumbnail_path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        // 縦長に回転してからリサイズされる
+        assert_eq!(image::image_dimensions(&thumbnail_path).unwrap(), (60, 120));
+    }
+
+    /// 左半分が透明、右半分が不透明な赤の PNG を作成してパスを返す
+    fn create_half_transparent_png(temp: &TempTestDir) -> String {
+        let path = temp.path().join("transparent.png");
+        image::RgbaImage::from_fn(32, 32, |x, _| {
+            if x < 16 {
+                Rgba([0, 0, 0, 0])
+            } else {
+                Rgba([255, 0, 0, 255])
+            }
+        })
+        .save(&path)
+        .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn create_generator_with_background(
+        background: ThumbnailBackground,
+    ) -> (ThumbnailGenerator, TempTestDir) {
+        let temp = TempTestDir::new_random();
+        let config =
+            ThumbnailConfig::new(32, 32, 80, 1024 * 1024 * 1024).with_background(background);
+        let gen = ThumbnailGenerator::new(config, temp.path().join("cache")).unwrap();
+        (gen, temp)
+    }
+
+    #[test]
+    fn test_transparent_image_uses_matte_color() {
+        let (gen, temp) = create_generator_with_background(ThumbnailBackground::Matte([255; 3]));
+        let image_path = create_half_transparent_png(&temp);
+
+        let thumbnail_path = gen.get_or_create_thumbnail(&image_path).unwrap();
+        assert_eq!(thumbnail_path.extension().unwrap(), "jpg");
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_rgb8();
+        // 透明部分は黒ではなく指定した色になる
+        let transparent = thumbnail.get_pixel(4, 4);
+        assert!(
+            transparent.0.iter().all(|&c| c > 240),
+            "got {:?}",
+            transparent
+        );
+        let opaque = thumbnail.get_pixel(28, 4);
+        assert!(opaque[0] > 200 && opaque[1] < 60, "got {:?}", opaque);
+    }
+
+    #[test]
+    fn test_transparent_image_uses_checkerboard() {
+        let (gen, temp) = create_generator_with_background(ThumbnailBackground::Checkerboard);
+        let image_path = create_half_transparent_png(&temp);
+
+        let thumbnail_path = gen.get_or_create_thumbnail(&image_path).unwrap();
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_rgb8();
+        // 隣り合うマスは異なる色になる
+        let light = thumbnail.get_pixel(3, 3)[0];
+        let dark = thumbnail.get_pixel(11, 3)[0];
+        assert!(
+            light > 240 && (190..=220).contains(&dark),
+            "got {} {}",
+            light,
+            dark
+        );
+    }
+
+    #[test]
+    fn test_transparent_background_keeps_alpha_as_png() {
+        let (gen, temp) = create_generator_with_background(ThumbnailBackground::Transparent);
+        let image_path = create_half_transparent_png(&temp);
+
+        let thumbnail_path = gen.get_or_create_thumbnail(&image_path).unwrap();
+        assert_eq!(thumbnail_path.extension().unwrap(), "png");
+        let thumbnail = image::open(&thumbnail_path).unwrap().to_rgba8();
+        assert_eq!(thumbnail.get_pixel(4, 4)[3], 0);
+        assert_eq!(thumbnail.get_pixel(28, 4)[3], 255);
+
+        // モザイクは不透明な背景と合成して PNG で保存する
+        let mosaic_path = gen.get_or_create_mosaic_thumbnail(&[image_path]).unwrap();
+        assert_eq!(mosaic_path.extension().unwrap(), "png");
+        let mosaic = image::open(&mosaic_path).unwrap().to_rgba8();
+        assert_eq!(mosaic.get_pixel(4, 4).0, [32, 32, 32, 255]);
+    }
+
+    #[test]
+    fn test_animated_gif_thumbnail_uses_first_frame() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let temp = TempTestDir::new_random();
+        let image_path = temp.path().join("anim.gif");
+        let mut encoder = GifEncoder::new(std::fs::File::create(&image_path).unwrap());
+        for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+            let frame = Frame::from_parts(
+                RgbaImage::from_pixel(40, 40, Rgba(color)),
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            );
+            encoder.encode_frame(frame).unwrap();
+        }
+        drop(encoder);
+
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+        let path = gen
+            .get_or_create_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        let thumbnail = image::open(path).unwrap().to_rgb8();
+        let pixel = thumbnail.get_pixel(100, 100);
+        assert!(pixel[0] > 200 && pixel[2] < 60, "got: {:?}", pixel);
+    }
+
+    // --- キャッシュパス テスト ---
+
+    #[test]
+    fn test_cache_path_changes_with_output_config() {
+        let temp = TempTestDir::new_random();
+        let cache_dir = temp.path().join("cache");
+        let image_path = temp.path().join("page.png");
+        image::RgbImage::from_pixel(400, 200, Rgb([0, 128, 255]))
+            .save(&image_path)
+            .unwrap();
+        let image_path = image_path.to_str().unwrap();
+
+        let contain = ThumbnailGenerator::with_default_config(cache_dir.clone()).unwrap();
+        let cover = ThumbnailGenerator::new(
+            ThumbnailConfig {
+                fit: ThumbnailFit::Cover,
+                ..ThumbnailConfig::default()
+            },
+            cache_dir.clone(),
+        )
+        .unwrap();
+        let same = ThumbnailGenerator::with_default_config(cache_dir).unwrap();
+
+        assert_ne!(
+            contain.get_thumbnail_cache_path(image_path),
+            cover.get_thumbnail_cache_path(image_path)
+        );
+        assert_eq!(
+            contain.get_thumbnail_cache_path(image_path),
+            same.get_thumbnail_cache_path(image_path)
+        );
+        let images = [image_path.to_string()];
+        assert_ne!(
+            contain.get_mosaic_cache_path(&images),
+            cover.get_mosaic_cache_path(&images)
+        );
+
+        // 設定を変えると、古い設定のサムネイルではなく新しく生成したものを返す
+        let contained = contain.get_or_create_thumbnail(image_path).unwrap();
+        let covered = cover.get_or_create_thumbnail(image_path).unwrap();
+        assert_ne!(contained, covered);
+        assert_ne!(
+            image::image_dimensions(&contained).unwrap(),
+            image::image_dimensions(&covered).unwrap()
+        );
+    }
+
+    // --- モザイクサムネイル テスト ---
+
+    /// 単色の画像を作成してパスを返す
+    fn create_solid_image(temp: &TempTestDir, name: &str, color: [u8; 3]) -> String {
+        let path = temp.path().join(name);
+        image::RgbImage::from_pixel(50, 50, Rgb(color))
+            .save(&path)
+            .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_mosaic_thumbnail_grid_layout() {
+        let temp = TempTestDir::new_random();
+        let images = vec![
+            create_solid_image(&temp, "1.png", [255, 0, 0]),
+            create_solid_image(&temp, "2.png", [0, 255, 0]),
+            create_solid_image(&temp, "3.png", [0, 0, 255]),
+            create_solid_image(&temp, "4.png", [255, 255, 255]),
+        ];
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let path = gen.get_or_create_mosaic_thumbnail(&images).unwrap();
+        let mosaic = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(mosaic.dimensions(), (200, 200));
+
+        // 左上は赤、右上は緑、左下は青
+        let top_left = mosaic.get_pixel(20, 20);
+        assert!(top_left[0] > 200 && top_left[1] < 60);
+        let top_right = mosaic.get_pixel(180, 20);
+        assert!(top_right[1] > 200 && top_right[0] < 60);
+        let bottom_left = mosaic.get_pixel(20, 180);
+        assert!(bottom_left[2] > 200 && bottom_left[0] < 60);
+        // 右下はバッジ領域として暗くなる
+        let badge = mosaic.get_pixel(190, 190);
+        assert!(badge[0] < 160, "Badge area should be darkened: {:?}", badge);
+    }
+
+    #[test]
+    fn test_mosaic_thumbnail_uses_own_cache_key() {
+        let temp = TempTestDir::new_random();
+        let images = vec![
+            create_solid_image(&temp, "1.png", [255, 0, 0]),
+            create_solid_image(&temp, "2.png", [0, 255, 0]),
+        ];
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        let single = gen.get_or_create_thumbnail(&images[0]).unwrap();
+        let mosaic = gen.get_or_create_mosaic_thumbnail(&images).unwrap();
+        assert_ne!(single, mosaic);
+        assert_eq!(gen.get_or_create_mosaic_thumbnail(&images).unwrap(), mosaic);
+
+        let other = gen.get_or_create_mosaic_thumbnail(&images[..1]).unwrap();
+        assert_ne!(other, mosaic);
+    }
+
+    #[test]
+    fn test_mosaic_thumbnail_skips_undecodable_images() {
+        let temp = TempTestDir::new_random();
+        let broken = temp.path().join("broken.png");
+        std::fs::write(&broken, b"not an image").unwrap();
+        let images = vec![
+            broken.to_string_lossy().to_string(),
+            create_solid_image(&temp, "1.png", [255, 0, 0]),
+        ];
+        let gen = ThumbnailGenerator::with_default_config(temp.path().join("cache")).unwrap();
+
+        assert!(gen.get_or_create_mosaic_thumbnail(&images).is_ok());
+        assert!(gen.get_or_create_mosaic_thumbnail(&images[..1]).is_err());
+    }
+
+    #[test]
+    fn test_mosaic_thumbnail_errors() {
+        let (gen, _temp) = create_test_generator();
+        assert!(matches!(
+            gen.get_or_create_mosaic_thumbnail(&[]),
+            Err(ThumbnailError::GenerationError(_))
+        ));
+        assert!(matches!(
+            gen.get_or_create_mosaic_thumbnail(&["/nonexistent/1.jpg".to_string()]),
+            Err(ThumbnailError::ImageNotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,242 @@
+// サムネイル生成ジョブの管理（キャンセル・置き換え）
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::thumbnail::batch::BatchResult;
+
+/// ジョブを識別するID
+pub type JobId = u64;
+
+/// ジョブのキャンセル状態を共有するトークン
+///
+/// クローンしたトークンは同じキャンセル状態を共有する。
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// 新しいトークンを作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// キャンセルを要求
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// キャンセルが要求されているか
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// 登録済みジョブの情報
+struct JobEntry {
+    token: CancellationToken,
+    group: Option<String>,
+}
+
+/// 実行中のサムネイル生成ジョブを管理するレジストリ
+///
+/// グループを指定して開始したジョブは、同じグループの新しいジョブによって置き換え（キャンセル）される。
+/// スクロールで表示範囲が変わった際に、古いプリフェッチを止めるために使用する。
+#[derive(Default)]
+pub struct ThumbnailJobRegistry {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<JobId, JobEntry>>,
+}
+
+impl ThumbnailJobRegistry {
+    /// 新しいレジストリを作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新しいジョブを登録
+    ///
+    /// # Arguments
+    /// * `group` - ジョブのグループ。同じグループで実行中のジョブはキャンセルされる
+    ///
+    /// # Returns
+    /// (ジョブID, キャンセルトークン)
+    pub fn start(&self, group: Option<&str>) -> (JobId, CancellationToken) {
+        let job_id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let token = CancellationToken::new();
+
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(group) = group {
+            // 同じグループの古いジョブを置き換える
+            jobs.retain(|_, entry| {
+                if entry.group.as_deref() == Some(group) {
+                    entry.token.cancel();
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        jobs.insert(
+            job_id,
+            JobEntry {
+                token: token.clone(),
+                group: group.map(|g| g.to_string()),
+            },
+        );
+
+        (job_id, token)
+    }
+
+    /// 指定したジョブをキャンセル
+    ///
+    /// # Returns
+    /// 実行中のジョブが見つかった場合は true
+    pub fn cancel(&self, job_id: JobId) -> bool {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        match jobs.remove(&job_id) {
+            Some(entry) => {
+                entry.token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ジョブの完了を登録し、レジストリから取り除く
+    pub fn finish(&self, job_id: JobId) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.remove(&job_id);
+    }
+
+    /// 指定したジョブが実行中か
+    pub fn is_running(&self, job_id: JobId) -> bool {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.contains_key(&job_id)
+    }
+}
+
+/// サムネイル1件の生成完了を通知するイベント
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailJobProgress {
+    pub job_id: JobId,
+    #[serde(flatten)]
+    pub result: BatchResult,
+}
+
+/// ジョブ全体の終了を通知するイベント
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailJobFinished {
+    pub job_id: JobId,
+    pub cancelled: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl ThumbnailJobFinished {
+    /// 生成結果からジョブ終了イベントを作成
+    pub fn from_results(job_id: JobId, cancelled: bool, results: &[BatchResult]) -> Self {
+        let failed = results.iter().filter(|r| r.error.is_some()).count();
+        Self {
+            job_id,
+            cancelled,
+            succeeded: results.len() - failed,
+            failed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_cancellation_token_shared_between_clones() {
+        let token = CancellationToken::new();
+        let cloned = token.clone();
+        assert!(!cloned.is_cancelled());
+        token.cancel();
+        assert!(cloned.is_cancelled());
+    }
+
+    #[test]
+    fn test_registry_assigns_unique_ids() {
+        let registry = ThumbnailJobRegistry::new();
+        let (id1, _) = registry.start(None);
+        let (id2, _) = registry.start(None);
+        assert_ne!(id1, id2);
+        assert!(registry.is_running(id1));
+        assert!(registry.is_running(id2));
+    }
+
+    #[test]
+    fn test_registry_cancel_by_id() {
+        let registry = ThumbnailJobRegistry::new();
+        let (id, token) = registry.start(None);
+
+        assert!(registry.cancel(id));
+        assert!(token.is_cancelled());
+        assert!(!registry.is_running(id));
+        // 2回目のキャンセルは対象なし
+        assert!(!registry.cancel(id));
+    }
+
+    #[test]
+    fn test_registry_supersedes_job_in_same_group() {
+        let registry = ThumbnailJobRegistry::new();
+        let (old_id, old_token) = registry.start(Some("prefetch"));
+        let (other_id, other_token) = registry.start(Some("other"));
+        let (new_id, new_token) = registry.start(Some("prefetch"));
+
+        assert!(old_token.is_cancelled(), "Old job should be superseded");
+        assert!(!registry.is_running(old_id));
+        assert!(!other_token.is_cancelled(), "Other group should be kept");
+        assert!(registry.is_running(other_id));
+        assert!(!new_token.is_cancelled());
+        assert!(registry.is_running(new_id));
+    }
+
+    #[test]
+    fn test_registry_finish_does_not_cancel() {
+        let registry = ThumbnailJobRegistry::new();
+        let (id, token) = registry.start(None);
+        registry.finish(id);
+        assert!(!registry.is_running(id));
+        assert!(!token.is_cancelled());
+    }
+
+    #[test]
+    fn test_job_progress_serializes_flattened_result() {
+        let progress = ThumbnailJobProgress {
+            job_id: 7,
+            result: BatchResult::success(
+                "/photos/a.jpg".to_string(),
+                PathBuf::from("/cache/abc.jpg"),
+            ),
+        };
+        let json = serde_json::to_value(&progress).unwrap();
+        assert_eq!(json["jobId"], 7);
+        assert_eq!(json["imagePath"], "/photos/a.jpg");
+        assert_eq!(json["thumbnailPath"], "/cache/abc.jpg");
+        assert!(json["error"].is_null());
+    }
+
+    #[test]
+    fn test_job_finished_counts_results() {
+        let results = vec![
+            BatchResult::success("/a.jpg".to_string(), PathBuf::from("/cache/a.jpg")),
+            BatchResult::failure("/b.jpg".to_string(), "broken".to_string()),
+            BatchResult::success("/c.jpg".to_string(), PathBuf::from("/cache/c.jpg")),
+        ];
+        let finished = ThumbnailJobFinished::from_results(1, false, &results);
+        assert_eq!(finished.succeeded, 2);
+        assert_eq!(finished.failed, 1);
+        assert!(!finished.cancelled);
+    }
+}
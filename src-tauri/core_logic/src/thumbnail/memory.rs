@@ -0,0 +1,115 @@
+// 同時に行うデコードのメモリ使用量の制限
+//
+// バッチ生成のワーカースレッドは同じ ThumbnailGenerator を共有するため、
+// ジェネレーターが持つ予算で全ワーカーのデコードをまとめて制限できる。
+// サムネイル・レンディション・タイルのサービスに同じ予算を渡すと、アプリ全体のデコードをまとめて制限できる。
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// デコードに使うメモリの予算
+///
+/// 各ジェネレーター・サービスの `new` は設定の `decode_memory_budget` から専用の予算を作る。
+/// `with_memory_budget` で同じ予算（`Arc<MemoryBudget>`）を渡したインスタンスどうしは、
+/// 設定の `decode_memory_budget` の代わりにこの予算の範囲で同時に行うデコードをまとめて制限する。
+pub struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    /// 上限（バイト）を指定して予算を作成
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// 指定したバイト数を予約できるまで待つ
+    ///
+    /// 予算を超える量は予算いっぱいに切り詰めて予約する（他のデコードが終わるのを待ってから実行する）。
+    /// 予約は返り値が破棄されるときに解放される。
+    pub(crate) fn reserve(&self, bytes: u64) -> MemoryReservation<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut used = self.lock();
+        while *used + bytes > self.limit {
+            used = self.released.wait(used).unwrap_or_else(|e| e.into_inner());
+        }
+        *used += bytes;
+        MemoryReservation {
+            budget: self,
+            bytes,
+        }
+    }
+
+    /// 予約済みのバイト数
+    #[cfg(test)]
+    fn used(&self) -> u64 {
+        *self.lock()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.used.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 予約したメモリ（破棄すると解放される）
+pub(crate) struct MemoryReservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for MemoryReservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.lock() -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reservation_is_released_on_drop() {
+        let budget = MemoryBudget::new(100);
+        let first = budget.reserve(60);
+        let second = budget.reserve(40);
+        assert_eq!(budget.used(), 100);
+        drop(first);
+        drop(second);
+        assert_eq!(budget.used(), 0);
+
+        // 予算を超える量は予算いっぱいに切り詰める
+        let large = budget.reserve(1000);
+        assert_eq!(budget.used(), 100);
+        drop(large);
+    }
+
+    #[test]
+    fn test_reserve_waits_for_release() {
+        let budget = Arc::new(MemoryBudget::new(100));
+        let first = budget.reserve(80);
+
+        let (sender, receiver) = mpsc::channel();
+        let waiting = {
+            let budget = Arc::clone(&budget);
+            std::thread::spawn(move || {
+                let _reservation = budget.reserve(50);
+                sender.send(()).unwrap();
+            })
+        };
+
+        // 予算が空くまで待つ
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(first);
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        waiting.join().unwrap();
+        assert_eq!(budget.used(), 0);
+    }
+}
@@ -0,0 +1,237 @@
+// コンテナ内の各ページ（画像）のサムネイル取得のためのユーティリティ
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::{registry, FormatKind};
+use crate::image_container::pdf::PdfImageContainer;
+use crate::image_container::reader_config::ImageContainerReaderConfig;
+use crate::list_images_in_container;
+use crate::utils::natural_cmp_file_name;
+
+/// 取得するページの範囲（`start` を含み `end` を含まない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl PageRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// ページ数に収まるように範囲を切り詰める
+    pub fn clamp(&self, page_count: usize) -> std::ops::Range<usize> {
+        let end = self.end.min(page_count);
+        self.start.min(end)..end
+    }
+}
+
+/// ページサムネイル取得結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageThumbnailResult {
+    /// コンテナ内のページ番号（0始まり、ファイル名の自然順）
+    pub index: usize,
+    pub image_path: String,
+    pub thumbnail_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// コンテナ内の画像をページ順（ファイル名の自然順）で取得
+/// crate::fs::list_images_in_container を内部で使用
+pub fn list_container_pages<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    container_path: P,
+    cache_dir: Q,
+) -> Result<Vec<String>, String> {
+    let container_path = container_path.as_ref();
+
+    let mut images = list_images_in_container(container_path, cache_dir).map_err(|e| {
+        format!(
+            "Failed to list images in '{}': {:?}",
+            container_path.display(),
+            e
+        )
+    })?;
+    images.sort_by(|a, b| natural_cmp_file_name(a, b));
+
+    Ok(images)
+}
+
+/// コンテナ内のページ
+///
+/// PDF は全ページを展開せず、取得するページだけを1ページずつ書き出す。
+pub enum ContainerPages {
+    /// ページ順に並べた画像のパス
+    Images(Vec<String>),
+    /// PDF と、`list_pages` に含まれるページの PDF 内での番号
+    Pdf {
+        container: PdfImageContainer,
+        page_indexes: Vec<usize>,
+    },
+}
+
+impl ContainerPages {
+    /// コンテナを開いてページを数える
+    pub fn open<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+        container_path: P,
+        cache_dir: Q,
+    ) -> Result<Self, String> {
+        let container_path = container_path.as_ref();
+        let is_pdf = container_path.is_file()
+            && registry()
+                .get(container_path)
+                .is_some_and(|entry| entry.kind == FormatKind::Pdf);
+        if !is_pdf {
+            return list_container_pages(container_path, cache_dir).map(Self::Images);
+        }
+
+        let open_error = |e| {
+            format!(
+                "Failed to list images in '{}': {:?}",
+                container_path.display(),
+                e
+            )
+        };
+        let container =
+            PdfImageContainer::new(container_path, ImageContainerReaderConfig::new(cache_dir))
+                .map_err(open_error)?;
+        let page_indexes = container.page_indexes().map_err(open_error)?;
+        Ok(Self::Pdf {
+            container,
+            page_indexes,
+        })
+    }
+
+    /// ページ数
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Images(images) => images.len(),
+            Self::Pdf { page_indexes, .. } => page_indexes.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 指定したページ（0始まり）の画像のパスを取得する
+    ///
+    /// 範囲外や書き出せなかったページは、そのページのエラーメッセージを返す。
+    pub fn image_paths(&self, indexes: &[usize]) -> Result<Vec<Result<String, String>>, String> {
+        let not_found = |index: usize| format!("Page {} not found", index + 1);
+        match self {
+            Self::Images(images) => Ok(indexes
+                .iter()
+                .map(|&index| images.get(index).cloned().ok_or_else(|| not_found(index)))
+                .collect()),
+            Self::Pdf {
+                container,
+                page_indexes,
+            } => {
+                let pdf_indexes: Vec<usize> = indexes
+                    .iter()
+                    .filter_map(|&index| page_indexes.get(index).copied())
+                    .collect();
+                let mut rendered = container
+                    .render_pages(&pdf_indexes)
+                    .map_err(|e| format!("Failed to render PDF pages: {:?}", e))?
+                    .into_iter();
+                Ok(indexes
+                    .iter()
+                    .map(|&index| {
+                        if index >= page_indexes.len() {
+                            return Err(not_found(index));
+                        }
+                        match rendered.next() {
+                            Some(Ok(Some(path))) => Ok(path.to_string_lossy().to_string()),
+                            Some(Ok(None)) => Err(format!("Page {} has no page image", index + 1)),
+                            Some(Err(e)) => {
+                                Err(format!("Failed to render page {}: {:?}", index + 1, e))
+                            }
+                            None => Err(not_found(index)),
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::TempTestDir;
+    use std::fs::File;
+
+    #[test]
+    fn test_page_range_clamp() {
+        assert_eq!(PageRange::new(0, 10).clamp(5), 0..5);
+        assert_eq!(PageRange::new(2, 4).clamp(5), 2..4);
+        assert_eq!(PageRange::new(7, 10).clamp(5), 5..5);
+        assert_eq!(PageRange::new(4, 2).clamp(5), 2..2);
+    }
+
+    #[test]
+    fn test_page_range_deserializes_from_camel_case() {
+        let range: PageRange = serde_json::from_str(r#"{"start": 1, "end": 3}"#).unwrap();
+        assert_eq!(range, PageRange::new(1, 3));
+    }
+
+    #[test]
+    fn test_list_container_pages_in_natural_order() {
+        let temp = TempTestDir::new_random();
+        for name in ["10.jpg", "2.jpg", "1.jpg"] {
+            File::create(temp.path().join(name)).unwrap();
+        }
+
+        let pages = list_container_pages(temp.path(), temp.path()).unwrap();
+        let names: Vec<_> = pages
+            .iter()
+            .map(|p| {
+                std::path::Path::new(p)
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(names, vec!["1.jpg", "2.jpg", "10.jpg"]);
+    }
+
+    #[test]
+    fn test_pdf_pages_written_on_demand() {
+        use crate::image_container::pdf::test::{create_pdf, TestPage};
+
+        let temp = TempTestDir::new_random();
+        let pdf_path = temp.path().join("book.pdf");
+        create_pdf(
+            &pdf_path,
+            &[
+                TestPage::Jpeg(80, 80),
+                TestPage::Rgb(80, 80),
+                TestPage::Jpeg(80, 80),
+            ],
+        );
+        let cache_dir = temp.path().join("cache");
+
+        let pages = ContainerPages::open(&pdf_path, &cache_dir).unwrap();
+        assert_eq!(pages.len(), 3);
+
+        let paths = pages.image_paths(&[1, 5]).unwrap();
+        let second = paths[0].as_ref().unwrap();
+        assert!(second.ends_with("0002.png"));
+        assert!(paths[1].is_err());
+        // 要求したページだけが書き出される
+        let written = std::fs::read_dir(std::path::Path::new(second).parent().unwrap())
+            .unwrap()
+            .count();
+        assert_eq!(written, 1);
+    }
+
+    #[test]
+    fn test_list_container_pages_error_for_missing_container() {
+        assert!(list_container_pages("/nonexistent/container", "").is_err());
+    }
+}
@@ -0,0 +1,352 @@
+// 優先度付きサムネイル生成タスクキュー
+//
+// 複数の呼び出し元から投入されたタスクを1つのキューにまとめ、
+// 同じキーのタスク（同じ画像など）は1回の生成に統合（coalesce）する。
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::mpsc::Sender;
+
+use crate::thumbnail::batch::{BatchResult, TaskPriority};
+use crate::thumbnail::job::CancellationToken;
+
+/// タスクの生成結果を受け取る呼び出し元
+pub(crate) struct Waiter {
+    token: CancellationToken,
+    container_path: Option<String>,
+    sender: Sender<BatchResult>,
+}
+
+impl Waiter {
+    pub(crate) fn new(
+        token: CancellationToken,
+        container_path: Option<String>,
+        sender: Sender<BatchResult>,
+    ) -> Self {
+        Self {
+            token,
+            container_path,
+            sender,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// 生成結果を呼び出し元に通知する（キャンセル済みの場合は通知しない）
+    pub(crate) fn deliver(&self, result: &BatchResult) {
+        if self.is_cancelled() {
+            return;
+        }
+        // 受信側が既に待機を終えている場合は送信に失敗するが、無視してよい
+        let _ = self
+            .sender
+            .send(result.clone().with_container(self.container_path.clone()));
+    }
+}
+
+/// ヒープ上のエントリ
+///
+/// 優先度を引き上げた場合は新しいエントリを積み、古いエントリは取り出し時に読み飛ばす。
+#[derive(Debug, PartialEq, Eq)]
+struct HeapEntry {
+    priority: TaskPriority,
+    seq: u64,
+    image_path: String,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 優先度が高い順、同じ優先度なら投入順（seqが小さい順）
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 待機中のタスク
+struct PendingTask<T> {
+    priority: TaskPriority,
+    payload: T,
+    waiters: Vec<Waiter>,
+}
+
+/// タスク投入の結果
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PushOutcome {
+    /// 新しいタスクとしてキューに追加された
+    Queued,
+    /// 待機中の同じ画像のタスクに統合された
+    Coalesced,
+    /// 生成中の同じ画像のタスクに統合された
+    AttachedToInFlight,
+}
+
+/// 優先度付きタスクキュー
+///
+/// タスクはキー（画像パスなど）で識別し、生成に必要な情報を `T` として保持する。
+pub(crate) struct TaskQueue<T> {
+    heap: BinaryHeap<HeapEntry>,
+    pending: HashMap<String, PendingTask<T>>,
+    in_flight: HashMap<String, Vec<Waiter>>,
+    next_seq: u64,
+}
+
+impl<T> TaskQueue<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            pending: HashMap::new(),
+            in_flight: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// タスクを投入する
+    ///
+    /// 同じキーのタスクが待機中・生成中の場合は、そのタスクに統合する（`payload` は破棄される）。
+    /// 待機中のタスクより高い優先度で投入された場合は、優先度を引き上げる。
+    pub(crate) fn push(
+        &mut self,
+        image_path: &str,
+        priority: TaskPriority,
+        payload: T,
+        waiter: Waiter,
+    ) -> PushOutcome {
+        if let Some(waiters) = self.in_flight.get_mut(image_path) {
+            waiters.push(waiter);
+            return PushOutcome::AttachedToInFlight;
+        }
+
+        if let Some(pending) = self.pending.get_mut(image_path) {
+            pending.waiters.push(waiter);
+            self.bump(image_path, priority);
+            return PushOutcome::Coalesced;
+        }
+
+        self.pending.insert(
+            image_path.to_string(),
+            PendingTask {
+                priority,
+                payload,
+                waiters: vec![waiter],
+            },
+        );
+        self.push_entry(image_path, priority);
+        PushOutcome::Queued
+    }
+
+    /// 待機中のタスクの優先度を引き上げる
+    ///
+    /// # Returns
+    /// 優先度が引き上げられた場合は true（待機中でない、または既に同等以上の優先度の場合は false）
+    pub(crate) fn bump(&mut self, image_path: &str, priority: TaskPriority) -> bool {
+        match self.pending.get_mut(image_path) {
+            Some(pending) if pending.priority < priority => {
+                pending.priority = priority;
+                self.push_entry(image_path, priority);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 最も優先度の高いタスクを取り出し、生成中として登録する
+    ///
+    /// 全ての呼び出し元がキャンセル済みのタスクは生成せずに破棄する。
+    pub(crate) fn pop(&mut self) -> Option<(String, T)> {
+        while let Some(entry) = self.heap.pop() {
+            // 優先度の引き上げで古くなったエントリは読み飛ばす
+            let is_current = self
+                .pending
+                .get(&entry.image_path)
+                .is_some_and(|pending| pending.priority == entry.priority);
+            if !is_current {
+                continue;
+            }
+
+            let Some(pending) = self.pending.remove(&entry.image_path) else {
+                continue;
+            };
+            let waiters: Vec<Waiter> = pending
+                .waiters
+                .into_iter()
+                .filter(|waiter| !waiter.is_cancelled())
+                .collect();
+            if waiters.is_empty() {
+                continue;
+            }
+
+            self.in_flight.insert(entry.image_path.clone(), waiters);
+            return Some((entry.image_path, pending.payload));
+        }
+        None
+    }
+
+    /// 生成が完了したタスクを取り除き、結果を通知すべき呼び出し元を返す
+    pub(crate) fn complete(&mut self, image_path: &str) -> Vec<Waiter> {
+        self.in_flight.remove(image_path).unwrap_or_default()
+    }
+
+    /// 待機中のタスク数
+    pub(crate) fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn push_entry(&mut self, image_path: &str, priority: TaskPriority) {
+        self.next_seq += 1;
+        self.heap.push(HeapEntry {
+            priority,
+            seq: self.next_seq,
+            image_path: image_path.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+
+    fn waiter() -> (Waiter, Receiver<BatchResult>) {
+        let (sender, receiver) = channel();
+        (
+            Waiter::new(CancellationToken::new(), None, sender),
+            receiver,
+        )
+    }
+
+    fn push(queue: &mut TaskQueue<()>, path: &str, priority: TaskPriority) -> PushOutcome {
+        let (w, _) = waiter();
+        queue.push(path, priority, (), w)
+    }
+
+    fn pop(queue: &mut TaskQueue<()>) -> Option<String> {
+        queue.pop().map(|(key, _)| key)
+    }
+
+    #[test]
+    fn test_pop_returns_highest_priority_first() {
+        let mut queue = TaskQueue::<()>::new();
+        push(&mut queue, "low.jpg", TaskPriority::Low);
+        push(&mut queue, "high.jpg", TaskPriority::High);
+        push(&mut queue, "normal.jpg", TaskPriority::Normal);
+
+        assert_eq!(pop(&mut queue).as_deref(), Some("high.jpg"));
+        assert_eq!(pop(&mut queue).as_deref(), Some("normal.jpg"));
+        assert_eq!(pop(&mut queue).as_deref(), Some("low.jpg"));
+        assert_eq!(pop(&mut queue), None);
+    }
+
+    #[test]
+    fn test_pop_is_fifo_within_same_priority() {
+        let mut queue = TaskQueue::<()>::new();
+        push(&mut queue, "1.jpg", TaskPriority::Normal);
+        push(&mut queue, "2.jpg", TaskPriority::Normal);
+        push(&mut queue, "3.jpg", TaskPriority::Normal);
+
+        assert_eq!(pop(&mut queue).as_deref(), Some("1.jpg"));
+        assert_eq!(pop(&mut queue).as_deref(), Some("2.jpg"));
+        assert_eq!(pop(&mut queue).as_deref(), Some("3.jpg"));
+    }
+
+    #[test]
+    fn test_bump_moves_task_ahead() {
+        let mut queue = TaskQueue::<()>::new();
+        push(&mut queue, "a.jpg", TaskPriority::Normal);
+        push(&mut queue, "b.jpg", TaskPriority::Low);
+
+        assert!(queue.bump("b.jpg", TaskPriority::High));
+        // 既に同等以上の優先度なら引き上げない
+        assert!(!queue.bump("b.jpg", TaskPriority::Normal));
+        // 待機中でないタスクは引き上げられない
+        assert!(!queue.bump("missing.jpg", TaskPriority::High));
+
+        assert_eq!(pop(&mut queue).as_deref(), Some("b.jpg"));
+        assert_eq!(pop(&mut queue).as_deref(), Some("a.jpg"));
+        assert_eq!(pop(&mut queue), None, "Stale entry should be skipped");
+    }
+
+    #[test]
+    fn test_duplicate_tasks_are_coalesced() {
+        let mut queue = TaskQueue::<()>::new();
+        let (w1, r1) = waiter();
+        let (w2, r2) = waiter();
+        assert_eq!(
+            queue.push("a.jpg", TaskPriority::Low, (), w1),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push("a.jpg", TaskPriority::High, (), w2),
+            PushOutcome::Coalesced
+        );
+        assert_eq!(queue.pending_count(), 1);
+
+        assert_eq!(pop(&mut queue).as_deref(), Some("a.jpg"));
+        assert_eq!(pop(&mut queue), None);
+
+        let result = BatchResult::failure("a.jpg".to_string(), "error".to_string());
+        let waiters = queue.complete("a.jpg");
+        assert_eq!(waiters.len(), 2);
+        waiters.iter().for_each(|w| w.deliver(&result));
+        assert!(r1.try_recv().is_ok());
+        assert!(r2.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_task_attaches_to_in_flight() {
+        let mut queue = TaskQueue::<()>::new();
+        push(&mut queue, "a.jpg", TaskPriority::Normal);
+        assert_eq!(pop(&mut queue).as_deref(), Some("a.jpg"));
+
+        assert_eq!(
+            push(&mut queue, "a.jpg", TaskPriority::High),
+            PushOutcome::AttachedToInFlight
+        );
+        assert_eq!(queue.pending_count(), 0);
+        assert_eq!(queue.complete("a.jpg").len(), 2);
+        assert!(queue.complete("a.jpg").is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_tasks_are_skipped() {
+        let mut queue = TaskQueue::<()>::new();
+        let (sender, _receiver) = channel();
+        let token = CancellationToken::new();
+        queue.push(
+            "cancelled.jpg",
+            TaskPriority::High,
+            (),
+            Waiter::new(token.clone(), None, sender),
+        );
+        push(&mut queue, "alive.jpg", TaskPriority::Low);
+
+        token.cancel();
+
+        assert_eq!(pop(&mut queue).as_deref(), Some("alive.jpg"));
+        assert_eq!(pop(&mut queue), None);
+    }
+
+    #[test]
+    fn test_deliver_sets_container_and_respects_cancel() {
+        let (sender, receiver) = channel();
+        let token = CancellationToken::new();
+        let w = Waiter::new(token.clone(), Some("/photos/a".to_string()), sender);
+        let result = BatchResult::failure("/photos/a/1.jpg".to_string(), "error".to_string());
+
+        w.deliver(&result);
+        let delivered = receiver.try_recv().unwrap();
+        assert_eq!(delivered.container_path.as_deref(), Some("/photos/a"));
+
+        token.cancel();
+        w.deliver(&result);
+        assert!(receiver.try_recv().is_err());
+    }
+}
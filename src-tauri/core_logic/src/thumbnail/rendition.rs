@@ -0,0 +1,411 @@
+// 表示用の縮小画像（ディスプレイレンディション）の生成
+//
+// ビューアでページをめくるたびに原寸の画像をデコードすると高解像度のスキャン画像では重いため、
+// 表示領域に収まる大きさに縮小した画像を生成してサムネイルとは別のディレクトリにキャッシュする。
+// ウィンドウのリサイズのたびに作り直さないよう、表示領域の大きさは RENDITION_STEP 単位に切り上げる。
+// キャッシュは設定の `max_rendition_cache_size` を超えないよう、生成のたびに古いものから削除する。
+
+use crate::decode::open_image_to_fit_with_limits;
+use crate::thumbnail::config::ThumbnailConfig;
+use crate::thumbnail::error::{Result, ThumbnailError};
+use crate::thumbnail::generator::{estimate_decode, filter_type, is_cache_fresh, open_error};
+use crate::thumbnail::memory::MemoryBudget;
+use crate::utils::{hash_path, prune_cache_dir};
+use image::{GenericImageView, ImageFormat};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 表示領域の大きさを切り上げる単位（ピクセル）
+const RENDITION_STEP: u32 = 256;
+
+/// 表示領域の大きさの上限（ピクセル）
+const MAX_VIEWPORT_SIZE: u32 = 8192;
+
+/// レンディションの保存形式（透過画像は PNG、それ以外は JPEG）
+const RENDITION_FORMATS: [ImageFormat; 2] = [ImageFormat::Jpeg, ImageFormat::Png];
+
+/// 画像を収める表示領域の大きさ（デバイスピクセル）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// キャッシュに使う大きさ（`RENDITION_STEP` 単位に切り上げ、上限で切り詰める）
+    pub fn bucketed(&self) -> Self {
+        let bucket = |size: u32| {
+            size.clamp(1, MAX_VIEWPORT_SIZE)
+                .div_ceil(RENDITION_STEP)
+                .saturating_mul(RENDITION_STEP)
+        };
+        Self::new(bucket(self.width), bucket(self.height))
+    }
+}
+
+/// 表示用の縮小画像の生成と管理
+///
+/// 拡大はしないため、表示領域より小さい画像は原寸のまま（表示できる形式に変換して）保存する。
+pub struct RenditionGenerator {
+    config: ThumbnailConfig,
+    cache_dir: PathBuf,
+    memory: Arc<MemoryBudget>,
+    /// 画像ごとのロック（プリフェッチと表示の要求で同じ画像を同時に生成しない）
+    locks: Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl RenditionGenerator {
+    /// 新しいRenditionGeneratorを作成
+    ///
+    /// デコードの上限・メモリ予算とリサイズのフィルターは `config` に従う。
+    pub fn new(config: ThumbnailConfig, cache_dir: PathBuf) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, cache_dir, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してRenditionGeneratorを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        cache_dir: PathBuf,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
+        config.validate_decode_limits()?;
+        Ok(Self {
+            config,
+            cache_dir,
+            memory,
+            locks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// デフォルト設定でRenditionGeneratorを作成
+    pub fn with_default_config(cache_dir: PathBuf) -> Result<Self> {
+        Self::new(ThumbnailConfig::default(), cache_dir)
+    }
+
+    /// 表示領域に収まるレンディションを生成または取得
+    ///
+    /// # Arguments
+    /// * `image_path` - ソース画像のパス
+    /// * `viewport` - 表示領域の大きさ
+    ///
+    /// # Returns
+    /// レンディションのキャッシュパス
+    pub fn get_or_create_rendition(&self, image_path: &str, viewport: Viewport) -> Result<PathBuf> {
+        let source_path = Path::new(image_path);
+        if !source_path.exists() {
+            return Err(ThumbnailError::ImageNotFound(image_path.to_string()));
+        }
+        if !crate::format::can_decode(source_path) {
+            return Err(ThumbnailError::UnsupportedFormat(image_path.to_string()));
+        }
+
+        let viewport = viewport.bucketed();
+        let base_path = self.get_rendition_base_path(image_path, viewport);
+        let lock = self.lock_for(&base_path);
+        let result = {
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            match cached_rendition(&base_path, image_path) {
+                Some(cache_path) => Ok(cache_path),
+                None => self.generate_rendition(image_path, viewport, &base_path),
+            }
+        };
+        self.release_lock(&base_path, lock);
+        result
+    }
+
+    /// 拡張子を除いたキャッシュパスを計算（表示領域の大きさごとに別のファイルにする）
+    fn get_rendition_base_path(&self, image_path: &str, viewport: Viewport) -> PathBuf {
+        let hash = hash_path(&image_path);
+        self.cache_dir
+            .join(format!("{}-{}x{}", hash, viewport.width, viewport.height))
+    }
+
+    fn lock_for(&self, base_path: &Path) -> Arc<Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(locks.entry(base_path.to_path_buf()).or_default())
+    }
+
+    /// ロックを返し、他に使っているスレッドがなければ一覧から取り除く
+    fn release_lock(&self, base_path: &Path, lock: Arc<Mutex<()>>) {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        drop(lock);
+        // ロックの複製は一覧のロック中にだけ作られるため、ここで数えた参照数は増えない
+        if locks
+            .get(base_path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(base_path);
+        }
+    }
+
+    /// レンディションを生成してキャッシュに保存
+    fn generate_rendition(
+        &self,
+        image_path: &str,
+        viewport: Viewport,
+        base_path: &Path,
+    ) -> Result<PathBuf> {
+        let (info, decode_bytes) =
+            estimate_decode(&self.config, image_path, (viewport.width, viewport.height))?;
+        let img = {
+            let _reservation = self.memory.reserve(decode_bytes);
+            open_image_to_fit_with_limits(
+                image_path,
+                viewport.width,
+                viewport.height,
+                &self.config.decode_limits(),
+            )
+            .map_err(|e| open_error(image_path, &info, e))?
+        };
+
+        let (width, height) = img.dimensions();
+        let img = if width > viewport.width || height > viewport.height {
+            img.resize(
+                viewport.width,
+                viewport.height,
+                filter_type(self.config.filter),
+            )
+        } else {
+            img
+        };
+
+        let format = if img.color().has_alpha() {
+            ImageFormat::Png
+        } else {
+            ImageFormat::Jpeg
+        };
+        let img = match format {
+            ImageFormat::Jpeg => image::DynamicImage::ImageRgb8(img.into_rgb8()),
+            _ => img,
+        };
+
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let output_path = base_path.with_extension(format.extensions_str()[0]);
+        save_rendition(&img, &output_path, format)?;
+        self.prune_cache();
+        Ok(output_path)
+    }
+
+    /// キャッシュの合計サイズを `max_rendition_cache_size` 以下に保つ
+    ///
+    /// 生成中（ロック中）のレンディションと書き込み中の一時ファイルは削除しない。
+    fn prune_cache(&self) {
+        let locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        let result = prune_cache_dir(
+            &self.cache_dir,
+            self.config.max_rendition_cache_size,
+            |path| {
+                path.extension().is_some_and(|ext| ext == "tmp")
+                    || locks.contains_key(&path.with_extension(""))
+            },
+        );
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to prune rendition cache {}: {}",
+                self.cache_dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// 一時ファイルに保存してから置き換える（書き込み途中のファイルをキャッシュとして返さない）
+///
+/// 同じレンディションの生成はロックで直列化されるため、一時ファイルの名前は固定でよい。
+fn save_rendition(
+    img: &image::DynamicImage,
+    output_path: &Path,
+    format: ImageFormat,
+) -> Result<()> {
+    let temp_path = output_path.with_extension(format!("{}.tmp", format.extensions_str()[0]));
+    let result = img
+        .save_with_format(&temp_path, format)
+        .map_err(|e| {
+            ThumbnailError::GenerationError(format!(
+                "Failed to save rendition to {:?}: {}",
+                output_path, e
+            ))
+        })
+        .and_then(|()| Ok(std::fs::rename(&temp_path, output_path)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// ソースより新しいレンディションがあればそのパスを返す
+fn cached_rendition(base_path: &Path, image_path: &str) -> Option<PathBuf> {
+    RENDITION_FORMATS
+        .iter()
+        .map(|format| base_path.with_extension(format.extensions_str()[0]))
+        .find(|path| is_cache_fresh(path, &[image_path]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::{create_test_rendition_generator, TempTestDir};
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn create_test_image(temp: &TempTestDir, name: &str, (width, height): (u32, u32)) -> String {
+        let path = temp.path().join(name);
+        RgbImage::from_pixel(width, height, Rgb([255, 0, 0]))
+            .save(&path)
+            .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_viewport_bucketed() {
+        assert_eq!(
+            Viewport::new(1920, 1080).bucketed(),
+            Viewport::new(2048, 1280)
+        );
+        assert_eq!(Viewport::new(256, 1).bucketed(), Viewport::new(256, 256));
+        assert_eq!(
+            Viewport::new(0, 100_000).bucketed(),
+            Viewport::new(256, 8192)
+        );
+    }
+
+    #[test]
+    fn test_rendition_fits_viewport() {
+        let (generator, temp) = create_test_rendition_generator(ThumbnailConfig::default());
+        let image_path = create_test_image(&temp, "scan.png", (1500, 2000));
+
+        let rendition = generator
+            .get_or_create_rendition(&image_path, Viewport::new(500, 350))
+            .unwrap();
+        assert_eq!(rendition.extension().unwrap(), "jpg");
+        // 表示領域は 512x512 に切り上げられる
+        assert_eq!(image::image_dimensions(&rendition).unwrap(), (384, 512));
+
+        // 同じ区切りに入る表示領域ではキャッシュを使う
+        let modified = std::fs::metadata(&rendition).unwrap().modified().unwrap();
+        let cached = generator
+            .get_or_create_rendition(&image_path, Viewport::new(512, 512))
+            .unwrap();
+        assert_eq!(cached, rendition);
+        assert_eq!(
+            std::fs::metadata(&cached).unwrap().modified().unwrap(),
+            modified
+        );
+
+        // 表示領域が変わると別のレンディションになる
+        let larger = generator
+            .get_or_create_rendition(&image_path, Viewport::new(1000, 700))
+            .unwrap();
+        assert_ne!(larger, rendition);
+        assert_eq!(image::image_dimensions(&larger).unwrap(), (576, 768));
+
+        // 生成が終わったレンディションのロックは残らない
+        assert!(generator.locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rendition_cache_is_pruned() {
+        let config = ThumbnailConfig {
+            max_rendition_cache_size: 1,
+            ..ThumbnailConfig::default()
+        };
+        let (generator, temp) = create_test_rendition_generator(config);
+        let cache_dir = temp.path().join("renditions");
+        let viewport = Viewport::new(256, 256);
+        let first = create_test_image(&temp, "first.png", (300, 300));
+        let second = create_test_image(&temp, "second.png", (300, 300));
+
+        let first = generator.get_or_create_rendition(&first, viewport).unwrap();
+        assert!(first.exists());
+        // 上限を超えると、生成したばかりのもの以外は削除される
+        let second = generator
+            .get_or_create_rendition(&second, viewport)
+            .unwrap();
+        assert!(second.exists());
+        assert!(!first.exists());
+        // 一時ファイルは残らない
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_rendition_does_not_upscale() {
+        let (generator, temp) = create_test_rendition_generator(ThumbnailConfig::default());
+        let image_path = create_test_image(&temp, "small.png", (300, 200));
+
+        let rendition = generator
+            .get_or_create_rendition(&image_path, Viewport::new(1920, 1080))
+            .unwrap();
+        assert_eq!(image::image_dimensions(&rendition).unwrap(), (300, 200));
+    }
+
+    #[test]
+    fn test_transparent_rendition_keeps_alpha() {
+        let (generator, temp) = create_test_rendition_generator(ThumbnailConfig::default());
+        let image_path = temp.path().join("icon.png");
+        RgbaImage::from_pixel(600, 600, Rgba([0, 0, 0, 0]))
+            .save(&image_path)
+            .unwrap();
+
+        let rendition = generator
+            .get_or_create_rendition(image_path.to_str().unwrap(), Viewport::new(256, 256))
+            .unwrap();
+        assert_eq!(rendition.extension().unwrap(), "png");
+        let rendition = image::open(&rendition).unwrap().to_rgba8();
+        assert_eq!(rendition.dimensions(), (256, 256));
+        assert_eq!(rendition.get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_rendition_regenerated_when_source_changes() {
+        let (generator, temp) = create_test_rendition_generator(ThumbnailConfig::default());
+        let image_path = create_test_image(&temp, "scan.png", (800, 600));
+        let viewport = Viewport::new(512, 512);
+
+        generator
+            .get_or_create_rendition(&image_path, viewport)
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        RgbImage::from_pixel(800, 600, Rgb([0, 0, 255]))
+            .save(&image_path)
+            .unwrap();
+
+        let rendition = generator
+            .get_or_create_rendition(&image_path, viewport)
+            .unwrap();
+        let pixel = *image::open(&rendition).unwrap().to_rgb8().get_pixel(10, 10);
+        assert!(pixel[2] > 200);
+    }
+
+    #[test]
+    fn test_rendition_errors() {
+        let (generator, temp) = create_test_rendition_generator(ThumbnailConfig::default());
+        let viewport = Viewport::new(512, 512);
+
+        assert!(matches!(
+            generator.get_or_create_rendition("/nonexistent/scan.png", viewport),
+            Err(ThumbnailError::ImageNotFound(_))
+        ));
+
+        let text_path = temp.path().join("notes.txt");
+        std::fs::write(&text_path, "not an image").unwrap();
+        assert!(matches!(
+            generator.get_or_create_rendition(text_path.to_str().unwrap(), viewport),
+            Err(ThumbnailError::UnsupportedFormat(_))
+        ));
+
+        // 生成に失敗した場合もロックは残らない
+        let broken_path = temp.path().join("broken.png");
+        std::fs::write(&broken_path, "not an image").unwrap();
+        assert!(generator
+            .get_or_create_rendition(broken_path.to_str().unwrap(), viewport)
+            .is_err());
+        assert!(generator.locks.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+// アプリ全体で共有する表示用レンディションのサービス
+//
+// ビューアで表示中のページのレンディションを返し、前後のページのレンディションを先に生成しておく。
+// ページ送りで古くなったプリフェッチは、新しいプリフェッチの開始時にキャンセルされる。
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::thumbnail::config::ThumbnailConfig;
+use crate::thumbnail::error::{Result, ThumbnailError};
+use crate::thumbnail::job::{CancellationToken, ThumbnailJobRegistry};
+use crate::thumbnail::memory::MemoryBudget;
+use crate::thumbnail::page::ContainerPages;
+use crate::thumbnail::rendition::{RenditionGenerator, Viewport};
+use crate::thumbnail::service::path_to_string;
+
+/// ページのレンディションのプリフェッチ結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRenditionResult {
+    /// コンテナ内のページ番号（0始まり、ファイル名の自然順）
+    pub index: usize,
+    pub image_path: String,
+    pub rendition_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 表示用レンディションのサービス
+///
+/// クローンしたインスタンスは同じジェネレーターとジョブレジストリを共有する。
+#[derive(Clone)]
+pub struct RenditionService {
+    generator: Arc<RenditionGenerator>,
+    jobs: Arc<ThumbnailJobRegistry>,
+    archive_cache_dir: PathBuf,
+}
+
+impl RenditionService {
+    /// 新しいRenditionServiceを作成
+    ///
+    /// # Arguments
+    /// * `config` - デコードの上限とリサイズのフィルターの設定
+    /// * `rendition_cache_dir` - レンディションのキャッシュディレクトリのパス
+    /// * `archive_cache_dir` - アーカイブ展開先ディレクトリのパス
+    pub fn new(
+        config: ThumbnailConfig,
+        rendition_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+    ) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, rendition_cache_dir, archive_cache_dir, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してRenditionServiceを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        rendition_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
+        Ok(Self {
+            generator: Arc::new(RenditionGenerator::with_memory_budget(
+                config,
+                rendition_cache_dir,
+                memory,
+            )?),
+            jobs: Arc::new(ThumbnailJobRegistry::new()),
+            archive_cache_dir,
+        })
+    }
+
+    /// デフォルト設定でRenditionServiceを作成
+    pub fn with_default_config(
+        rendition_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+    ) -> Result<Self> {
+        Self::new(
+            ThumbnailConfig::default(),
+            rendition_cache_dir,
+            archive_cache_dir,
+        )
+    }
+
+    /// ジョブレジストリを取得
+    pub fn jobs(&self) -> &ThumbnailJobRegistry {
+        &self.jobs
+    }
+
+    /// 画像のレンディションを生成または取得し、キャッシュパスを文字列で返す
+    pub fn get_display_rendition(&self, image_path: &str, viewport: Viewport) -> Result<String> {
+        let cache_path = self
+            .generator
+            .get_or_create_rendition(image_path, viewport)?;
+        path_to_string(&cache_path)
+    }
+
+    /// 表示中のページの前後 `count` ページのレンディションを生成する
+    ///
+    /// 表示中のページに近い順（次のページを前のページより先）に生成する。
+    /// 個々のページの生成失敗は結果の `error` に格納し、全体はエラーにしない。
+    ///
+    /// # Arguments
+    /// * `container_path` - コンテナ（フォルダ・アーカイブ）のパス
+    /// * `current_index` - 表示中のページ番号
+    /// * `count` - 前後それぞれに生成するページ数
+    /// * `viewport` - 表示領域の大きさ
+    /// * `token` - キャンセルトークン
+    pub fn prefetch_display_renditions(
+        &self,
+        container_path: &str,
+        current_index: usize,
+        count: usize,
+        viewport: Viewport,
+        token: &CancellationToken,
+    ) -> Result<Vec<PageRenditionResult>> {
+        let pages = ContainerPages::open(container_path, &self.archive_cache_dir)
+            .map_err(ThumbnailError::ContainerError)?;
+
+        let mut results = Vec::new();
+        for index in prefetch_order(current_index, count, pages.len()) {
+            if token.is_cancelled() {
+                break;
+            }
+            // PDF のページはプリフェッチするときに1ページずつ書き出す
+            let image_path = pages
+                .image_paths(&[index])
+                .and_then(|mut paths| paths.remove(0));
+            let (image_path, rendition_path, error) = match image_path {
+                Ok(image_path) => match self.get_display_rendition(&image_path, viewport) {
+                    Ok(path) => (image_path, Some(path), None),
+                    Err(e) => (image_path, None, Some(e.to_string())),
+                },
+                Err(error) => (String::new(), None, Some(error)),
+            };
+            results.push(PageRenditionResult {
+                index,
+                image_path,
+                rendition_path,
+                error,
+            });
+        }
+        Ok(results)
+    }
+}
+
+/// プリフェッチするページ番号を表示中のページに近い順に並べる
+fn prefetch_order(current_index: usize, count: usize, page_count: usize) -> Vec<usize> {
+    let mut order = Vec::with_capacity(count * 2);
+    for distance in 1..=count {
+        if let Some(next) = current_index
+            .checked_add(distance)
+            .filter(|&i| i < page_count)
+        {
+            order.push(next);
+        }
+        if let Some(previous) = current_index
+            .checked_sub(distance)
+            .filter(|&i| i < page_count)
+        {
+            order.push(previous);
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::create_test_rendition_service;
+    use std::fs::create_dir_all;
+
+    #[test]
+    fn test_prefetch_order() {
+        assert_eq!(prefetch_order(5, 2, 10), vec![6, 4, 7, 3]);
+        assert_eq!(prefetch_order(0, 2, 10), vec![1, 2]);
+        assert_eq!(prefetch_order(9, 2, 10), vec![8, 7]);
+        assert_eq!(prefetch_order(0, 3, 1), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_prefetch_display_renditions() {
+        let (service, temp) = create_test_rendition_service(ThumbnailConfig::default());
+        let folder = temp.path().join("pages");
+        create_dir_all(&folder).unwrap();
+        for name in ["1.png", "2.png", "3.png", "10.png"] {
+            image::RgbImage::new(800, 600)
+                .save(folder.join(name))
+                .unwrap();
+        }
+        // 壊れた画像は個別のエラーとして返される
+        std::fs::write(folder.join("4.png"), b"broken").unwrap();
+
+        let results = service
+            .prefetch_display_renditions(
+                folder.to_str().unwrap(),
+                2,
+                2,
+                Viewport::new(400, 300),
+                &CancellationToken::new(),
+            )
+            .unwrap();
+
+        let indexes: Vec<_> = results.iter().map(|r| r.index).collect();
+        assert_eq!(indexes, vec![3, 1, 4, 0]);
+        assert!(results[0].image_path.ends_with("4.png"));
+        assert!(results[0].rendition_path.is_none());
+        assert!(results[0].error.is_some());
+        assert!(results[1].image_path.ends_with("2.png"));
+        let rendition = results[1].rendition_path.as_ref().unwrap();
+        assert_eq!(image::image_dimensions(rendition).unwrap(), (512, 384));
+
+        // プリフェッチしたレンディションは表示時にそのまま使われる
+        let displayed = service
+            .get_display_rendition(&results[1].image_path, Viewport::new(400, 300))
+            .unwrap();
+        assert_eq!(&displayed, rendition);
+    }
+
+    #[test]
+    fn test_cancelled_prefetch_skips_pages() {
+        let (service, temp) = create_test_rendition_service(ThumbnailConfig::default());
+        let folder = temp.path().join("pages");
+        create_dir_all(&folder).unwrap();
+        for name in ["1.png", "2.png"] {
+            image::RgbImage::new(20, 20)
+                .save(folder.join(name))
+                .unwrap();
+        }
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let results = service
+            .prefetch_display_renditions(
+                folder.to_str().unwrap(),
+                0,
+                1,
+                Viewport::new(400, 300),
+                &token,
+            )
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_prefetch_error_for_missing_container() {
+        let (service, _temp) = create_test_rendition_service(ThumbnailConfig::default());
+
+        assert!(matches!(
+            service.prefetch_display_renditions(
+                "/nonexistent/container",
+                0,
+                1,
+                Viewport::new(400, 300),
+                &CancellationToken::new(),
+            ),
+            Err(ThumbnailError::ContainerError(_))
+        ));
+    }
+}
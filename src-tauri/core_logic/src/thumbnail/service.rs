@@ -0,0 +1,724 @@
+// アプリ全体で共有するサムネイルサービス
+//
+// アプリ起動時に1度だけ作成し、全てのサムネイル関連コマンドから利用する。
+// ワーカースレッド群（生成中タスクの管理を含む）、ジョブレジストリ、キャッシュインデックスを保持する。
+// 壊れた画像などの生成の失敗もキャッシュインデックスに記録し、画像が変更されるまで再生成しない。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::thumbnail::batch::{
+    BatchResult, BatchTask, BatchThumbnailGenerator, TaskPriority, ThumbnailKind,
+};
+use crate::thumbnail::cache_index::{CachedThumbnail, ThumbnailCacheIndex};
+use crate::thumbnail::config::{FolderThumbnailMode, ThumbnailConfig};
+use crate::thumbnail::error::{Result, ThumbnailError};
+use crate::thumbnail::folder::{self, CoverOverrides, FolderCover, FolderThumbnailResult};
+use crate::thumbnail::job::{CancellationToken, ThumbnailJobRegistry};
+use crate::thumbnail::memory::MemoryBudget;
+use crate::thumbnail::page::{ContainerPages, PageRange, PageThumbnailResult};
+
+/// サムネイルサービス
+///
+/// クローンしたインスタンスは同じワーカースレッド群とキャッシュを共有する。
+#[derive(Clone)]
+pub struct ThumbnailService {
+    batch: Arc<BatchThumbnailGenerator>,
+    jobs: Arc<ThumbnailJobRegistry>,
+    cache_index: Arc<ThumbnailCacheIndex>,
+    cover_overrides: Arc<CoverOverrides>,
+    archive_cache_dir: PathBuf,
+    /// フォルダサムネイルに並べる画像の最大数（1ならモザイクにしない）
+    folder_images: usize,
+}
+
+impl ThumbnailService {
+    /// 新しいThumbnailServiceを作成
+    ///
+    /// # Arguments
+    /// * `config` - サムネイル設定
+    /// * `thumbnail_cache_dir` - サムネイルキャッシュディレクトリのパス
+    /// * `archive_cache_dir` - アーカイブ展開先ディレクトリのパス
+    pub fn new(
+        config: ThumbnailConfig,
+        thumbnail_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+    ) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, thumbnail_cache_dir, archive_cache_dir, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してThumbnailServiceを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        thumbnail_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
+        let folder_images = match config.folder_mode {
+            FolderThumbnailMode::Single => 1,
+            FolderThumbnailMode::Mosaic => config.mosaic_max_images,
+        };
+        Ok(Self {
+            folder_images,
+            batch: Arc::new(BatchThumbnailGenerator::with_memory_budget(
+                config,
+                thumbnail_cache_dir,
+                memory,
+            )?),
+            jobs: Arc::new(ThumbnailJobRegistry::new()),
+            cache_index: Arc::new(ThumbnailCacheIndex::new()),
+            cover_overrides: Arc::new(CoverOverrides::in_memory()),
+            archive_cache_dir,
+        })
+    }
+
+    /// ユーザーが指定したカバー画像の保存先を設定
+    pub fn with_cover_overrides(mut self, cover_overrides: CoverOverrides) -> Self {
+        self.cover_overrides = Arc::new(cover_overrides);
+        self
+    }
+
+    /// デフォルト設定でThumbnailServiceを作成
+    pub fn with_default_config(
+        thumbnail_cache_dir: PathBuf,
+        archive_cache_dir: PathBuf,
+    ) -> Result<Self> {
+        Self::new(
+            ThumbnailConfig::default(),
+            thumbnail_cache_dir,
+            archive_cache_dir,
+        )
+    }
+
+    /// ジョブレジストリを取得
+    pub fn jobs(&self) -> &ThumbnailJobRegistry {
+        &self.jobs
+    }
+
+    /// 画像のサムネイルを生成または取得
+    ///
+    /// 同じ画像がプリフェッチで生成中の場合は、その完了を待って結果を共有する。
+    /// 以前に生成に失敗して画像が変更されていない場合は、再生成せずに以前のエラーを返す
+    /// （[`ThumbnailError::into_cached`]）。
+    pub fn get_or_create_thumbnail(&self, image_path: &str) -> Result<PathBuf> {
+        match self.cache_index.lookup(image_path) {
+            Some(CachedThumbnail::Generated(thumbnail_path)) => return Ok(thumbnail_path),
+            Some(CachedThumbnail::Failed(error)) => return Err(error.into_cached()),
+            None => {}
+        }
+
+        let task = BatchTask::new(image_path.to_string(), TaskPriority::High);
+        self.run_task(task)
+    }
+
+    /// 1件のタスクを最優先で実行し、完了を待つ
+    fn run_task(&self, task: BatchTask) -> Result<PathBuf> {
+        let image_path = task.image_path.clone();
+        let is_single = task.kind == ThumbnailKind::Single;
+        let result = self
+            .batch
+            .batch_create_thumbnails(vec![task])
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ThumbnailError::GenerationError(format!(
+                    "Thumbnail task for {} was dropped",
+                    image_path
+                ))
+            })?;
+        if is_single {
+            self.record(&result);
+        }
+
+        match (result.thumbnail_path, result.cause, result.error) {
+            (Some(thumbnail_path), _, _) => Ok(thumbnail_path),
+            (None, Some(cause), _) => Err(cause),
+            (None, None, error) => Err(ThumbnailError::GenerationError(
+                error.unwrap_or_else(|| "Unknown error".to_string()),
+            )),
+        }
+    }
+
+    /// フォルダ（コンテナ）のサムネイルを取得
+    ///
+    /// モザイク表示が有効で画像が2枚以上ある場合は、先頭の画像を並べたサムネイルになる。
+    ///
+    /// # Returns
+    /// 画像を含まないフォルダの場合は None
+    pub fn get_folder_thumbnail(
+        &self,
+        container_path: &str,
+    ) -> Result<Option<FolderThumbnailResult>> {
+        let Some(cover) = self.select_cover(container_path)? else {
+            return Ok(None);
+        };
+
+        let task = folder_task(&cover, TaskPriority::High);
+        let mosaic = task.kind != ThumbnailKind::Single;
+        let cache_path = if mosaic {
+            self.run_task(task)?
+        } else {
+            self.get_or_create_thumbnail(&task.image_path)?
+        };
+        let FolderCover {
+            selection,
+            image_count,
+            ..
+        } = cover;
+        let image_path = selection.image_path;
+        let image_name = Path::new(&image_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let thumbnail_path = path_to_string(&cache_path)?;
+
+        Ok(Some(FolderThumbnailResult {
+            image_path,
+            thumbnail_path,
+            image_name,
+            cover_source: selection.source,
+            image_count,
+            mosaic,
+        }))
+    }
+
+    /// フォルダのカバー画像を指定する（`None` で指定を解除）
+    ///
+    /// # Returns
+    /// 新しいカバー画像によるフォルダサムネイル
+    pub fn set_folder_cover(
+        &self,
+        container_path: &str,
+        image_path: Option<String>,
+    ) -> Result<Option<FolderThumbnailResult>> {
+        if let Some(image_path) = &image_path {
+            let images = crate::list_images_in_container(container_path, &self.archive_cache_dir)
+                .map_err(|e| ThumbnailError::ContainerError(format!("{:?}", e)))?;
+            if !images.contains(image_path) {
+                return Err(ThumbnailError::ContainerError(format!(
+                    "'{}' is not an image in '{}'",
+                    image_path, container_path
+                )));
+            }
+        }
+
+        self.cover_overrides.set(container_path, image_path)?;
+        self.get_folder_thumbnail(container_path)
+    }
+
+    /// 任意の画像（コンテナ内のページ）のサムネイルを取得
+    ///
+    /// # Returns
+    /// サムネイルのキャッシュパス
+    pub fn get_image_thumbnail(&self, image_path: &str) -> Result<String> {
+        let cache_path = self.get_or_create_thumbnail(image_path)?;
+        path_to_string(&cache_path)
+    }
+
+    /// コンテナ内の指定範囲のページのサムネイルをまとめて取得
+    ///
+    /// ページはファイル名の自然順で数える。アーカイブの場合は展開済みの画像から生成する。
+    /// 個々のページの生成失敗は結果の `error` に格納し、全体はエラーにしない。
+    ///
+    /// # Arguments
+    /// * `container_path` - コンテナ（フォルダ・アーカイブ）のパス
+    /// * `range` - 取得するページの範囲
+    pub fn get_container_thumbnails(
+        &self,
+        container_path: &str,
+        range: PageRange,
+    ) -> Result<Vec<PageThumbnailResult>> {
+        let pages = ContainerPages::open(container_path, &self.archive_cache_dir)
+            .map_err(ThumbnailError::ContainerError)?;
+        let indexes: Vec<usize> = range.clamp(pages.len()).collect();
+        let image_paths = pages
+            .image_paths(&indexes)
+            .map_err(ThumbnailError::ContainerError)?;
+
+        let mut results: Vec<PageThumbnailResult> = Vec::with_capacity(indexes.len());
+        let mut tasks = Vec::new();
+        for (index, image_path) in indexes.into_iter().zip(image_paths) {
+            let image_path = match image_path {
+                Ok(image_path) => image_path,
+                Err(error) => {
+                    results.push(PageThumbnailResult {
+                        index,
+                        image_path: String::new(),
+                        thumbnail_path: None,
+                        error: Some(error),
+                    });
+                    continue;
+                }
+            };
+            let (thumbnail_path, error) = match self.cache_index.lookup(&image_path) {
+                Some(CachedThumbnail::Generated(path)) => {
+                    (Some(path.to_string_lossy().to_string()), None)
+                }
+                Some(CachedThumbnail::Failed(error)) => {
+                    (None, Some(error.into_cached().to_string()))
+                }
+                None => {
+                    tasks.push(BatchTask::new(image_path.clone(), TaskPriority::High));
+                    (None, None)
+                }
+            };
+            results.push(PageThumbnailResult {
+                index,
+                image_path,
+                thumbnail_path,
+                error,
+            });
+        }
+
+        for generated in self.batch.batch_create_thumbnails(tasks) {
+            self.record(&generated);
+            if let Some(result) = results
+                .iter_mut()
+                .find(|r| r.image_path == generated.image_path)
+            {
+                result.thumbnail_path = generated
+                    .thumbnail_path
+                    .map(|p| p.to_string_lossy().to_string());
+                result.error = generated.error;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// 複数フォルダのサムネイルをプリフェッチ
+    ///
+    /// フォルダの並び順から優先度を決定する。生成済みのサムネイル（と以前の生成の失敗）は
+    /// キューに投入せず即座に通知する。
+    ///
+    /// # Arguments
+    /// * `folder_paths` - フォルダ（コンテナ）のパスのリスト
+    /// * `token` - キャンセルトークン
+    /// * `on_result` - サムネイル1件ごとに呼ばれるコールバック
+    pub fn prefetch_folder_thumbnails<F>(
+        &self,
+        folder_paths: &[String],
+        token: &CancellationToken,
+        on_result: F,
+    ) -> Vec<BatchResult>
+    where
+        F: Fn(&BatchResult),
+    {
+        let mut results = Vec::new();
+        let mut tasks = Vec::new();
+        // モザイクの結果は代表画像のサムネイルではないため、キャッシュインデックスに登録しない
+        let mut mosaic_folders = HashSet::new();
+
+        for (index, folder_path) in folder_paths.iter().enumerate() {
+            if token.is_cancelled() {
+                return results;
+            }
+            let priority = folder::assign_priority(index);
+            let Some(task) = self.resolve_folder_task(folder_path, priority) else {
+                continue;
+            };
+            let task = task.with_container(folder_path.clone());
+
+            if task.kind != ThumbnailKind::Single {
+                mosaic_folders.insert(folder_path.as_str());
+            } else if let Some(cached) = self.cache_index.lookup(&task.image_path) {
+                let result = match cached {
+                    CachedThumbnail::Generated(thumbnail_path) => {
+                        BatchResult::success(task.image_path, thumbnail_path)
+                    }
+                    CachedThumbnail::Failed(error) => {
+                        BatchResult::from_error(task.image_path, &error.into_cached())
+                    }
+                }
+                .with_container(Some(folder_path.clone()));
+                on_result(&result);
+                results.push(result);
+                continue;
+            }
+
+            tasks.push(task);
+        }
+
+        let generated = self
+            .batch
+            .batch_create_thumbnails_cancellable(tasks, token, |result| {
+                let is_mosaic = result
+                    .container_path
+                    .as_deref()
+                    .is_some_and(|container| mosaic_folders.contains(container));
+                if !is_mosaic {
+                    self.record(result);
+                }
+                on_result(result);
+            });
+        results.extend(generated);
+        results
+    }
+
+    /// プリフェッチ中のフォルダサムネイルの優先度を引き上げる（例: 画面内にスクロールされた）
+    ///
+    /// # Returns
+    /// 優先度が引き上げられたタスク数
+    pub fn bump_folder_priority(&self, folder_paths: &[String], priority: TaskPriority) -> usize {
+        folder_paths
+            .iter()
+            .filter_map(|folder_path| self.resolve_folder_task(folder_path, priority))
+            .filter(|task| self.batch.bump_priority(&task.queue_key(), priority))
+            .count()
+    }
+
+    /// フォルダのカバー画像を選択（ユーザーの指定を考慮する）
+    fn select_cover(&self, container_path: &str) -> Result<Option<FolderCover>> {
+        let cover_override = self.cover_overrides.get(container_path);
+        folder::select_folder_cover_images(
+            container_path,
+            &self.archive_cache_dir,
+            cover_override.as_deref(),
+            self.folder_images,
+        )
+        .map_err(ThumbnailError::ContainerError)
+    }
+
+    /// フォルダのサムネイル生成タスクを作成（取得に失敗した場合はログを出力して None）
+    fn resolve_folder_task(&self, folder_path: &str, priority: TaskPriority) -> Option<BatchTask> {
+        self.select_cover(folder_path)
+            .inspect_err(|e| {
+                log::error!("Failed to select cover for folder '{}': {}", folder_path, e);
+            })
+            .ok()
+            .flatten()
+            .map(|cover| folder_task(&cover, priority))
+    }
+
+    /// 生成結果をキャッシュインデックスに登録
+    ///
+    /// 失敗は、原因がソース画像にある場合のみ記録する（キャッシュの書き込み失敗などは再試行する）。
+    fn record(&self, result: &BatchResult) {
+        match (&result.thumbnail_path, &result.cause) {
+            (Some(thumbnail_path), _) => self
+                .cache_index
+                .insert(&result.image_path, thumbnail_path.clone()),
+            (None, Some(error)) if result.permanent_failure => self
+                .cache_index
+                .insert_failure(&result.image_path, error.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// フォルダサムネイルの生成タスクを作成（画像が2枚以上ならモザイク）
+fn folder_task(cover: &FolderCover, priority: TaskPriority) -> BatchTask {
+    let task = BatchTask::new(cover.selection.image_path.clone(), priority);
+    if cover.images.len() > 1 {
+        task.with_mosaic(cover.images.clone())
+    } else {
+        task
+    }
+}
+
+/// サムネイルのパスを IPC 向けの文字列に変換
+pub(super) fn path_to_string(path: &Path) -> Result<String> {
+    path.to_str().map(|s| s.to_string()).ok_or_else(|| {
+        ThumbnailError::GenerationError("Failed to convert thumbnail path to string".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::{create_test_thumbnail_service, TempTestDir};
+    use crate::thumbnail::folder::CoverSource;
+    use std::cell::RefCell;
+    use std::fs::create_dir_all;
+
+    fn create_folder_with_image(temp: &TempTestDir, name: &str) -> String {
+        let folder = temp.path().join(name);
+        create_dir_all(&folder).unwrap();
+        image::RgbImage::new(20, 20)
+            .save(folder.join("image.png"))
+            .unwrap();
+        folder.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_get_folder_thumbnail_creates_thumbnail() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = create_folder_with_image(&temp, "folder");
+
+        let result = service.get_folder_thumbnail(&folder).unwrap().unwrap();
+        assert_eq!(result.image_name, "image.png");
+        assert!(Path::new(&result.thumbnail_path).exists());
+    }
+
+    #[test]
+    fn test_get_folder_thumbnail_returns_none_for_empty_folder() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = temp.path().join("empty");
+        create_dir_all(&folder).unwrap();
+
+        let result = service
+            .get_folder_thumbnail(folder.to_str().unwrap())
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_get_folder_thumbnail_error_for_missing_folder() {
+        let (service, _temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+
+        let result = service.get_folder_thumbnail("/nonexistent/folder");
+        assert!(matches!(result, Err(ThumbnailError::ContainerError(_))));
+    }
+
+    #[test]
+    fn test_prefetch_records_results_in_cache_index() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folders = vec![
+            create_folder_with_image(&temp, "a"),
+            create_folder_with_image(&temp, "b"),
+        ];
+
+        let reported = RefCell::new(Vec::new());
+        let results =
+            service.prefetch_folder_thumbnails(&folders, &CancellationToken::new(), |r| {
+                reported
+                    .borrow_mut()
+                    .push(r.container_path.clone().unwrap())
+            });
+        assert_eq!(results.len(), 2);
+        assert_eq!(reported.borrow().len(), 2);
+        assert_eq!(service.cache_index.len(), 2);
+
+        // 2回目はキャッシュインデックスから即座に返される
+        let again = service.prefetch_folder_thumbnails(&folders, &CancellationToken::new(), |_| {});
+        assert_eq!(again.len(), 2);
+        assert!(again.iter().all(|r| r.thumbnail_path.is_some()));
+    }
+
+    #[test]
+    fn test_image_too_large_error() {
+        let config = ThumbnailConfig {
+            max_decode_pixels: 1000,
+            ..ThumbnailConfig::default()
+        };
+        let (service, temp) = create_test_thumbnail_service(config);
+        let large = temp.path().join("large.png");
+        image::RgbImage::new(50, 40).save(&large).unwrap();
+        let large = large.to_string_lossy().to_string();
+
+        // 初回も、失敗がキャッシュされた後も ImageTooLarge を返す
+        for _ in 0..2 {
+            match service.get_image_thumbnail(&large) {
+                Err(ThumbnailError::ImageTooLarge { width, height, .. }) => {
+                    assert_eq!((width, height), (50, 40));
+                }
+                other => panic!("Expected ImageTooLarge, got: {:?}", other),
+            }
+        }
+        assert!(matches!(
+            service.cache_index.lookup(&large),
+            Some(CachedThumbnail::Failed(
+                ThumbnailError::ImageTooLarge { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_failed_generation_is_cached_until_source_changes() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = temp.path().join("broken");
+        create_dir_all(&folder).unwrap();
+        let image_path = folder.join("image.png");
+        std::fs::write(&image_path, b"not an image").unwrap();
+        let folder = folder.to_string_lossy().to_string();
+
+        let first = service.get_folder_thumbnail(&folder).unwrap_err();
+        assert!(first.is_source_error(), "got: {:?}", first);
+        // 2回目以降はデコードせず、以前の失敗の理由を返す
+        match service.get_folder_thumbnail(&folder) {
+            Err(ThumbnailError::CachedFailure(reason)) => {
+                assert!(reason.contains("Failed to decode"), "got: {}", reason)
+            }
+            other => panic!("Expected CachedFailure, got: {:?}", other),
+        }
+        let prefetched = service.prefetch_folder_thumbnails(
+            std::slice::from_ref(&folder),
+            &CancellationToken::new(),
+            |_| {},
+        );
+        assert!(prefetched[0].permanent_failure);
+        assert!(prefetched[0].error.is_some());
+
+        // 画像が変更されたら再生成する
+        image::RgbImage::new(20, 20).save(&image_path).unwrap();
+        let result = service.get_folder_thumbnail(&folder).unwrap().unwrap();
+        assert!(Path::new(&result.thumbnail_path).exists());
+    }
+
+    #[test]
+    fn test_get_image_thumbnail() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = create_folder_with_image(&temp, "folder");
+
+        let image_path = Path::new(&folder).join("image.png");
+        let thumbnail = service
+            .get_image_thumbnail(image_path.to_str().unwrap())
+            .unwrap();
+        assert!(Path::new(&thumbnail).exists());
+    }
+
+    #[test]
+    fn test_get_container_thumbnails_returns_requested_range() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = temp.path().join("pages");
+        create_dir_all(&folder).unwrap();
+        for name in ["1.png", "2.png", "3.png", "10.png"] {
+            image::RgbImage::new(20, 20)
+                .save(folder.join(name))
+                .unwrap();
+        }
+        // 壊れた画像は個別のエラーとして返される
+        std::fs::write(folder.join("4.png"), b"broken").unwrap();
+
+        let results = service
+            .get_container_thumbnails(folder.to_str().unwrap(), PageRange::new(1, 4))
+            .unwrap();
+
+        let indexes: Vec<_> = results.iter().map(|r| r.index).collect();
+        assert_eq!(indexes, vec![1, 2, 3]);
+        assert!(results[0].image_path.ends_with("2.png"));
+        assert!(results[0].thumbnail_path.is_some());
+        assert!(results[1].image_path.ends_with("3.png"));
+        assert!(results[2].image_path.ends_with("4.png"));
+        assert!(results[2].thumbnail_path.is_none());
+        assert!(results[2].error.is_some());
+    }
+
+    #[test]
+    fn test_get_container_thumbnails_for_archive() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let sources = temp.path().join("sources");
+        create_dir_all(&sources).unwrap();
+        let images: Vec<_> = ["b.png", "a.png"]
+            .iter()
+            .map(|name| {
+                let path = sources.join(name);
+                image::RgbImage::new(20, 20).save(&path).unwrap();
+                path
+            })
+            .collect();
+        let zip_path = temp.path().join("book.zip");
+        TempTestDir::create_zip(&zip_path, images.iter().collect()).unwrap();
+
+        let results = service
+            .get_container_thumbnails(zip_path.to_str().unwrap(), PageRange::new(0, 100))
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].image_path.ends_with("a.png"));
+        assert!(results.iter().all(|r| r.thumbnail_path.is_some()));
+    }
+
+    #[test]
+    fn test_get_folder_thumbnail_prefers_named_cover() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = create_folder_with_image(&temp, "folder");
+        image::RgbImage::new(20, 20)
+            .save(Path::new(&folder).join("cover.png"))
+            .unwrap();
+
+        let result = service.get_folder_thumbnail(&folder).unwrap().unwrap();
+        assert_eq!(result.image_name, "cover.png");
+        assert_eq!(result.cover_source, CoverSource::Named);
+    }
+
+    #[test]
+    fn test_set_folder_cover_overrides_selection() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let overrides_path = temp.path().join("covers.json");
+        let service = service.with_cover_overrides(CoverOverrides::load(&overrides_path).unwrap());
+        let folder = create_folder_with_image(&temp, "folder");
+        let page = Path::new(&folder).join("page.png");
+        image::RgbImage::new(20, 20).save(&page).unwrap();
+        let page = page.to_string_lossy().to_string();
+
+        let result = service
+            .set_folder_cover(&folder, Some(page.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.image_path, page);
+        assert_eq!(result.cover_source, CoverSource::Override);
+        assert!(overrides_path.exists(), "Override should be persisted");
+
+        let result = service.set_folder_cover(&folder, None).unwrap().unwrap();
+        assert_eq!(result.image_name, "image.png");
+        assert_eq!(result.cover_source, CoverSource::FirstImage);
+    }
+
+    #[test]
+    fn test_set_folder_cover_rejects_image_outside_folder() {
+        let (service, temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let folder = create_folder_with_image(&temp, "folder");
+
+        let result = service.set_folder_cover(&folder, Some("/elsewhere/image.png".to_string()));
+        assert!(matches!(result, Err(ThumbnailError::ContainerError(_))));
+    }
+
+    #[test]
+    fn test_get_folder_thumbnail_mosaic_mode() {
+        let config = ThumbnailConfig::default().with_folder_mode(FolderThumbnailMode::Mosaic);
+        let (service, temp) = create_test_thumbnail_service(config);
+        let folder = create_folder_with_image(&temp, "folder");
+        for name in ["1.png", "2.png"] {
+            image::RgbImage::new(20, 20)
+                .save(Path::new(&folder).join(name))
+                .unwrap();
+        }
+
+        let result = service.get_folder_thumbnail(&folder).unwrap().unwrap();
+        assert!(result.mosaic);
+        assert_eq!(result.image_count, 3);
+        assert!(Path::new(&result.thumbnail_path).exists());
+        assert!(
+            service.cache_index.is_empty(),
+            "Mosaic should not be indexed as the cover's thumbnail"
+        );
+
+        let cover = service.get_image_thumbnail(&result.image_path).unwrap();
+        assert_ne!(cover, result.thumbnail_path);
+
+        // 画像が1枚のフォルダは通常のサムネイル
+        let single = create_folder_with_image(&temp, "single");
+        let result = service.get_folder_thumbnail(&single).unwrap().unwrap();
+        assert!(!result.mosaic);
+        assert_eq!(result.image_count, 1);
+    }
+
+    #[test]
+    fn test_prefetch_mosaic_folders() {
+        let config = ThumbnailConfig::default().with_folder_mode(FolderThumbnailMode::Mosaic);
+        let (service, temp) = create_test_thumbnail_service(config);
+        let folder = create_folder_with_image(&temp, "folder");
+        image::RgbImage::new(20, 20)
+            .save(Path::new(&folder).join("1.png"))
+            .unwrap();
+
+        let results =
+            service.prefetch_folder_thumbnails(&[folder], &CancellationToken::new(), |_| {});
+        assert_eq!(results.len(), 1);
+        assert!(results[0].thumbnail_path.is_some());
+        assert!(service.cache_index.is_empty());
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let (service, _temp) = create_test_thumbnail_service(ThumbnailConfig::default());
+        let cloned = service.clone();
+
+        let (job_id, _) = service.jobs().start(None);
+        assert!(cloned.jobs().is_running(job_id));
+    }
+}
@@ -0,0 +1,13 @@
+// 大きな画像を拡大表示するためのタイルピラミッド
+//
+// 画像を縮小率ごとのレベルに分け、各レベルを一定サイズのタイルに分割して配信する。
+// レベルの数え方は Deep Zoom（DZI）と同じで、最大レベルが原寸、1つ下がるごとに 1/2 になる。
+// タイルは要求されたレベルを初めて使うときに生成し、キャッシュディレクトリに保存する。
+
+pub mod error;
+pub mod pyramid;
+pub mod service;
+
+pub use error::{Result, TileError};
+pub use pyramid::{TileLevel, TilePyramid, DEFAULT_TILE_SIZE};
+pub use service::TileService;
@@ -0,0 +1,42 @@
+// タイル生成のエラー型定義
+
+use thiserror::Error;
+
+/// タイルの生成と取得に関するエラー
+#[derive(Error, Debug)]
+pub enum TileError {
+    /// 画像ファイルが見つからない
+    #[error("Image file not found: {0}")]
+    ImageNotFound(String),
+
+    /// 画像のデコードに失敗
+    #[error("Failed to decode image: {0}")]
+    DecodeError(String),
+
+    /// デコードの上限を超える大きさの画像
+    #[error("Image too large to decode: {path} ({width}x{height})")]
+    ImageTooLarge {
+        path: String,
+        width: u32,
+        height: u32,
+    },
+
+    /// 存在しないレベル・位置のタイル
+    #[error("Tile out of range: level {level}, column {column}, row {row}")]
+    OutOfRange { level: u32, column: u32, row: u32 },
+
+    /// タイルの保存に失敗
+    #[error("Failed to save tile: {0}")]
+    SaveError(String),
+
+    /// I/Oエラー
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// 設定バリデーションエラー
+    #[error("Configuration validation error: {0}")]
+    ConfigError(String),
+}
+
+/// Resultのエイリアスで TileError をデフォルトのエラー型として使用
+pub type Result<T> = std::result::Result<T, TileError>;
@@ -0,0 +1,135 @@
+// タイルピラミッドのレベルとタイルの寸法の計算
+
+use serde::Serialize;
+
+/// タイルの一辺の標準の大きさ（ピクセル）
+pub const DEFAULT_TILE_SIZE: u32 = 256;
+
+/// タイルピラミッドの構成（ビューアがタイルを要求するための情報）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TilePyramid {
+    /// 原寸の幅（向きを適用した表示上の寸法）
+    pub width: u32,
+    /// 原寸の高さ
+    pub height: u32,
+    /// タイルの一辺の大きさ（右端・下端のタイルはこれより小さい）
+    pub tile_size: u32,
+    /// 最大レベル（原寸）。レベル 0 は 1x1 ピクセル
+    pub max_level: u32,
+    /// レベル 0 から最大レベルまでの各レベル
+    pub levels: Vec<TileLevel>,
+}
+
+/// ピラミッドの1つのレベル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileLevel {
+    pub level: u32,
+    pub width: u32,
+    pub height: u32,
+    /// 横方向のタイル数
+    pub columns: u32,
+    /// 縦方向のタイル数
+    pub rows: u32,
+}
+
+impl TilePyramid {
+    /// 原寸とタイルの大きさからピラミッドを作成
+    pub fn new(width: u32, height: u32, tile_size: u32) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        // 長辺が1ピクセルになるまで半分にし続けた回数
+        let max_level = width.max(height).next_power_of_two().trailing_zeros();
+        let levels = (0..=max_level)
+            .map(|level| {
+                let divisor = 1u64 << (max_level - level);
+                let level_width = u64::from(width).div_ceil(divisor) as u32;
+                let level_height = u64::from(height).div_ceil(divisor) as u32;
+                TileLevel {
+                    level,
+                    width: level_width,
+                    height: level_height,
+                    columns: level_width.div_ceil(tile_size),
+                    rows: level_height.div_ceil(tile_size),
+                }
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            tile_size,
+            max_level,
+            levels,
+        }
+    }
+
+    /// レベルの情報を取得（存在しないレベルは None）
+    pub fn level(&self, level: u32) -> Option<&TileLevel> {
+        self.levels.get(level as usize)
+    }
+
+    /// タイルのレベル内での位置と大きさ（x, y, 幅, 高さ）。存在しないタイルは None
+    pub fn tile_rect(&self, level: u32, column: u32, row: u32) -> Option<(u32, u32, u32, u32)> {
+        let info = self.level(level)?;
+        if column >= info.columns || row >= info.rows {
+            return None;
+        }
+        let x = column * self.tile_size;
+        let y = row * self.tile_size;
+        Some((
+            x,
+            y,
+            self.tile_size.min(info.width - x),
+            self.tile_size.min(info.height - y),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        let pyramid = TilePyramid::new(1000, 600, 256);
+        // 1000 < 1024 = 2^10
+        assert_eq!(pyramid.max_level, 10);
+        assert_eq!(pyramid.levels.len(), 11);
+
+        let full = pyramid.level(10).unwrap();
+        assert_eq!(
+            (full.width, full.height, full.columns, full.rows),
+            (1000, 600, 4, 3)
+        );
+        let half = pyramid.level(9).unwrap();
+        assert_eq!(
+            (half.width, half.height, half.columns, half.rows),
+            (500, 300, 2, 2)
+        );
+        let smallest = pyramid.level(0).unwrap();
+        assert_eq!((smallest.width, smallest.height), (1, 1));
+        assert!(pyramid.level(11).is_none());
+    }
+
+    #[test]
+    fn test_tile_rect() {
+        let pyramid = TilePyramid::new(1000, 600, 256);
+        assert_eq!(pyramid.tile_rect(10, 0, 0), Some((0, 0, 256, 256)));
+        // 右下のタイルは端までの大きさ
+        assert_eq!(pyramid.tile_rect(10, 3, 2), Some((768, 512, 232, 88)));
+        assert_eq!(pyramid.tile_rect(10, 4, 0), None);
+        assert_eq!(pyramid.tile_rect(11, 0, 0), None);
+    }
+
+    #[test]
+    fn test_power_of_two_size() {
+        let pyramid = TilePyramid::new(512, 256, 256);
+        assert_eq!(pyramid.max_level, 9);
+        assert_eq!(pyramid.level(9).unwrap().columns, 2);
+        assert_eq!(pyramid.level(8).unwrap().columns, 1);
+
+        let pyramid = TilePyramid::new(1, 1, 256);
+        assert_eq!(pyramid.max_level, 0);
+    }
+}
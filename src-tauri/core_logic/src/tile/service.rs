@@ -0,0 +1,479 @@
+// タイルの生成とキャッシュ
+//
+// 要求されたレベルのタイルがなければ、画像をそのレベルの大きさで開いてタイルに分割する。
+// 他のレベルは要求されるまで生成しない。デコードはサムネイルと同じ上限とメモリ予算に従う。
+// キャッシュは `{cache_dir}/{画像パスのハッシュ}/{レベル}/{列}_{行}.{拡張子}` に保存する。
+// キャッシュは設定の `max_tile_cache_size` を超えないよう、生成のたびに古い画像のタイルから削除する。
+
+use crate::decode::{open_image_to_fit_with_limits, open_image_with_limits, read_image_info};
+use crate::thumbnail::config::ThumbnailConfig;
+use crate::thumbnail::error::ThumbnailError;
+use crate::thumbnail::generator::estimate_decode;
+use crate::thumbnail::MemoryBudget;
+use crate::tile::error::{Result, TileError};
+use crate::tile::pyramid::{TilePyramid, DEFAULT_TILE_SIZE};
+use crate::utils::{hash_path, prune_cache_dir};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// タイルの大きさの下限・上限（ピクセル）
+const MIN_TILE_SIZE: u32 = 64;
+const MAX_TILE_SIZE: u32 = 2048;
+
+/// レベルの全タイルを保存したことを示すファイル（中身はタイルの拡張子）
+const COMPLETE_MARKER: &str = "complete";
+
+/// タイルの生成と取得
+///
+/// クローンしたインスタンスはキャッシュ・メモリ予算・生成中のロックを共有する。
+#[derive(Clone)]
+pub struct TileService {
+    config: Arc<ThumbnailConfig>,
+    cache_dir: PathBuf,
+    tile_size: u32,
+    memory: Arc<MemoryBudget>,
+    /// 生成中の画像ごとのロック（同じ画像のタイルを同時に生成しない）
+    locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl TileService {
+    /// 新しいTileServiceを作成
+    ///
+    /// # Arguments
+    /// * `config` - デコードの上限とメモリ予算の設定
+    /// * `cache_dir` - タイルキャッシュディレクトリのパス
+    /// * `tile_size` - タイルの一辺の大きさ（64-2048）
+    pub fn new(config: ThumbnailConfig, cache_dir: PathBuf, tile_size: u32) -> Result<Self> {
+        let memory = Arc::new(MemoryBudget::new(config.decode_memory_budget));
+        Self::with_memory_budget(config, cache_dir, tile_size, memory)
+    }
+
+    /// 共有するメモリ予算（[`MemoryBudget`]）を指定してTileServiceを作成
+    pub fn with_memory_budget(
+        config: ThumbnailConfig,
+        cache_dir: PathBuf,
+        tile_size: u32,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self> {
+        if !(MIN_TILE_SIZE..=MAX_TILE_SIZE).contains(&tile_size) {
+            return Err(TileError::ConfigError(format!(
+                "Tile size must be between {} and {}, got {}",
+                MIN_TILE_SIZE, MAX_TILE_SIZE, tile_size
+            )));
+        }
+        config
+            .validate_decode_limits()
+            .map_err(TileError::ConfigError)?;
+        Ok(Self {
+            config: Arc::new(config),
+            cache_dir,
+            tile_size,
+            memory,
+            locks: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// デフォルト設定と標準のタイルの大きさ（256px）でTileServiceを作成
+    pub fn with_default_config(cache_dir: PathBuf) -> Result<Self> {
+        Self::new(ThumbnailConfig::default(), cache_dir, DEFAULT_TILE_SIZE)
+    }
+
+    /// 画像のタイルピラミッドの構成を取得（画像全体はデコードしない）
+    pub fn get_pyramid(&self, image_path: &str) -> Result<TilePyramid> {
+        if !Path::new(image_path).exists() {
+            return Err(TileError::ImageNotFound(image_path.to_string()));
+        }
+        let info = read_image_info(image_path).map_err(|e| decode_error(image_path, e))?;
+        Ok(TilePyramid::new(info.width, info.height, self.tile_size))
+    }
+
+    /// タイルを生成または取得
+    ///
+    /// # Arguments
+    /// * `image_path` - ソース画像のパス
+    /// * `level` - レベル（`max_level` が原寸）
+    /// * `column` / `row` - レベル内のタイルの位置（左上が 0, 0）
+    ///
+    /// # Returns
+    /// タイルのキャッシュパス
+    pub fn get_tile(&self, image_path: &str, level: u32, column: u32, row: u32) -> Result<PathBuf> {
+        let pyramid = self.get_pyramid(image_path)?;
+        if pyramid.tile_rect(level, column, row).is_none() {
+            return Err(TileError::OutOfRange { level, column, row });
+        }
+
+        let image_dir = self.cache_dir.join(hash_path(&image_path));
+        let lock = self.lock_for(image_path);
+        let extension = {
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            match completed_extension(image_path, &image_dir, level) {
+                Some(extension) => Ok(extension),
+                None => {
+                    let extension = self.generate_level(image_path, &image_dir, &pyramid, level);
+                    self.prune_cache();
+                    extension
+                }
+            }
+        };
+        self.release_lock(image_path, lock);
+        Ok(tile_path(&image_dir, level, column, row, &extension?))
+    }
+
+    fn lock_for(&self, image_path: &str) -> Arc<Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(locks.entry(image_path.to_string()).or_default())
+    }
+
+    /// ロックを返し、他に使っているスレッドがなければ一覧から取り除く
+    fn release_lock(&self, image_path: &str, lock: Arc<Mutex<()>>) {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        drop(lock);
+        // ロックの複製は一覧のロック中にだけ作られるため、ここで数えた参照数は増えない
+        if locks
+            .get(image_path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(image_path);
+        }
+    }
+
+    /// キャッシュの合計サイズを `max_tile_cache_size` 以下に保つ（生成中の画像のタイルは削除しない）
+    fn prune_cache(&self) {
+        let locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        let in_use: HashSet<String> = locks.keys().map(hash_path).collect();
+        let result = prune_cache_dir(&self.cache_dir, self.config.max_tile_cache_size, |path| {
+            path.file_name()
+                .is_some_and(|name| in_use.contains(name.to_string_lossy().as_ref()))
+        });
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to prune tile cache {}: {}",
+                self.cache_dir.display(),
+                e
+            );
+        }
+    }
+
+    /// 指定したレベルのタイルを生成する
+    ///
+    /// # Returns
+    /// タイルの拡張子（透過画像は png、それ以外は jpg）
+    fn generate_level(
+        &self,
+        image_path: &str,
+        image_dir: &Path,
+        pyramid: &TilePyramid,
+        level: u32,
+    ) -> Result<String> {
+        let Some(info) = pyramid.level(level) else {
+            return Err(TileError::OutOfRange {
+                level,
+                column: 0,
+                row: 0,
+            });
+        };
+        let (image_info, decode_bytes) =
+            estimate_decode(&self.config, image_path, (info.width, info.height))
+                .map_err(estimate_error)?;
+
+        let img = {
+            let _reservation = self.memory.reserve(decode_bytes);
+            let limits = self.config.decode_limits();
+            let img = if level == pyramid.max_level {
+                open_image_with_limits(image_path, &limits)
+            } else {
+                open_image_to_fit_with_limits(image_path, info.width, info.height, &limits)
+            }
+            .map_err(|e| match e {
+                image::ImageError::Limits(_) => TileError::ImageTooLarge {
+                    path: image_path.to_string(),
+                    width: image_info.width,
+                    height: image_info.height,
+                },
+                e => decode_error(image_path, e),
+            })?;
+            if img.dimensions() == (info.width, info.height) {
+                img
+            } else {
+                img.resize_exact(info.width, info.height, FilterType::CatmullRom)
+            }
+        };
+        let extension = if img.color().has_alpha() {
+            "png"
+        } else {
+            "jpg"
+        };
+        write_level(image_dir, pyramid, level, &img, extension)?;
+        Ok(extension.to_string())
+    }
+}
+
+/// レベルの全タイルを保存する
+fn write_level(
+    image_dir: &Path,
+    pyramid: &TilePyramid,
+    level: u32,
+    img: &DynamicImage,
+    extension: &str,
+) -> Result<()> {
+    let level_dir = image_dir.join(level.to_string());
+    // ソース画像が変更された場合などの古いタイルを消してから保存する
+    if level_dir.exists() {
+        std::fs::remove_dir_all(&level_dir)?;
+    }
+    std::fs::create_dir_all(&level_dir)?;
+
+    let format = if extension == "png" {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    };
+    let Some(info) = pyramid.level(level) else {
+        return Ok(());
+    };
+    for row in 0..info.rows {
+        for column in 0..info.columns {
+            let Some((x, y, width, height)) = pyramid.tile_rect(level, column, row) else {
+                continue;
+            };
+            let tile = img.crop_imm(x, y, width, height);
+            // JPEG はアルファを保存できないため RGB にする
+            let tile = match format {
+                ImageFormat::Jpeg => DynamicImage::ImageRgb8(tile.to_rgb8()),
+                _ => tile,
+            };
+            let path = tile_path(image_dir, level, column, row, extension);
+            tile.save_with_format(&path, format).map_err(|e| {
+                TileError::SaveError(format!("Failed to save tile to {:?}: {}", path, e))
+            })?;
+        }
+    }
+
+    std::fs::write(level_dir.join(COMPLETE_MARKER), extension)?;
+    Ok(())
+}
+
+/// レベルの全タイルが保存済みで、ソース画像より新しい場合はタイルの拡張子を返す
+fn completed_extension(image_path: &str, image_dir: &Path, level: u32) -> Option<String> {
+    let marker = image_dir.join(level.to_string()).join(COMPLETE_MARKER);
+    let marker_modified = std::fs::metadata(&marker).and_then(|m| m.modified()).ok()?;
+    let source_modified = std::fs::metadata(image_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    if marker_modified < source_modified {
+        return None;
+    }
+    std::fs::read_to_string(marker).ok()
+}
+
+fn tile_path(image_dir: &Path, level: u32, column: u32, row: u32, extension: &str) -> PathBuf {
+    image_dir
+        .join(level.to_string())
+        .join(format!("{}_{}.{}", column, row, extension))
+}
+
+fn decode_error(image_path: &str, e: image::ImageError) -> TileError {
+    TileError::DecodeError(format!("Failed to open image {}: {}", image_path, e))
+}
+
+/// デコード前の見積もりのエラーを変換する
+fn estimate_error(e: ThumbnailError) -> TileError {
+    match e {
+        ThumbnailError::ImageTooLarge {
+            path,
+            width,
+            height,
+        } => TileError::ImageTooLarge {
+            path,
+            width,
+            height,
+        },
+        e => TileError::DecodeError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::test_helpers::{create_test_tile_service, TempTestDir};
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    /// 左半分が赤、右半分が青の画像を作成する
+    fn create_split_image(temp: &TempTestDir, name: &str, (width, height): (u32, u32)) -> String {
+        let path = temp.path().join(name);
+        RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        })
+        .save(&path)
+        .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_get_tile_at_full_resolution() {
+        let (service, temp) = create_test_tile_service(ThumbnailConfig::default());
+        let image_path = create_split_image(&temp, "scan.png", (1000, 600));
+
+        let first = service.get_tile(&image_path, 10, 0, 0).unwrap();
+        assert_eq!(image::image_dimensions(&first).unwrap(), (256, 256));
+        assert!(image::open(&first).unwrap().to_rgb8().get_pixel(10, 10)[0] > 200);
+
+        // 右下のタイルは端までの大きさで、右半分の色になる
+        let last = service.get_tile(&image_path, 10, 3, 2).unwrap();
+        assert_eq!(image::image_dimensions(&last).unwrap(), (232, 88));
+        assert!(image::open(&last).unwrap().to_rgb8().get_pixel(10, 10)[2] > 200);
+        assert_eq!(last.extension().unwrap(), "jpg");
+    }
+
+    #[test]
+    fn test_get_tile_generates_only_requested_level() {
+        let (service, temp) = create_test_tile_service(ThumbnailConfig::default());
+        let image_path = create_split_image(&temp, "scan.png", (1000, 600));
+
+        let tile = service.get_tile(&image_path, 9, 1, 1).unwrap();
+        assert_eq!(image::image_dimensions(&tile).unwrap(), (244, 44));
+
+        // 要求したレベルだけが生成される
+        let image_dir = temp.path().join("tiles").join(hash_path(&image_path));
+        assert!(completed_extension(&image_path, &image_dir, 9).is_some());
+        for level in (0..=8).chain([10]) {
+            assert!(completed_extension(&image_path, &image_dir, level).is_none());
+        }
+        let smallest = service.get_tile(&image_path, 0, 0, 0).unwrap();
+        assert_eq!(image::image_dimensions(&smallest).unwrap(), (1, 1));
+
+        // 生成が終わった画像のロックは残らない
+        assert!(service.locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_regenerates_tiles_when_source_changes() {
+        let (service, temp) = create_test_tile_service(ThumbnailConfig::default());
+        let image_path = create_split_image(&temp, "scan.png", (300, 300));
+
+        let tile = service.get_tile(&image_path, 9, 0, 0).unwrap();
+        assert!(image::open(&tile).unwrap().to_rgb8().get_pixel(10, 10)[0] > 200);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        RgbImage::from_pixel(300, 300, Rgb([0, 255, 0]))
+            .save(&image_path)
+            .unwrap();
+        let tile = service.get_tile(&image_path, 9, 0, 0).unwrap();
+        assert!(image::open(&tile).unwrap().to_rgb8().get_pixel(10, 10)[1] > 200);
+    }
+
+    #[test]
+    fn test_transparent_image_uses_png_tiles() {
+        let (service, temp) = create_test_tile_service(ThumbnailConfig::default());
+        let image_path = temp.path().join("icon.png");
+        RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 0]))
+            .save(&image_path)
+            .unwrap();
+
+        let tile = service
+            .get_tile(image_path.to_str().unwrap(), 7, 0, 0)
+            .unwrap();
+        assert_eq!(tile.extension().unwrap(), "png");
+        assert_eq!(image::open(&tile).unwrap().to_rgba8().get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (service, temp) = create_test_tile_service(ThumbnailConfig::default());
+        let image_path = create_split_image(&temp, "scan.png", (1000, 600));
+
+        assert!(matches!(
+            service.get_tile(&image_path, 10, 4, 0),
+            Err(TileError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            service.get_tile(&image_path, 11, 0, 0),
+            Err(TileError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            service.get_pyramid("/nonexistent/scan.png"),
+            Err(TileError::ImageNotFound(_))
+        ));
+        assert!(matches!(
+            TileService::new(ThumbnailConfig::default(), temp.path().join("tiles"), 16),
+            Err(TileError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_shared_memory_budget() {
+        let temp = TempTestDir::new_random();
+        let memory = Arc::new(MemoryBudget::new(1024 * 1024));
+        let service = TileService::with_memory_budget(
+            ThumbnailConfig::default(),
+            temp.path().join("tiles"),
+            DEFAULT_TILE_SIZE,
+            Arc::clone(&memory),
+        )
+        .unwrap();
+        let image_path = create_split_image(&temp, "scan.png", (300, 300));
+
+        // 他のサービスが予算を使い切っている間はデコードを待つ
+        let reservation = memory.reserve(1024 * 1024);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            sender.send(service.get_tile(&image_path, 9, 0, 0)).unwrap();
+        });
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_millis(200))
+            .is_err());
+
+        drop(reservation);
+        let tile = receiver
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap();
+        assert!(tile.is_ok());
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_tile_cache_is_pruned() {
+        let config = ThumbnailConfig {
+            max_tile_cache_size: 1,
+            ..ThumbnailConfig::default()
+        };
+        let (service, temp) = create_test_tile_service(config);
+        let first = create_split_image(&temp, "first.png", (300, 200));
+        let second = create_split_image(&temp, "second.png", (300, 200));
+
+        let first_tile = service.get_tile(&first, 9, 0, 0).unwrap();
+        assert!(first_tile.exists());
+        // 上限を超えると、生成したばかりの画像以外のタイルは削除される
+        let second_tile = service.get_tile(&second, 9, 0, 0).unwrap();
+        assert!(second_tile.exists());
+        assert!(!temp.path().join("tiles").join(hash_path(&first)).exists());
+
+        // 削除されたタイルは要求されれば作り直す
+        assert!(service.get_tile(&first, 9, 0, 0).unwrap().exists());
+    }
+
+    #[test]
+    fn test_image_too_large_error() {
+        let config = ThumbnailConfig {
+            max_decode_pixels: 1000,
+            ..ThumbnailConfig::default()
+        };
+        let (service, temp) = create_test_tile_service(config);
+        let image_path = create_split_image(&temp, "scan.png", (50, 40));
+
+        match service.get_tile(&image_path, 6, 0, 0) {
+            Err(TileError::ImageTooLarge { width, height, .. }) => {
+                assert_eq!((width, height), (50, 40));
+            }
+            other => panic!("Expected ImageTooLarge, got: {:?}", other),
+        }
+        assert!(service.locks.lock().unwrap().is_empty());
+    }
+}
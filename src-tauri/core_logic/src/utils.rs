@@ -1,6 +1,10 @@
 // サムネイル生成のユーティリティ関数
 
-use std::path::Path;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::time::SystemTime;
 
 use blake3;
 
@@ -16,6 +20,148 @@ pub fn hash_path<P: AsRef<Path>>(path: &P) -> String {
     hash.to_hex().to_string()
 }
 
+/// 数字部分を数値として比較する自然順の比較（大文字小文字は区別しない）
+///
+/// `page2.jpg` < `page10.jpg` のように、ファイル名を人間にとって自然な順序で並べる。
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let ordering = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let a_digits = take_digits(&mut a_chars);
+                let b_digits = take_digits(&mut b_chars);
+                compare_digits(&a_digits, &b_digits)
+            }
+            (Some(&ca), Some(&cb)) => {
+                a_chars.next();
+                b_chars.next();
+                ca.to_lowercase().cmp(cb.to_lowercase())
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // "01" と "1" のように自然順で等しい場合も順序が一意になるようにする
+    a.cmp(b)
+}
+
+/// パスのファイル名部分を自然順で比較
+pub fn natural_cmp_file_name<P: AsRef<Path>>(a: P, b: P) -> Ordering {
+    let a_name = a.as_ref().file_name().unwrap_or_default().to_string_lossy();
+    let b_name = b.as_ref().file_name().unwrap_or_default().to_string_lossy();
+    natural_cmp(&a_name, &b_name)
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// 数字列を数値として比較（桁数に上限はない）
+fn compare_digits(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// 値を JSON として保存する
+///
+/// 書き込み途中で中断しても既存のファイルが壊れないよう、一時ファイル経由で置き換える。
+pub fn write_json_atomic<T: serde::Serialize + ?Sized>(
+    file_path: &Path,
+    value: &T,
+) -> std::io::Result<()> {
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let temp_path = file_path.with_extension("json.tmp");
+    std::fs::write(&temp_path, json)?;
+    std::fs::rename(&temp_path, file_path)
+}
+
+/// キャッシュディレクトリの合計サイズが `max_size` を超えていれば、古いエントリから削除する
+///
+/// ディレクトリ直下のファイル・ディレクトリを1つのエントリとし、
+/// エントリ内で最も新しい更新日時が古い順に、合計サイズが `max_size` 以下になるまで削除する。
+/// `in_use` が true を返すエントリ（生成中など）は削除せず、サイズだけ数える。
+///
+/// # Returns
+/// 削除したエントリ数
+pub fn prune_cache_dir<F>(dir: &Path, max_size: u64, in_use: F) -> std::io::Result<usize>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let (size, modified) = entry_usage(&path);
+        entries.push((modified, size, path));
+    }
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    if total <= max_size {
+        return Ok(0);
+    }
+
+    entries.sort();
+    let mut removed = 0;
+    for (_, size, path) in entries {
+        if total <= max_size {
+            break;
+        }
+        if in_use(&path) {
+            continue;
+        }
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => {
+                total = total.saturating_sub(size);
+                removed += 1;
+            }
+            Err(e) => log::warn!("Failed to remove cache entry {}: {}", path.display(), e),
+        }
+    }
+    Ok(removed)
+}
+
+/// エントリの合計サイズと最も新しい更新日時（ディレクトリは中のファイルをたどる）
+fn entry_usage(path: &Path) -> (u64, SystemTime) {
+    let mut size = 0;
+    let mut modified = SystemTime::UNIX_EPOCH;
+    let mut pending: Vec<PathBuf> = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(children) = std::fs::read_dir(&path) {
+                pending.extend(children.flatten().map(|child| child.path()));
+            }
+        } else {
+            size += metadata.len();
+            if let Ok(time) = metadata.modified() {
+                modified = modified.max(time);
+            }
+        }
+    }
+    (size, modified)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,4 +186,83 @@ mod tests {
             "Different paths should produce different hashes"
         );
     }
+
+    #[test]
+    fn test_natural_cmp_orders_numbers_numerically() {
+        let mut names = vec!["page10.jpg", "page2.jpg", "page1.jpg", "Page3.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["page1.jpg", "page2.jpg", "Page3.jpg", "page10.jpg"]
+        );
+    }
+
+    #[test]
+    fn test_natural_cmp_handles_leading_zeros_and_prefixes() {
+        assert_eq!(natural_cmp("001.jpg", "2.jpg"), Ordering::Less);
+        assert_eq!(natural_cmp("a.jpg", "a1.jpg"), Ordering::Less);
+        assert_eq!(natural_cmp("b", "A"), Ordering::Greater);
+        // 自然順で等しくても順序は一意
+        assert_ne!(natural_cmp("01.jpg", "1.jpg"), Ordering::Equal);
+        assert_eq!(natural_cmp("1.jpg", "1.jpg"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_natural_cmp_file_name_ignores_directories() {
+        assert_eq!(
+            natural_cmp_file_name("/z/page2.jpg", "/a/page10.jpg"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_write_json_atomic_replaces_file() {
+        let temp = crate::test_helper::test_helpers::TempTestDir::new_random();
+        let file_path = temp.path().join("settings").join("values.json");
+
+        write_json_atomic(&file_path, &vec![1, 2]).unwrap();
+        write_json_atomic(&file_path, &vec![3]).unwrap();
+
+        let saved: Vec<i32> = serde_json::from_slice(&std::fs::read(&file_path).unwrap()).unwrap();
+        assert_eq!(saved, vec![3]);
+        assert!(!file_path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_prune_cache_dir_removes_oldest_entries() {
+        let temp = crate::test_helper::test_helpers::TempTestDir::new_random();
+        let dir = temp.path();
+        let write = |name: &str, age_secs: u64| {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, [0; 100]).unwrap();
+            let modified = SystemTime::now() - std::time::Duration::from_secs(age_secs);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("oldest.jpg", 300);
+        write("busy.jpg", 250);
+        write("tiles/0/0_0.jpg", 1000);
+        write("tiles/1/0_0.jpg", 10);
+        write("newest.jpg", 0);
+
+        // 上限内なら削除しない
+        assert_eq!(prune_cache_dir(dir, 500, |_| false).unwrap(), 0);
+
+        // 古い順に上限まで削除する（ディレクトリは中のファイルの最も新しい更新日時で比べる）
+        assert_eq!(prune_cache_dir(dir, 400, |_| false).unwrap(), 1);
+        assert!(!dir.join("oldest.jpg").exists());
+        assert!(dir.join("tiles").exists());
+
+        // 生成中のエントリは削除しない
+        let removed = prune_cache_dir(dir, 150, |path| path.ends_with("busy.jpg")).unwrap();
+        assert_eq!(removed, 2);
+        assert!(dir.join("busy.jpg").exists());
+        assert!(!dir.join("tiles").exists());
+        assert!(!dir.join("newest.jpg").exists());
+    }
 }
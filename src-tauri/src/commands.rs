@@ -1,2 +1,6 @@
+pub mod format;
 pub mod fs;
+pub mod image;
+pub mod rendition;
 pub mod thumbnail;
+pub mod tile;
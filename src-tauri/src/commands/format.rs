@@ -0,0 +1,46 @@
+// 対応するファイル形式の設定を扱うTauriコマンド
+//
+// コアロジックは core_logic に実装し、このファイルは IPC 向けの薄いラッパーのみを担当する。
+
+use core_logic::format::{self, FormatEntry, FormatRegistry};
+use tauri::command;
+use tauri_plugin_log::log;
+
+use crate::utils::get_formats_path;
+
+/// 保存された形式の設定を読み込み、レジストリに反映する（起動時に呼ぶ）
+///
+/// 読み込みに失敗しても組み込みの形式でアプリを起動させる
+pub fn load_format_registry(app_handle: &tauri::AppHandle) {
+    let registry = get_formats_path(app_handle).and_then(FormatRegistry::load);
+    match registry {
+        Ok(registry) => format::configure(registry),
+        Err(e) => log::error!("Failed to load format registry: {}", e),
+    }
+}
+
+/// 対応するファイル形式の一覧を取得する
+#[command]
+pub fn get_supported_formats() -> Vec<FormatEntry> {
+    format::registry().entries().cloned().collect()
+}
+
+/// 対応するファイル形式を設定し、保存する
+///
+/// 以降のフォルダ一覧・兄弟コンテナの検出・サムネイル生成に反映される
+#[command]
+pub async fn set_supported_formats(
+    entries: Vec<FormatEntry>,
+    app_handle: tauri::AppHandle,
+) -> std::result::Result<Vec<FormatEntry>, String> {
+    tokio::task::spawn_blocking(move || {
+        let registry = FormatRegistry::from_entries(entries)?;
+        let formats_path = get_formats_path(&app_handle).map_err(|e| e.to_string())?;
+        registry.save(&formats_path).map_err(|e| e.to_string())?;
+        let entries = registry.entries().cloned().collect();
+        format::configure(registry);
+        Ok(entries)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
@@ -1,4 +1,6 @@
+use core_logic::get_container_metadata as core_get_container_metadata;
 use core_logic::get_sibling_containers as core_get_sibling_containers;
+use core_logic::image_container::metadata::ContainerMetadata;
 use core_logic::list_images_in_container as core_list_images_in_container;
 use core_logic::CommandError;
 use tauri::command;
@@ -29,3 +31,18 @@ pub async fn get_sibling_containers(container_path: String) -> Result<Vec<String
         .await
         .map_err(|e| CommandError::Io(format!("Task join error: {}", e)))?
 }
+
+/// Gets metadata (title, authors, page progression) of a container. (Wrapper for core logic)
+///
+/// 非同期実行でUIブロッキングを防止（tokio::spawn_blockingでファイルシステム操作を別スレッドで実行）
+#[command]
+pub async fn get_container_metadata(
+    container_path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<ContainerMetadata, CommandError> {
+    tokio::task::spawn_blocking(move || {
+        core_get_container_metadata(container_path, get_archive_cache_dir(&app_handle)?)
+    })
+    .await
+    .map_err(|e| CommandError::Io(format!("Task join error: {}", e)))?
+}
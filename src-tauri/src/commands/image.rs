@@ -0,0 +1,53 @@
+// 画像そのものの情報を取得するためのTauriコマンド
+//
+// コアロジックは core_logic に実装し、このファイルは IPC 向けの薄いラッパーのみを担当する。
+
+use core_logic::animation::{read_animation_info, AnimationInfo};
+use core_logic::decode::{read_image_info, write_raw_preview, ImageInfo};
+use core_logic::metadata::{read_image_metadata, ImageMetadata};
+use tauri::command;
+
+use crate::utils::get_raw_preview_cache_dir;
+
+/// 画像のフレーム数と各フレームの表示時間を取得する（アニメーション再生用）
+///
+/// アニメーションでない画像は1フレームとして返す
+#[command]
+pub async fn get_animation_info(image_path: String) -> std::result::Result<AnimationInfo, String> {
+    tokio::task::spawn_blocking(move || read_animation_info(&image_path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 画像の表示上の寸法と EXIF の向きを取得する（ページを正しい向きで表示するため）
+#[command]
+pub async fn get_image_info(image_path: String) -> std::result::Result<ImageInfo, String> {
+    tokio::task::spawn_blocking(move || read_image_info(&image_path).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 画像の EXIF / XMP / IPTC のメタデータ（撮影情報・撮影場所・キャプションなど）を取得する
+///
+/// アーカイブ内の画像は、`list_images_in_container` が返す展開先のパスを指定する
+#[command]
+pub async fn get_image_metadata(image_path: String) -> std::result::Result<ImageMetadata, String> {
+    tokio::task::spawn_blocking(move || read_image_metadata(&image_path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// カメラ RAW に埋め込まれた JPEG プレビューを書き出し、そのパスを返す（表示用）
+#[command]
+pub async fn get_raw_preview(
+    image_path: String,
+    app_handle: tauri::AppHandle,
+) -> std::result::Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let cache_dir = get_raw_preview_cache_dir(&app_handle).map_err(|e| e.to_string())?;
+        let preview_path = write_raw_preview(&image_path, cache_dir).map_err(|e| e.to_string())?;
+        Ok(preview_path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
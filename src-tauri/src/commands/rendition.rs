@@ -0,0 +1,91 @@
+// 表示用レンディション（表示領域に合わせて縮小したページ画像）のTauriコマンド
+//
+// コアロジックは core_logic::thumbnail::rendition にある。
+// ビューアは表示中のページをレンディションで表示し、ページ送りのたびに前後のページをプリフェッチする。
+
+use core_logic::thumbnail::{JobId, MemoryBudget, RenditionService, ThumbnailConfig, Viewport};
+use std::sync::Arc;
+use tauri::{command, State};
+use tauri_plugin_log::log;
+
+use crate::utils::{get_archive_cache_dir, get_rendition_cache_dir};
+
+/// レンディションのプリフェッチジョブのグループ
+/// 新しいプリフェッチは実行中の古いプリフェッチを置き換える
+const RENDITION_PREFETCH_JOB_GROUP: &str = "rendition-prefetch";
+
+/// アプリ全体で共有する RenditionService を作成する（アプリ起動時に呼ぶ）
+///
+/// `memory` はサムネイル・タイルと共有するデコードのメモリ予算
+pub fn create_rendition_service(
+    app_handle: &tauri::AppHandle,
+    memory: Arc<MemoryBudget>,
+) -> std::result::Result<RenditionService, Box<dyn std::error::Error>> {
+    let rendition_cache_dir = get_rendition_cache_dir(app_handle)?;
+    let archive_cache_dir = get_archive_cache_dir(app_handle)?;
+    Ok(RenditionService::with_memory_budget(
+        ThumbnailConfig::default(),
+        rendition_cache_dir,
+        archive_cache_dir,
+        memory,
+    )?)
+}
+
+/// 画像を表示領域に収まる大きさに縮小したレンディションを取得する
+///
+/// レンディションのキャッシュパスを返す
+#[command]
+pub async fn get_display_rendition(
+    image_path: String,
+    viewport: Viewport,
+    service: State<'_, RenditionService>,
+) -> std::result::Result<String, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .get_display_rendition(&image_path, viewport)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 表示中のページの前後 `count` ページのレンディションをプリフェッチする
+///
+/// ジョブIDを即座に返し、生成はバックグラウンドで行う（結果は `get_display_rendition` で取得する）。
+/// 新しいプリフェッチを開始すると、実行中の古いプリフェッチはキャンセルされる。
+#[command]
+pub async fn prefetch_display_renditions(
+    container_path: String,
+    current_index: usize,
+    count: usize,
+    viewport: Viewport,
+    service: State<'_, RenditionService>,
+) -> std::result::Result<JobId, String> {
+    let service = service.inner().clone();
+    let (job_id, token) = service.jobs().start(Some(RENDITION_PREFETCH_JOB_GROUP));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        match service.prefetch_display_renditions(
+            &container_path,
+            current_index,
+            count,
+            viewport,
+            &token,
+        ) {
+            Ok(results) => {
+                for result in results.iter().filter(|r| r.error.is_some()) {
+                    log::warn!(
+                        "Failed to prefetch rendition for {}: {}",
+                        result.image_path,
+                        result.error.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+            Err(e) => log::error!("Failed to prefetch renditions: {}", e),
+        }
+        service.jobs().finish(job_id);
+    });
+
+    Ok(job_id)
+}
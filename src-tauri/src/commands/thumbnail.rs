@@ -2,100 +2,185 @@
 //
 // コアロジックは core_logic::thumbnail に移動済み。
 // このファイルは Tauri コマンドの薄いラッパーとして、以下のみを担当:
-// 1. AppHandle → PathBuf の変換（get_cache_dir）
+// 1. 管理状態の ThumbnailService の取得（アプリ起動時に1度だけ作成）
 // 2. core_logic::thumbnail::* の呼び出し
-// 3. 結果の Tauri IPC 向けシリアライズ
+// 3. 結果の Tauri IPC 向けシリアライズ・イベント発行
 
-use core_logic::thumbnail::folder;
 use core_logic::thumbnail::{
-    BatchTask, BatchThumbnailGenerator, FolderThumbnailResult, ThumbnailGenerator,
+    CoverOverrides, FolderThumbnailResult, JobId, MemoryBudget, PageRange, PageThumbnailResult,
+    TaskPriority, ThumbnailConfig, ThumbnailJobFinished, ThumbnailJobProgress, ThumbnailService,
 };
-use tauri::command;
+use std::sync::Arc;
+use tauri::{command, Emitter, State};
 use tauri_plugin_log::log;
 
-use crate::utils::{get_archive_cache_dir, get_thumbnail_cache_dir};
+use crate::utils::{get_archive_cache_dir, get_cover_overrides_path, get_thumbnail_cache_dir};
+
+/// サムネイル1件の生成完了時に発行するイベント名
+pub const THUMBNAIL_JOB_PROGRESS_EVENT: &str = "thumbnail-job-progress";
+
+/// サムネイル生成ジョブの終了時に発行するイベント名
+pub const THUMBNAIL_JOB_FINISHED_EVENT: &str = "thumbnail-job-finished";
+
+/// フォルダサムネイルのプリフェッチジョブのグループ
+/// 新しいプリフェッチは実行中の古いプリフェッチを置き換える
+const FOLDER_PREFETCH_JOB_GROUP: &str = "folder-prefetch";
+
+/// アプリ全体で共有する ThumbnailService を作成する（アプリ起動時に呼ぶ）
+///
+/// `memory` はタイル・レンディションと共有するデコードのメモリ予算
+pub fn create_thumbnail_service(
+    app_handle: &tauri::AppHandle,
+    memory: Arc<MemoryBudget>,
+) -> std::result::Result<ThumbnailService, Box<dyn std::error::Error>> {
+    let thumbnail_cache_dir = get_thumbnail_cache_dir(app_handle)?;
+    let archive_cache_dir = get_archive_cache_dir(app_handle)?;
+    let cover_overrides_path = get_cover_overrides_path(app_handle)?;
+    // 指定の読み込みに失敗してもアプリは起動させる（次回の指定時に上書きされる）
+    let cover_overrides = CoverOverrides::load(&cover_overrides_path).unwrap_or_else(|e| {
+        log::error!(
+            "Failed to load cover overrides from {:?}: {}",
+            cover_overrides_path,
+            e
+        );
+        CoverOverrides::in_memory()
+    });
+    Ok(ThumbnailService::with_memory_budget(
+        ThumbnailConfig::default(),
+        thumbnail_cache_dir,
+        archive_cache_dir,
+        memory,
+    )?
+    .with_cover_overrides(cover_overrides))
+}
 
 /// フォルダのサムネイルを取得する
 #[command]
 pub async fn get_folder_thumbnail(
     container_path: String,
-    app_handle: tauri::AppHandle,
+    service: State<'_, ThumbnailService>,
 ) -> std::result::Result<Option<FolderThumbnailResult>, String> {
-    let thumbnail_cache_dir = get_thumbnail_cache_dir(&app_handle).map_err(|e| e.to_string())?;
-    let archive_cache_dir = get_archive_cache_dir(&app_handle).map_err(|e| e.to_string())?;
-
-    let result = tokio::task::spawn_blocking(move || {
-        let first_image = folder::get_first_image_in_folder(&container_path, &archive_cache_dir)?;
-        let image_path = match first_image {
-            Some(path) => path,
-            None => return Ok::<Option<FolderThumbnailResult>, String>(None),
-        };
-        let generator = ThumbnailGenerator::with_default_config(thumbnail_cache_dir)
-            .map_err(|e| e.to_string())?;
-        let cache_path = generator
-            .get_or_create_thumbnail(&image_path)
-            .map_err(|e| e.to_string())?;
-        let image_name = std::path::Path::new(&image_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        let thumbnail_path = cache_path
-            .to_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| "Failed to convert thumbnail path to string".to_string())?;
-        Ok(Some(FolderThumbnailResult {
-            image_path,
-            thumbnail_path,
-            image_name,
-        }))
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .get_folder_thumbnail(&container_path)
+            .map_err(|e| e.to_string())
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))??;
-    Ok(result)
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// フォルダのカバー画像を指定する（`image_path` が null の場合は指定を解除）
+///
+/// 新しいカバー画像によるフォルダサムネイルを返す
+#[command]
+pub async fn set_folder_cover(
+    container_path: String,
+    image_path: Option<String>,
+    service: State<'_, ThumbnailService>,
+) -> std::result::Result<Option<FolderThumbnailResult>, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .set_folder_cover(&container_path, image_path)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 任意の画像（コンテナ内のページ）のサムネイルを取得する
+///
+/// サムネイルのキャッシュパスを返す
+#[command]
+pub async fn get_image_thumbnail(
+    image_path: String,
+    service: State<'_, ThumbnailService>,
+) -> std::result::Result<String, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .get_image_thumbnail(&image_path)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// コンテナ内の指定範囲のページのサムネイルをまとめて取得する（ビューアのフィルムストリップ用）
+///
+/// ページはファイル名の自然順で数える。アーカイブにも対応する。
+#[command]
+pub async fn get_container_thumbnails(
+    container_path: String,
+    range: PageRange,
+    service: State<'_, ThumbnailService>,
+) -> std::result::Result<Vec<PageThumbnailResult>, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .get_container_thumbnails(&container_path, range)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
 }
 
 /// 複数フォルダのサムネイルをプリフェッチする
+///
+/// ジョブIDを即座に返し、生成はバックグラウンドで行う。
+/// サムネイル1件ごとに `thumbnail-job-progress`、終了時に `thumbnail-job-finished` イベントを発行する。
+/// 新しいプリフェッチを開始すると、実行中の古いプリフェッチはキャンセルされる。
 #[command]
 pub async fn prefetch_folder_thumbnails(
     folder_paths: Vec<String>,
     app_handle: tauri::AppHandle,
-) -> std::result::Result<(), String> {
-    let thumbnail_cache_dir = get_thumbnail_cache_dir(&app_handle).map_err(|e| e.to_string())?;
-    let archive_cache_dir = get_archive_cache_dir(&app_handle).map_err(|e| e.to_string())?;
-    tokio::task::spawn_blocking(move || {
-        let image_entries: Vec<(usize, String)> = folder_paths
-            .iter()
-            .enumerate()
-            .filter_map(|(index, folder_path)| {
-                folder::get_first_image_in_folder(folder_path, &archive_cache_dir)
-                    .inspect_err(|e| {
-                        log::error!(
-                            "Failed to get first image for folder '{}': {}",
-                            folder_path,
-                            e
-                        );
-                    })
-                    .ok()
-                    .flatten()
-                    .map(|image_path| (index, image_path))
-            })
-            .collect();
-        if image_entries.is_empty() {
-            return Ok(());
+    service: State<'_, ThumbnailService>,
+) -> std::result::Result<JobId, String> {
+    let service = service.inner().clone();
+    let (job_id, token) = service.jobs().start(Some(FOLDER_PREFETCH_JOB_GROUP));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let results = service.prefetch_folder_thumbnails(&folder_paths, &token, |result| {
+            let progress = ThumbnailJobProgress {
+                job_id,
+                result: result.clone(),
+            };
+            if let Err(e) = app_handle.emit(THUMBNAIL_JOB_PROGRESS_EVENT, progress) {
+                log::error!("Failed to emit thumbnail progress: {}", e);
+            }
+        });
+
+        service.jobs().finish(job_id);
+        let finished = ThumbnailJobFinished::from_results(job_id, token.is_cancelled(), &results);
+        if let Err(e) = app_handle.emit(THUMBNAIL_JOB_FINISHED_EVENT, finished) {
+            log::error!("Failed to emit thumbnail job finished: {}", e);
         }
-        let tasks: Vec<BatchTask> = image_entries
-            .into_iter()
-            .map(|(index, image_path)| {
-                let priority = folder::assign_priority(index);
-                BatchTask::new(image_path, priority)
-            })
-            .collect();
-        let batch_generator = BatchThumbnailGenerator::with_default_config(thumbnail_cache_dir)
-            .map_err(|e| e.to_string())?;
-        let _results = batch_generator.batch_create_thumbnails(tasks);
-        Ok(())
+    });
+
+    Ok(job_id)
+}
+
+/// サムネイル生成ジョブをキャンセルする
+///
+/// 実行中のジョブが見つかった場合は true を返す
+#[command]
+pub fn cancel_thumbnail_job(job_id: JobId, service: State<'_, ThumbnailService>) -> bool {
+    service.jobs().cancel(job_id)
+}
+
+/// プリフェッチ待ちのフォルダサムネイルを優先して生成する（画面内にスクロールされた場合など）
+///
+/// 優先度が引き上げられたフォルダ数を返す
+#[command]
+pub async fn bump_folder_thumbnail_priority(
+    folder_paths: Vec<String>,
+    service: State<'_, ThumbnailService>,
+) -> std::result::Result<usize, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service.bump_folder_priority(&folder_paths, TaskPriority::High)
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))?
+    .map_err(|e| format!("Task join error: {}", e))
 }
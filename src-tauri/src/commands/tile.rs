@@ -0,0 +1,61 @@
+// ディープズーム用タイルのTauriコマンド
+//
+// コアロジックは core_logic::tile にある。
+// ビューアはピラミッドの構成を取得し、表示範囲のタイルだけを要求する。
+
+use core_logic::thumbnail::{MemoryBudget, ThumbnailConfig};
+use core_logic::tile::{TilePyramid, TileService, DEFAULT_TILE_SIZE};
+use std::sync::Arc;
+use tauri::{command, State};
+
+use crate::utils::get_tile_cache_dir;
+
+/// アプリ全体で共有する TileService を作成する（アプリ起動時に呼ぶ）
+///
+/// `memory` はサムネイル・レンディションと共有するデコードのメモリ予算
+pub fn create_tile_service(
+    app_handle: &tauri::AppHandle,
+    memory: Arc<MemoryBudget>,
+) -> std::result::Result<TileService, Box<dyn std::error::Error>> {
+    let tile_cache_dir = get_tile_cache_dir(app_handle)?;
+    Ok(TileService::with_memory_budget(
+        ThumbnailConfig::default(),
+        tile_cache_dir,
+        DEFAULT_TILE_SIZE,
+        memory,
+    )?)
+}
+
+/// 画像のタイルピラミッドの構成（レベルごとの大きさとタイル数）を取得する
+#[command]
+pub async fn get_image_tile_pyramid(
+    image_path: String,
+    service: State<'_, TileService>,
+) -> std::result::Result<TilePyramid, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || service.get_pyramid(&image_path).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// 画像のタイルを取得する（未生成のレベルはこのときに生成する）
+///
+/// タイルのキャッシュパスを返す
+#[command]
+pub async fn get_image_tile(
+    image_path: String,
+    level: u32,
+    column: u32,
+    row: u32,
+    service: State<'_, TileService>,
+) -> std::result::Result<String, String> {
+    let service = service.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        service
+            .get_tile(&image_path, level, column, row)
+            .map(|path| path.to_string_lossy().to_string())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
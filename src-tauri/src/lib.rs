@@ -1,8 +1,21 @@
 pub mod commands;
 pub mod tauri_log_config;
 pub mod utils;
-use commands::fs::{get_sibling_containers, list_images_in_container};
-use commands::thumbnail::{get_folder_thumbnail, prefetch_folder_thumbnails};
+use commands::format::{get_supported_formats, load_format_registry, set_supported_formats};
+use commands::fs::{get_container_metadata, get_sibling_containers, list_images_in_container};
+use commands::image::{get_animation_info, get_image_info, get_image_metadata, get_raw_preview};
+use commands::rendition::{
+    create_rendition_service, get_display_rendition, prefetch_display_renditions,
+};
+use commands::thumbnail::{
+    bump_folder_thumbnail_priority, cancel_thumbnail_job, create_thumbnail_service,
+    get_container_thumbnails, get_folder_thumbnail, get_image_thumbnail,
+    prefetch_folder_thumbnails, set_folder_cover,
+};
+use commands::tile::{create_tile_service, get_image_tile, get_image_tile_pyramid};
+use core_logic::thumbnail::{MemoryBudget, ThumbnailConfig};
+use std::sync::Arc;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -12,11 +25,41 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::default().build())
+        .setup(|app| {
+            load_format_registry(app.handle());
+            // サムネイル・タイル・レンディションのデコードは同じメモリ予算で制限する
+            let memory = Arc::new(MemoryBudget::new(
+                ThumbnailConfig::default().decode_memory_budget,
+            ));
+            let thumbnail_service = create_thumbnail_service(app.handle(), Arc::clone(&memory))?;
+            app.manage(thumbnail_service);
+            let tile_service = create_tile_service(app.handle(), Arc::clone(&memory))?;
+            app.manage(tile_service);
+            let rendition_service = create_rendition_service(app.handle(), memory)?;
+            app.manage(rendition_service);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             list_images_in_container,
             get_sibling_containers,
+            get_container_metadata,
             get_folder_thumbnail,
-            prefetch_folder_thumbnails
+            prefetch_folder_thumbnails,
+            cancel_thumbnail_job,
+            bump_folder_thumbnail_priority,
+            get_image_thumbnail,
+            get_container_thumbnails,
+            set_folder_cover,
+            get_image_tile_pyramid,
+            get_image_tile,
+            get_display_rendition,
+            prefetch_display_renditions,
+            get_animation_info,
+            get_image_info,
+            get_image_metadata,
+            get_raw_preview,
+            get_supported_formats,
+            set_supported_formats
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -23,3 +23,65 @@ pub fn get_thumbnail_cache_dir(
     std::fs::create_dir_all(&thumbnail_dir)?;
     Ok(thumbnail_dir)
 }
+
+/// タイルキャッシュディレクトリのパスを取得（Tauri依存）
+pub fn get_tile_cache_dir(app_handle: &tauri::AppHandle) -> std::io::Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let cache_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    let tile_dir = cache_dir.join("tiles");
+    std::fs::create_dir_all(&tile_dir)?;
+    Ok(tile_dir)
+}
+
+/// 表示用レンディションのキャッシュディレクトリのパスを取得（Tauri依存）
+pub fn get_rendition_cache_dir(
+    app_handle: &tauri::AppHandle,
+) -> std::io::Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let cache_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    let rendition_dir = cache_dir.join("renditions");
+    std::fs::create_dir_all(&rendition_dir)?;
+    Ok(rendition_dir)
+}
+
+/// フォルダごとのカバー画像指定の保存先を取得（Tauri依存）
+pub fn get_cover_overrides_path(
+    app_handle: &tauri::AppHandle,
+) -> std::io::Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    Ok(data_dir.join("cover_overrides.json"))
+}
+
+/// 対応するファイル形式の設定の保存先を取得（Tauri依存）
+pub fn get_formats_path(app_handle: &tauri::AppHandle) -> std::io::Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    Ok(data_dir.join("formats.json"))
+}
+
+/// カメラ RAW から取り出した JPEG プレビューの保存先を取得（Tauri依存）
+pub fn get_raw_preview_cache_dir(
+    app_handle: &tauri::AppHandle,
+) -> std::io::Result<std::path::PathBuf> {
+    use tauri::Manager;
+    let cache_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    let preview_dir = cache_dir.join("raw_previews");
+    std::fs::create_dir_all(&preview_dir)?;
+    Ok(preview_dir)
+}
@@ -74,7 +74,14 @@ export interface SidebarProps {
  * - バックエンドが1回のIPCで画像選択・サムネイル生成を完結する新APIの返却型
  */
 export interface FolderThumbnailResult {
+  /** カバーとして選ばれた画像のパス */
   imagePath: string;
   thumbnailPath: string;
   imageName: string;
+  /** カバー画像が選ばれた理由（ユーザー指定・ファイル名・自然順で最初の画像） */
+  coverSource?: 'override' | 'named' | 'firstImage';
+  /** フォルダ内の画像の枚数（モザイクの枚数バッジ用） */
+  imageCount?: number;
+  /** 複数の画像を並べたモザイクサムネイルか */
+  mosaic?: boolean;
 }